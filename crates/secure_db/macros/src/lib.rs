@@ -0,0 +1,174 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+extern crate proc_macro;
+
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{parse_macro_input, spanned::Spanned, Data, DeriveInput, Fields};
+
+/// Derive `secure_db::Storable` for a struct with named fields.
+///
+/// Exactly one field is marked `#[storable(key)]` and becomes the
+/// `unique_id()` of the records. Fields marked `#[storable(index)]` become
+/// secondary indexes named after the field; their type must implement
+/// `secure_db::IndexValue`. At most one field, a `u64` or an `Option<u64>`,
+/// is marked `#[storable(expires_at)]` and becomes the `expires_at()` of the
+/// records. The struct can declare its schema version with
+/// `#[storable(version = N)]`, which defaults to 0.
+///
+/// # Examples
+///
+/// ``` ignore
+/// #[derive(Storable)]
+/// #[storable(version = 1)]
+/// pub struct Wallet {
+///     #[storable(key)]
+///     id: String,
+///     #[storable(index)]
+///     label: String,
+///     entropy: Vec<u8>,
+/// }
+/// ```
+#[proc_macro_derive(Storable, attributes(storable))]
+pub fn derive_storable(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match expand(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+fn expand(input: &DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(syn::Error::new(
+                    input.span(),
+                    "`#[derive(Storable)]` requires a struct with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(syn::Error::new(
+                input.span(),
+                "`#[derive(Storable)]` requires a struct with named fields",
+            ))
+        }
+    };
+
+    let mut version = None;
+    for attr in input
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("storable"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("version") {
+                let value: syn::LitInt = meta.value()?.parse()?;
+                version = Some(value.base10_parse::<u32>()?);
+                Ok(())
+            } else {
+                Err(meta.error("expected `version`"))
+            }
+        })?;
+    }
+    let version = version.unwrap_or(0);
+
+    let mut key = None;
+    let mut indexes = Vec::new();
+    let mut expires_at = None;
+    for field in fields {
+        for attr in field
+            .attrs
+            .iter()
+            .filter(|attr| attr.path().is_ident("storable"))
+        {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("key") {
+                    if key.is_some() {
+                        return Err(meta.error("duplicate `#[storable(key)]` field"));
+                    }
+                    key = Some(field);
+                    Ok(())
+                } else if meta.path.is_ident("index") {
+                    indexes.push(field);
+                    Ok(())
+                } else if meta.path.is_ident("expires_at") {
+                    if expires_at.is_some() {
+                        return Err(meta.error("duplicate `#[storable(expires_at)]` field"));
+                    }
+                    expires_at = Some(field);
+                    Ok(())
+                } else {
+                    Err(meta.error("expected `key`, `index` or `expires_at`"))
+                }
+            })?;
+        }
+    }
+    let key = key.ok_or_else(|| {
+        syn::Error::new(
+            input.span(),
+            "`#[derive(Storable)]` requires a `#[storable(key)]` field",
+        )
+    })?;
+
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let key_ident = &key.ident;
+    let key_ty = &key.ty;
+    let index_idents: Vec<_> = indexes.iter().map(|field| &field.ident).collect();
+    let index_names: Vec<_> = index_idents
+        .iter()
+        .map(|ident| ident.as_ref().map(|ident| ident.to_string()))
+        .collect();
+    let expires_at = expires_at.map(|field| {
+        let ident = &field.ident;
+        quote!(
+            fn expires_at(&self) -> ::core::option::Option<u64> {
+                ::core::convert::Into::<::core::option::Option<u64>>::into(
+                    ::core::clone::Clone::clone(&self.#ident),
+                )
+            }
+        )
+    });
+
+    Ok(quote!(
+        impl #impl_generics ::secure_db::Storable for #ident #ty_generics #where_clause {
+            type Key = #key_ty;
+
+            fn unique_id(&self) -> Self::Key {
+                ::core::clone::Clone::clone(&self.#key_ident)
+            }
+
+            fn schema_version() -> u32 {
+                #version
+            }
+
+            fn index_names() -> &'static [&'static str] {
+                &[#(#index_names),*]
+            }
+
+            fn index_values(&self) -> ::secure_db::__private::Vec<::secure_db::__private::Vec<u8>> {
+                ::secure_db::__private::vec![#(::secure_db::IndexValue::index_value(&self.#index_idents)),*]
+            }
+
+            #expires_at
+        }
+    ))
+}
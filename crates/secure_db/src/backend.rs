@@ -15,40 +15,269 @@
 // specific language governing permissions and limitations
 // under the License.
 
-use anyhow::{anyhow, bail, Result};
+use crate::{sync::RwLock, Error, Result, Storable};
+use alloc::{collections::BTreeMap, format, string::String, sync::Arc, vec, vec::Vec};
+use core::sync::atomic::{AtomicUsize, Ordering};
 use optee_utee::{DataFlag, GenericObject, ObjectStorageConstants, PersistentObject};
 
-// Wrapper functions for OP-TEE raw API
+// StorageBackend is where a SecureStorageDb keeps its objects.
+// OpteeBackend, the default, stores them as OP-TEE persistent objects.
+// MemoryBackend and FaultyBackend let the database be tested on the host:
+// the former keeps the objects in memory, the latter fails the writes from a
+// chosen point on, as if the TA was interrupted there.
+
+pub trait StorageBackend: Clone + Send + Sync + 'static {
+    // Create or overwrite the object.
+    fn save(&self, obj_id: &[u8], data: &[u8]) -> Result<()>;
+    // Return the data of the object, or None if it does not exist.
+    fn load(&self, obj_id: &[u8]) -> Result<Option<Vec<u8>>>;
+    // Delete the object if it exists.
+    fn delete(&self, obj_id: &[u8]) -> Result<()>;
+    // Return the ids of the objects starting with `prefix`.
+    fn list(&self, prefix: &[u8]) -> Result<Vec<Vec<u8>>>;
+}
+
+// OpteeBackend stores the objects of a database in the given storage, the
+// default private storage unless chosen with new(). The records of some
+// tables can be placed in another storage with with_table(), e.g. to keep the
+// bulk of the data in the REE file system and the most sensitive tables in
+// RPMB. The index, the journal and the schema versions always stay in the
+// storage of the database, so the database storage should be the one with the
+// strongest protection:
+//
+//     let backend = OpteeBackend::new(ObjectStorageConstants::PrivateRpmb)
+//         .with_table::<Log>(ObjectStorageConstants::PrivateReeFs);
+//     let db = SecureStorageDb::open_with_backend(name, Bincode, backend)?;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OpteeBackend {
+    storage: ObjectStorageConstants,
+    tables: Vec<(String, ObjectStorageConstants)>,
+}
+
+impl Default for OpteeBackend {
+    fn default() -> Self {
+        Self::new(ObjectStorageConstants::Private)
+    }
+}
+
+impl OpteeBackend {
+    pub fn new(storage: ObjectStorageConstants) -> Self {
+        Self {
+            storage,
+            tables: Vec::new(),
+        }
+    }
+
+    // Store the records of the table of V in `storage`.
+    pub fn with_table<V: Storable>(mut self, storage: ObjectStorageConstants) -> Self {
+        let prefix = V::concat_key("");
+        self.tables.retain(|(table, _)| *table != prefix);
+        self.tables.push((prefix, storage));
+        self
+    }
+
+    // The storage of the object: the one of its table if it was placed, or
+    // else the one of the database.
+    fn storage_of(&self, obj_id: &[u8]) -> ObjectStorageConstants {
+        self.tables
+            .iter()
+            .find(|(prefix, _)| obj_id.starts_with(prefix.as_bytes()))
+            .map_or(self.storage, |(_, storage)| *storage)
+    }
+}
+
+impl StorageBackend for OpteeBackend {
+    fn save(&self, obj_id: &[u8], data: &[u8]) -> Result<()> {
+        save_in_storage(self.storage_of(obj_id), obj_id, data)
+    }
+
+    fn load(&self, obj_id: &[u8]) -> Result<Option<Vec<u8>>> {
+        load_from_storage(self.storage_of(obj_id), obj_id)
+    }
+
+    fn delete(&self, obj_id: &[u8]) -> Result<()> {
+        delete_from_storage_if_exists(self.storage_of(obj_id), obj_id)
+    }
+
+    // Each storage only holds the objects which are placed there.
+    fn list(&self, prefix: &[u8]) -> Result<Vec<Vec<u8>>> {
+        let mut storages = vec![self.storage];
+        for (_, storage) in &self.tables {
+            if !storages.contains(storage) {
+                storages.push(*storage);
+            }
+        }
+        let mut object_ids = Vec::new();
+        for storage in storages {
+            for object in PersistentObject::list(storage)?.with_prefix(prefix) {
+                let (object_id, _info) = object?;
+                if self.storage_of(&object_id) == storage {
+                    object_ids.push(object_id);
+                }
+            }
+        }
+        Ok(object_ids)
+    }
+}
+
+// MemoryBackend keeps the objects in memory. Its clones share the objects,
+// so a database can be reopened on a clone to check what was stored.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryBackend {
+    objects: Arc<RwLock<BTreeMap<Vec<u8>, Vec<u8>>>>,
+}
+
+impl MemoryBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl StorageBackend for MemoryBackend {
+    fn save(&self, obj_id: &[u8], data: &[u8]) -> Result<()> {
+        self.objects.write()?.insert(obj_id.to_vec(), data.to_vec());
+        Ok(())
+    }
+
+    fn load(&self, obj_id: &[u8]) -> Result<Option<Vec<u8>>> {
+        Ok(self.objects.read()?.get(obj_id).cloned())
+    }
+
+    fn delete(&self, obj_id: &[u8]) -> Result<()> {
+        self.objects.write()?.remove(obj_id);
+        Ok(())
+    }
+
+    fn list(&self, prefix: &[u8]) -> Result<Vec<Vec<u8>>> {
+        Ok(self
+            .objects
+            .read()?
+            .keys()
+            .filter(|obj_id| obj_id.starts_with(prefix))
+            .cloned()
+            .collect())
+    }
+}
+
+// FaultyBackend forwards to another backend, and fails every write, save or
+// delete, once a given number of writes has succeeded. Count the writes of an
+// operation with writes(), then replay it with fail_after() set to each
+// smaller count to check that the database recovers from an interruption at
+// any point. Its clones share the counters.
+#[derive(Debug, Clone)]
+pub struct FaultyBackend<B> {
+    inner: B,
+    writes: Arc<AtomicUsize>,
+    fail_after: Arc<AtomicUsize>,
+}
+
+impl<B: StorageBackend> FaultyBackend<B> {
+    pub fn new(inner: B) -> Self {
+        Self {
+            inner,
+            writes: Arc::new(AtomicUsize::new(0)),
+            fail_after: Arc::new(AtomicUsize::new(usize::MAX)),
+        }
+    }
+
+    // Fail the writes once `writes` more writes have succeeded.
+    pub fn fail_after(&self, writes: usize) {
+        let writes = self.writes().saturating_add(writes);
+        self.fail_after.store(writes, Ordering::SeqCst);
+    }
+
+    // Stop failing the writes.
+    pub fn heal(&self) {
+        self.fail_after.store(usize::MAX, Ordering::SeqCst);
+    }
+
+    // The number of writes which succeeded so far.
+    pub fn writes(&self) -> usize {
+        self.writes.load(Ordering::SeqCst)
+    }
+
+    pub fn inner(&self) -> &B {
+        &self.inner
+    }
+
+    fn write(&self, write: impl FnOnce(&B) -> Result<()>) -> Result<()> {
+        if self.writes() >= self.fail_after.load(Ordering::SeqCst) {
+            return Err(Error::Backend(String::from("injected write failure")));
+        }
+        write(&self.inner)?;
+        self.writes.fetch_add(1, Ordering::SeqCst);
+        Ok(())
+    }
+}
+
+impl<B: StorageBackend> StorageBackend for FaultyBackend<B> {
+    fn save(&self, obj_id: &[u8], data: &[u8]) -> Result<()> {
+        self.write(|inner| inner.save(obj_id, data))
+    }
+
+    fn load(&self, obj_id: &[u8]) -> Result<Option<Vec<u8>>> {
+        self.inner.load(obj_id)
+    }
+
+    fn delete(&self, obj_id: &[u8]) -> Result<()> {
+        self.write(|inner| inner.delete(obj_id))
+    }
+
+    fn list(&self, prefix: &[u8]) -> Result<Vec<Vec<u8>>> {
+        self.inner.list(prefix)
+    }
+}
+
+// Wrapper functions for OP-TEE raw API, in the default private storage
 
 pub fn save_in_secure_storage(obj_id: &[u8], data: &[u8]) -> Result<()> {
+    save_in_storage(ObjectStorageConstants::Private, obj_id, data)
+}
+
+pub fn load_from_secure_storage(obj_id: &[u8]) -> Result<Option<Vec<u8>>> {
+    load_from_storage(ObjectStorageConstants::Private, obj_id)
+}
+
+pub fn delete_from_secure_storage(obj_id: &[u8]) -> Result<()> {
+    match PersistentObject::open(
+        ObjectStorageConstants::Private,
+        obj_id,
+        DataFlag::ACCESS_READ | DataFlag::ACCESS_WRITE_META,
+    ) {
+        Err(e) => Err(e.into()),
+
+        Ok(object) => {
+            object.close_and_delete()?;
+            Ok(())
+        }
+    }
+}
+
+pub fn delete_from_secure_storage_if_exists(obj_id: &[u8]) -> Result<()> {
+    delete_from_storage_if_exists(ObjectStorageConstants::Private, obj_id)
+}
+
+fn save_in_storage(storage: ObjectStorageConstants, obj_id: &[u8], data: &[u8]) -> Result<()> {
     let obj_data_flag = DataFlag::ACCESS_READ
         | DataFlag::ACCESS_WRITE
         | DataFlag::ACCESS_WRITE_META
         | DataFlag::OVERWRITE;
 
-    PersistentObject::create(
-        ObjectStorageConstants::Private,
-        obj_id,
-        obj_data_flag,
-        None,
-        data,
-    )
-    .map_err(|e| anyhow!("[-] {:?}: failed to create object: {:?}", &obj_id, e))?;
+    PersistentObject::create(storage, obj_id, obj_data_flag, None, data)?;
 
     Ok(())
 }
 
-pub fn load_from_secure_storage(obj_id: &[u8]) -> Result<Option<Vec<u8>>> {
+fn load_from_storage(storage: ObjectStorageConstants, obj_id: &[u8]) -> Result<Option<Vec<u8>>> {
     match PersistentObject::open(
-        ObjectStorageConstants::Private,
+        storage,
         obj_id,
         DataFlag::ACCESS_READ | DataFlag::SHARE_READ,
     ) {
         Err(e) => match e.kind() {
             optee_utee::ErrorKind::ItemNotFound => Ok(None),
-            _ => {
-                bail!("[-] {:?}: failed to open object: {:?}", &obj_id, e);
-            }
+            _ => Err(e.into()),
         },
 
         Ok(object) => {
@@ -57,7 +286,7 @@ pub fn load_from_secure_storage(obj_id: &[u8]) -> Result<Option<Vec<u8>>> {
 
             let read_bytes = object.read(&mut buf)?;
             if read_bytes != obj_info.data_size() as u32 {
-                bail!("[-] {:?}: failed to read data", &obj_id);
+                return Err(Error::Backend(format!("{:?}: failed to read data", obj_id)));
             }
 
             Ok(Some(buf))
@@ -65,15 +294,16 @@ pub fn load_from_secure_storage(obj_id: &[u8]) -> Result<Option<Vec<u8>>> {
     }
 }
 
-pub fn delete_from_secure_storage(obj_id: &[u8]) -> Result<()> {
+fn delete_from_storage_if_exists(storage: ObjectStorageConstants, obj_id: &[u8]) -> Result<()> {
     match PersistentObject::open(
-        ObjectStorageConstants::Private,
+        storage,
         obj_id,
         DataFlag::ACCESS_READ | DataFlag::ACCESS_WRITE_META,
     ) {
-        Err(e) => {
-            bail!("[-] {:?}: failed to open object: {:?}", &obj_id, e);
-        }
+        Err(e) => match e.kind() {
+            optee_utee::ErrorKind::ItemNotFound => Ok(()),
+            _ => Err(e.into()),
+        },
 
         Ok(object) => {
             object.close_and_delete()?;
@@ -81,3 +311,74 @@ pub fn delete_from_secure_storage(obj_id: &[u8]) -> Result<()> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_memory_backend() {
+        let backend = MemoryBackend::new();
+        backend.save(b"db.a", b"1").unwrap();
+        backend.save(b"db.b", b"2").unwrap();
+        backend.save(b"other", b"3").unwrap();
+        assert_eq!(backend.load(b"db.a").unwrap(), Some(b"1".to_vec()));
+        assert_eq!(backend.clone().load(b"db.b").unwrap(), Some(b"2".to_vec()));
+        assert_eq!(
+            backend.list(b"db.").unwrap(),
+            vec![b"db.a".to_vec(), b"db.b".to_vec()]
+        );
+        backend.delete(b"db.a").unwrap();
+        backend.delete(b"db.a").unwrap();
+        assert_eq!(backend.load(b"db.a").unwrap(), None);
+    }
+
+    struct Seed;
+
+    impl Storable for Seed {
+        type Key = String;
+
+        fn unique_id(&self) -> String {
+            String::new()
+        }
+    }
+
+    #[test]
+    fn test_table_placement() {
+        let backend = OpteeBackend::new(ObjectStorageConstants::PrivateReeFs)
+            .with_table::<Seed>(ObjectStorageConstants::Private)
+            .with_table::<Seed>(ObjectStorageConstants::PrivateRpmb);
+        assert_eq!(
+            backend.storage_of(b"Seed#1"),
+            ObjectStorageConstants::PrivateRpmb
+        );
+        assert_eq!(
+            backend.storage_of(b"Seeds#1"),
+            ObjectStorageConstants::PrivateReeFs
+        );
+        assert_eq!(
+            backend.storage_of(b"db.index"),
+            ObjectStorageConstants::PrivateReeFs
+        );
+        assert_eq!(
+            OpteeBackend::default().storage_of(b"Seed#1"),
+            ObjectStorageConstants::Private
+        );
+    }
+
+    #[test]
+    fn test_faulty_backend() {
+        let backend = FaultyBackend::new(MemoryBackend::new());
+        backend.save(b"a", b"1").unwrap();
+        backend.fail_after(1);
+        backend.save(b"b", b"2").unwrap();
+        assert!(backend.save(b"c", b"3").is_err());
+        assert!(backend.delete(b"a").is_err());
+        assert_eq!(backend.writes(), 2);
+        assert_eq!(backend.load(b"a").unwrap(), Some(b"1".to_vec()));
+        assert_eq!(backend.inner().load(b"c").unwrap(), None);
+        backend.heal();
+        backend.delete(b"a").unwrap();
+        assert_eq!(backend.writes(), 3);
+    }
+}
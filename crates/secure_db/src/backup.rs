@@ -0,0 +1,682 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::{Error, Result};
+use alloc::{string::String, vec, vec::Vec};
+use optee_utee::{
+    AlgorithmId, Asymmetric, AttributeId, AttributeMemref, ErrorKind, GenericObject, OperationMode,
+    Random, TransientObject, TransientObjectType, AE,
+};
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroizing;
+
+// A backup moves the contents of a database to another device, whose secure
+// storage cannot read the objects of this one as they are bound to the
+// hardware unique key of the device. See SecureStorageDb::export_backup() and
+// SecureStorageDb::import_backup().
+//
+// The backup is sealed with a BackupCipher, BackupKey on a device, and laid
+// out as:
+//
+//     "SDBK" | format version: u16, big endian | sealed contents
+//
+// The header is authenticated along with the contents, which are encoded with
+// Postcard whatever the codec of the database. The format version is bumped
+// whenever the layout of the contents changes; import_backup() reads every
+// version up to BACKUP_VERSION.
+
+const MAGIC: &[u8; 4] = b"SDBK";
+pub const BACKUP_VERSION: u16 = 1;
+const HEADER_LEN: usize = MAGIC.len() + 2;
+
+// BackupCipher authenticates and encrypts backups.
+pub trait BackupCipher {
+    // Encrypt `plaintext`, and authenticate it along with `aad`.
+    fn seal(&self, aad: &[u8], plaintext: &[u8]) -> Result<Vec<u8>>;
+    // Return the plaintext of sealed data, or Error::BackupAuthentication if
+    // the data or `aad` were altered, or were sealed with another key.
+    fn open(&self, aad: &[u8], sealed: &[u8]) -> Result<Vec<u8>>;
+}
+
+// BackupKey seals backups with AES-256-GCM. The key is shared by the devices
+// a backup moves between, and is provisioned to each of them wrapped, i.e.
+// encrypted to a key pair of the device with RSA-OAEP, so that it never
+// appears in the clear outside of a TA:
+//
+//     let key = BackupKey::unwrap(
+//         &device_key,
+//         AlgorithmId::RsaesPkcs1OAepMgf1Sha256,
+//         &wrapped_key,
+//     )?;
+//     let backup = db.export_backup(&key, BackupTables::All)?;
+//
+// The wrapped key can be kept in the secure storage to unwrap it again later.
+pub struct BackupKey {
+    key: TransientObject,
+}
+
+impl BackupKey {
+    // Size of the key, in bits.
+    pub const KEY_SIZE: usize = 256;
+    const NONCE_LEN: usize = 12;
+    const TAG_LEN: usize = 16;
+
+    // Decrypt the wrapped key with `unwrapping_key`, a key pair of the device,
+    // and `algorithm`, one of the RSAES algorithms.
+    pub fn unwrap<T: GenericObject>(
+        unwrapping_key: &T,
+        algorithm: AlgorithmId,
+        wrapped: &[u8],
+    ) -> Result<Self> {
+        let key_size = unwrapping_key.info()?.object_size();
+        let operation = Asymmetric::allocate(algorithm, OperationMode::Decrypt, key_size)?;
+        operation.set_key(unwrapping_key)?;
+        let raw_key = Zeroizing::new(operation.decrypt(&[], wrapped)?);
+        if raw_key.len() * 8 != Self::KEY_SIZE {
+            return Err(Error::InvalidArgument("wrapped key is not an AES-256 key"));
+        }
+        let mut key = TransientObject::allocate(TransientObjectType::Aes, Self::KEY_SIZE)?;
+        key.populate(&[AttributeMemref::from_ref(AttributeId::SecretValue, &raw_key).into()])?;
+        Ok(Self { key })
+    }
+
+    fn operation(&self, mode: OperationMode) -> Result<AE> {
+        let operation = AE::allocate(AlgorithmId::AesGcm, mode, Self::KEY_SIZE)?;
+        operation.set_key(&self.key)?;
+        Ok(operation)
+    }
+}
+
+// The sealed data is "nonce | ciphertext | tag", with a random nonce.
+impl BackupCipher for BackupKey {
+    fn seal(&self, aad: &[u8], plaintext: &[u8]) -> Result<Vec<u8>> {
+        let mut nonce = [0u8; Self::NONCE_LEN];
+        Random::generate(&mut nonce);
+        let operation = self.operation(OperationMode::Encrypt)?;
+        operation.init(&nonce, Self::TAG_LEN * 8, aad.len(), plaintext.len())?;
+        operation.update_aad(aad);
+        let mut ciphertext = vec![0u8; plaintext.len()];
+        let mut tag = [0u8; Self::TAG_LEN];
+        let (ciphertext_len, tag_len) =
+            operation.encrypt_final(plaintext, &mut ciphertext, &mut tag)?;
+
+        let mut sealed = nonce.to_vec();
+        sealed.extend_from_slice(&ciphertext[..ciphertext_len]);
+        sealed.extend_from_slice(&tag[..tag_len]);
+        Ok(sealed)
+    }
+
+    fn open(&self, aad: &[u8], sealed: &[u8]) -> Result<Vec<u8>> {
+        if sealed.len() < Self::NONCE_LEN + Self::TAG_LEN {
+            return Err(Error::BackupFormat("truncated backup".into()));
+        }
+        let (nonce, rest) = sealed.split_at(Self::NONCE_LEN);
+        let (ciphertext, tag) = rest.split_at(rest.len() - Self::TAG_LEN);
+        let operation = self.operation(OperationMode::Decrypt)?;
+        operation.init(nonce, Self::TAG_LEN * 8, aad.len(), ciphertext.len())?;
+        operation.update_aad(aad);
+        let mut plaintext = vec![0u8; ciphertext.len()];
+        match operation.decrypt_final(ciphertext, &mut plaintext, tag) {
+            Ok(len) => {
+                plaintext.truncate(len);
+                Ok(plaintext)
+            }
+            Err(e) if e.kind() == ErrorKind::MacInvalid => Err(Error::BackupAuthentication),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+// The tables to export or import. Records whose key is not "$TABLE#$KEY" are
+// in the table named "".
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BackupTables<'a> {
+    All,
+    Only(&'a [&'a str]),
+}
+
+impl BackupTables<'_> {
+    pub(crate) fn contains(&self, table: &str) -> bool {
+        match self {
+            BackupTables::All => true,
+            BackupTables::Only(tables) => tables.contains(&table),
+        }
+    }
+}
+
+// The contents of a backup, at format version 1.
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct BackupContents {
+    // Codec::NAME of the database, which serialized the values.
+    pub(crate) codec: String,
+    pub(crate) tables: Vec<TableBackup>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct TableBackup {
+    pub(crate) name: String,
+    pub(crate) schema_version: u32,
+    pub(crate) records: Vec<RecordBackup>,
+}
+
+// The values may be secrets, such as the seeds of a wallet, so they are
+// zeroized once restored or sealed.
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct RecordBackup {
+    pub(crate) key: String,
+    pub(crate) value: Zeroizing<Vec<u8>>,
+    pub(crate) index_entries: Vec<String>,
+}
+
+pub(crate) fn seal(cipher: &impl BackupCipher, contents: &BackupContents) -> Result<Vec<u8>> {
+    let mut backup = MAGIC.to_vec();
+    backup.extend_from_slice(&BACKUP_VERSION.to_be_bytes());
+    let plaintext = Zeroizing::new(postcard::to_allocvec(contents)?);
+    let sealed = cipher.seal(&backup, &plaintext)?;
+    backup.extend_from_slice(&sealed);
+    Ok(backup)
+}
+
+pub(crate) fn open(cipher: &impl BackupCipher, backup: &[u8]) -> Result<BackupContents> {
+    if backup.len() < HEADER_LEN || !backup.starts_with(MAGIC) {
+        return Err(Error::BackupFormat("not a secure_db backup".into()));
+    }
+    let (header, sealed) = backup.split_at(HEADER_LEN);
+    let version = u16::from_be_bytes([header[MAGIC.len()], header[MAGIC.len() + 1]]);
+    if version == 0 || version > BACKUP_VERSION {
+        return Err(Error::BackupVersion(version));
+    }
+    let plaintext = Zeroizing::new(cipher.open(header, sealed)?);
+    Ok(postcard::from_bytes(&plaintext)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Bincode, MemoryBackend, SecureStorageClient, SecureStorageDb, Storable};
+    use alloc::string::ToString;
+    use core::ffi::c_void;
+    use core::ptr::NonNull;
+    use optee_utee_mock::{
+        crypto::{set_global_crypto_mock, MockCryptoController},
+        object::{set_global_object_mock, MockObjectController, SERIAL_TEST_LOCK},
+        raw,
+    };
+    use std::sync::{Arc, Mutex};
+
+    // Not a cipher: XORs with the key and appends a checksum of the key, the
+    // aad and the data, which is enough to exercise the backup format.
+    struct TestCipher(u8);
+
+    impl TestCipher {
+        fn checksum(&self, aad: &[u8], data: &[u8]) -> [u8; 8] {
+            let mut sum = 0xcbf29ce484222325u64 ^ self.0 as u64;
+            for byte in aad.iter().chain(data) {
+                sum = (sum ^ *byte as u64).wrapping_mul(0x100000001b3);
+            }
+            sum.to_be_bytes()
+        }
+    }
+
+    impl BackupCipher for TestCipher {
+        fn seal(&self, aad: &[u8], plaintext: &[u8]) -> Result<Vec<u8>> {
+            let mut sealed: Vec<u8> = plaintext.iter().map(|byte| byte ^ self.0).collect();
+            let checksum = self.checksum(aad, &sealed);
+            sealed.extend_from_slice(&checksum);
+            Ok(sealed)
+        }
+
+        fn open(&self, aad: &[u8], sealed: &[u8]) -> Result<Vec<u8>> {
+            if sealed.len() < 8 {
+                return Err(Error::BackupFormat("truncated backup".into()));
+            }
+            let (data, checksum) = sealed.split_at(sealed.len() - 8);
+            if self.checksum(aad, data) != checksum {
+                return Err(Error::BackupAuthentication);
+            }
+            Ok(data.iter().map(|byte| byte ^ self.0).collect())
+        }
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Wallet {
+        id: String,
+        owner: String,
+    }
+
+    impl Storable for Wallet {
+        type Key = String;
+
+        fn unique_id(&self) -> String {
+            self.id.clone()
+        }
+
+        fn schema_version() -> u32 {
+            2
+        }
+
+        fn index_names() -> &'static [&'static str] {
+            &["owner"]
+        }
+
+        fn index_values(&self) -> Vec<Vec<u8>> {
+            vec![self.owner.as_bytes().to_vec()]
+        }
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Log {
+        id: String,
+    }
+
+    impl Storable for Log {
+        type Key = String;
+
+        fn unique_id(&self) -> String {
+            self.id.clone()
+        }
+    }
+
+    fn client() -> SecureStorageClient<Bincode, MemoryBackend> {
+        open(&MemoryBackend::new())
+    }
+
+    fn open(backend: &MemoryBackend) -> SecureStorageClient<Bincode, MemoryBackend> {
+        let db =
+            SecureStorageDb::open_with_backend("db".to_string(), Bincode, backend.clone()).unwrap();
+        SecureStorageClient::from_db(db)
+    }
+
+    fn wallet(id: &str, owner: &str) -> Wallet {
+        Wallet {
+            id: id.to_string(),
+            owner: owner.to_string(),
+        }
+    }
+
+    fn log(id: &str) -> Log {
+        Log { id: id.to_string() }
+    }
+
+    fn export(client: &SecureStorageClient<Bincode, MemoryBackend>) -> Vec<u8> {
+        client
+            .export_backup(&TestCipher(7), BackupTables::All)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_backup_round_trip() {
+        let source = client();
+        source.put(&wallet("1", "alice")).unwrap();
+        source.put(&wallet("2", "bob")).unwrap();
+        source.put(&log("1")).unwrap();
+        let backup = export(&source);
+        assert!(backup.starts_with(b"SDBK\0\x01"));
+
+        let target = client();
+        target
+            .import_backup(&TestCipher(7), &backup, BackupTables::All)
+            .unwrap();
+        assert_eq!(
+            target.get::<Wallet>(&"2".to_string()).unwrap(),
+            wallet("2", "bob")
+        );
+        assert_eq!(target.get::<Log>(&"1".to_string()).unwrap(), log("1"));
+        // the secondary indexes and schema versions come along
+        assert_eq!(
+            target.find_by::<Wallet, _>("owner", "alice").unwrap(),
+            vec![wallet("1", "alice")]
+        );
+    }
+
+    #[test]
+    fn test_backup_selected_tables() {
+        let source = client();
+        source.put(&wallet("1", "alice")).unwrap();
+        source.put(&log("1")).unwrap();
+        let backup = export(&source);
+
+        let target = client();
+        target.put(&wallet("3", "carol")).unwrap();
+        target.put(&log("2")).unwrap();
+        target
+            .import_backup(
+                &TestCipher(7),
+                &backup,
+                BackupTables::Only(&[Wallet::table_name()]),
+            )
+            .unwrap();
+        // the restored table is replaced, the others are left as they are
+        assert_eq!(
+            target
+                .list_entries::<Wallet>()
+                .unwrap()
+                .into_values()
+                .collect::<Vec<_>>(),
+            vec![wallet("1", "alice")]
+        );
+        assert!(target
+            .find_by::<Wallet, _>("owner", "carol")
+            .unwrap()
+            .is_empty());
+        assert_eq!(target.list_entries::<Log>().unwrap().len(), 1);
+        assert_eq!(target.get::<Log>(&"2".to_string()).unwrap(), log("2"));
+
+        // only the selected tables are exported
+        let backup = source
+            .export_backup(&TestCipher(7), BackupTables::Only(&[Log::table_name()]))
+            .unwrap();
+        let target = client();
+        target.put(&wallet("3", "carol")).unwrap();
+        assert!(matches!(
+            target.import_backup(
+                &TestCipher(7),
+                &backup,
+                BackupTables::Only(&[Wallet::table_name()])
+            ),
+            Err(Error::BackupFormat(_))
+        ));
+        target
+            .import_backup(&TestCipher(7), &backup, BackupTables::All)
+            .unwrap();
+        assert_eq!(target.list_entries::<Wallet>().unwrap().len(), 1);
+        assert_eq!(target.get::<Log>(&"1".to_string()).unwrap(), log("1"));
+    }
+
+    #[test]
+    fn test_backup_other_client() {
+        let source = client();
+        source.put(&wallet("1", "alice")).unwrap();
+        let backup = export(&source);
+
+        let backend = MemoryBackend::new();
+        let target = open(&backend);
+        let other = open(&backend);
+        target.put(&wallet("2", "bob")).unwrap();
+        other.put(&wallet("3", "carol")).unwrap();
+        other.put(&log("1")).unwrap();
+
+        // the index pages loaded by target were replaced by other, whose
+        // records in the restored table are replaced as well
+        target
+            .import_backup(&TestCipher(7), &backup, BackupTables::All)
+            .unwrap();
+        assert_eq!(
+            other
+                .list_entries::<Wallet>()
+                .unwrap()
+                .into_values()
+                .collect::<Vec<_>>(),
+            vec![wallet("1", "alice")]
+        );
+        assert_eq!(other.get::<Log>(&"1".to_string()).unwrap(), log("1"));
+    }
+
+    #[test]
+    fn test_backup_rejected() {
+        let source = client();
+        source.put(&wallet("1", "alice")).unwrap();
+        let backup = export(&source);
+        let target = client();
+        target.put(&wallet("3", "carol")).unwrap();
+        let import = |backup: &[u8], cipher: TestCipher| {
+            target.import_backup(&cipher, backup, BackupTables::All)
+        };
+
+        assert!(matches!(
+            import(&backup, TestCipher(8)),
+            Err(Error::BackupAuthentication)
+        ));
+        let mut tampered = backup.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert!(matches!(
+            import(&tampered, TestCipher(7)),
+            Err(Error::BackupAuthentication)
+        ));
+        let mut newer = backup.clone();
+        newer[5] = 2;
+        assert!(matches!(
+            import(&newer, TestCipher(7)),
+            Err(Error::BackupVersion(2))
+        ));
+        assert!(matches!(
+            import(b"SDB", TestCipher(7)),
+            Err(Error::BackupFormat(_))
+        ));
+        assert_eq!(target.list_entries::<Wallet>().unwrap().len(), 1);
+
+        // the values are only readable with the codec of the source database
+        let contents = BackupContents {
+            codec: "other".to_string(),
+            tables: Vec::new(),
+        };
+        assert!(matches!(
+            import(&seal(&TestCipher(7), &contents).unwrap(), TestCipher(7)),
+            Err(Error::CodecMismatch { .. })
+        ));
+    }
+
+    const RAW_KEY: [u8; 32] = [0x42; 32];
+    const NONCE: [u8; 12] = [0x24; 12];
+
+    // The RSA key pair of the device, which is only ever passed to the mock.
+    struct DeviceKey;
+
+    impl GenericObject for DeviceKey {
+        fn handle(&self) -> raw::TEE_ObjectHandle {
+            NonNull::dangling().as_ptr()
+        }
+    }
+
+    fn bytes<'a>(data: *const c_void, len: usize) -> &'a [u8] {
+        unsafe { core::slice::from_raw_parts(data as *const u8, len) }
+    }
+
+    fn bytes_mut<'a>(data: *mut c_void, len: usize) -> &'a mut [u8] {
+        unsafe { core::slice::from_raw_parts_mut(data as *mut u8, len) }
+    }
+
+    // The mocked AES-GCM xors the payload with 0x5a, and its tag mixes the
+    // AAD and the ciphertext.
+    fn mocked_tag(aad: &[u8], ciphertext: &[u8]) -> [u8; 16] {
+        let mut tag = [0u8; 16];
+        for (i, byte) in aad.iter().chain(ciphertext).enumerate() {
+            tag[i % 16] ^= byte.wrapping_add(i as u8);
+        }
+        tag
+    }
+
+    // Mock the unwrapping of `raw_key` with a 2048-bit RSA key, then
+    // `encryptions` and `decryptions` with AES-GCM.
+    fn mock_backup_key(raw_key: &'static [u8], encryptions: usize, decryptions: usize) {
+        let aes_key = raw_key.len() * 8 == BackupKey::KEY_SIZE;
+        let mut object_mock = MockObjectController::new();
+        object_mock.expect_TEE_GetObjectInfo1_success_once(raw::TEE_ObjectInfo {
+            objectType: raw::TEE_TYPE_RSA_KEYPAIR,
+            objectSize: 2048,
+            maxObjectSize: 2048,
+            objectUsage: raw::TEE_USAGE_DECRYPT,
+            dataSize: 0,
+            dataPosition: 0,
+            handleFlags: 0,
+        });
+        object_mock
+            .expect_TEE_AllocateTransientObject()
+            .withf(|object_type, size, _| (*object_type, *size) == (raw::TEE_TYPE_AES, 256))
+            .times(aes_key as usize)
+            .returning(|_, _, object| {
+                unsafe { *object = NonNull::dangling().as_ptr() };
+                raw::TEE_SUCCESS
+            });
+        object_mock
+            .expect_TEE_PopulateTransientObject()
+            .times(aes_key as usize)
+            .returning(|_, attrs, count| {
+                assert_eq!(count, 1);
+                let attr = unsafe { &*attrs };
+                assert_eq!(attr.attributeID, raw::TEE_ATTR_SECRET_VALUE);
+                let memref = unsafe { attr.content.memref };
+                assert_eq!(bytes(memref.buffer, memref.size), &RAW_KEY);
+                raw::TEE_SUCCESS
+            });
+        object_mock
+            .expect_TEE_CloseObject()
+            .times(aes_key as usize)
+            .return_const(());
+        set_global_object_mock(object_mock);
+
+        let mut crypto_mock = MockCryptoController::new();
+        crypto_mock.expect_TEE_AllocateOperation_success(
+            1,
+            raw::TEE_ALG_RSAES_PKCS1_OAEP_MGF1_SHA256,
+            raw::TEE_MODE_DECRYPT,
+            2048,
+        );
+        crypto_mock.expect_TEE_AllocateOperation_success(
+            encryptions,
+            raw::TEE_ALG_AES_GCM,
+            raw::TEE_MODE_ENCRYPT,
+            256,
+        );
+        crypto_mock.expect_TEE_AllocateOperation_success(
+            decryptions,
+            raw::TEE_ALG_AES_GCM,
+            raw::TEE_MODE_DECRYPT,
+            256,
+        );
+        crypto_mock
+            .expect_TEE_SetOperationKey()
+            .return_const(raw::TEE_SUCCESS);
+        crypto_mock
+            .expect_TEE_GetOperationInfo()
+            .returning(|_, info| unsafe { (*info).keySize = 2048 });
+        crypto_mock
+            .expect_TEE_AsymmetricDecrypt()
+            .times(1)
+            .returning(move |_, _, _, src, src_len, dest, dest_len| {
+                assert_eq!(bytes(src, src_len), b"wrapped");
+                let dest_len = unsafe { &mut *dest_len };
+                bytes_mut(dest, raw_key.len()).copy_from_slice(raw_key);
+                *dest_len = raw_key.len();
+                raw::TEE_SUCCESS
+            });
+        crypto_mock
+            .expect_TEE_GenerateRandom()
+            .times(encryptions)
+            .returning(|buffer, len| bytes_mut(buffer, len).fill(0x24));
+        let aad = Arc::new(Mutex::new(Vec::new()));
+        let initialized = aad.clone();
+        crypto_mock
+            .expect_TEE_AEInit()
+            .returning(move |_, nonce, nonce_len, tag_len, _, _| {
+                assert_eq!(bytes(nonce, nonce_len), &NONCE);
+                assert_eq!(tag_len, 128);
+                initialized.lock().unwrap().clear();
+                raw::TEE_SUCCESS
+            });
+        let updated = aad.clone();
+        crypto_mock
+            .expect_TEE_AEUpdateAAD()
+            .returning(move |_, data, len| {
+                updated.lock().unwrap().extend_from_slice(bytes(data, len))
+            });
+        let encrypted = aad.clone();
+        crypto_mock
+            .expect_TEE_AEEncryptFinal()
+            .times(encryptions)
+            .returning(move |_, src, src_len, dest, dest_len, tag, tag_len| {
+                let dest = bytes_mut(dest, unsafe { *dest_len });
+                for (out, byte) in dest.iter_mut().zip(bytes(src, src_len)) {
+                    *out = byte ^ 0x5a;
+                }
+                unsafe { *dest_len = src_len };
+                let tag = bytes_mut(tag, unsafe { *tag_len });
+                tag.copy_from_slice(&mocked_tag(&encrypted.lock().unwrap(), &dest[..src_len]));
+                raw::TEE_SUCCESS
+            });
+        crypto_mock
+            .expect_TEE_AEDecryptFinal()
+            .times(decryptions)
+            .returning(move |_, src, src_len, dest, dest_len, tag, tag_len| {
+                let ciphertext = bytes(src, src_len);
+                if bytes(tag, tag_len) != mocked_tag(&aad.lock().unwrap(), ciphertext) {
+                    return raw::TEE_ERROR_MAC_INVALID;
+                }
+                for (out, byte) in bytes_mut(dest, unsafe { *dest_len })
+                    .iter_mut()
+                    .zip(ciphertext)
+                {
+                    *out = byte ^ 0x5a;
+                }
+                unsafe { *dest_len = src_len };
+                raw::TEE_SUCCESS
+            });
+        set_global_crypto_mock(crypto_mock);
+    }
+
+    fn unwrap_key() -> Result<BackupKey> {
+        BackupKey::unwrap(
+            &DeviceKey,
+            AlgorithmId::RsaesPkcs1OAepMgf1Sha256,
+            b"wrapped",
+        )
+    }
+
+    #[test]
+    fn test_backup_key() {
+        let _lock = SERIAL_TEST_LOCK.lock();
+        mock_backup_key(&RAW_KEY, 2, 3);
+        let key = unwrap_key().unwrap();
+
+        // "nonce | ciphertext | tag"
+        let sealed = key.seal(b"header", b"plaintext").unwrap();
+        assert_eq!(&sealed[..12], &NONCE);
+        let ciphertext: Vec<u8> = b"plaintext".iter().map(|byte| byte ^ 0x5a).collect();
+        assert_eq!(&sealed[12..21], &ciphertext[..]);
+        assert_eq!(&sealed[21..], &mocked_tag(b"header", &ciphertext));
+        assert_eq!(key.open(b"header", &sealed).unwrap(), b"plaintext");
+
+        assert!(matches!(
+            key.open(b"HEADER", &sealed),
+            Err(Error::BackupAuthentication)
+        ));
+        assert!(matches!(
+            key.open(b"header", &sealed[..27]),
+            Err(Error::BackupFormat(_))
+        ));
+
+        // a whole backup
+        let source = client();
+        source.put(&wallet("1", "alice")).unwrap();
+        let backup = source.export_backup(&key, BackupTables::All).unwrap();
+        let target = client();
+        target
+            .import_backup(&key, &backup, BackupTables::All)
+            .unwrap();
+        assert_eq!(
+            target.get::<Wallet>(&"1".to_string()).unwrap(),
+            wallet("1", "alice")
+        );
+    }
+
+    #[test]
+    fn test_backup_key_size() {
+        let _lock = SERIAL_TEST_LOCK.lock();
+        mock_backup_key(&RAW_KEY[..16], 0, 0);
+        assert!(matches!(unwrap_key(), Err(Error::InvalidArgument(_))));
+    }
+}
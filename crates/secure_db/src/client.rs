@@ -15,29 +15,90 @@
 // specific language governing permissions and limitations
 // under the License.
 
+use crate::expiry::{expiry_entry, EXPIRY_PREFIX};
 use crate::SecureStorageDb;
 use crate::Storable;
-use anyhow::{anyhow, Result};
-use std::{
-    string::ToString,
-    collections::HashMap,
+use crate::Transaction;
+use crate::{index_entry, index_prefix, Codec, DefaultCodec, IndexValue, Migrations};
+use crate::{sync::ReadGuard, sync::RwLock, Error, HashMap, OpteeBackend, Result, StorageBackend};
+use crate::{ta_time_now, BackupCipher, BackupTables};
+use alloc::{
+    string::{String, ToString},
+    sync::Arc,
+    vec::Vec,
+};
+use core::{
     convert::TryFrom,
     hash::Hash,
-    sync::{Arc, RwLock},
+    ops::{Bound, RangeBounds},
 };
+use optee_utee::ObjectStorageConstants;
 
 // SecureStorageClient is a client to interact with SecureStorageDb.
 // Bound operations to Structure that implements Storable trait.
+// The values are serialized with the codec C of the database, DefaultCodec
+// unless the database is opened with open_with_codec().
+// Expired records, see expiry.rs, are left out as if they were deleted.
 
-pub struct SecureStorageClient {
-    db: Arc<RwLock<SecureStorageDb>>,
+pub struct SecureStorageClient<C: Codec = DefaultCodec, B: StorageBackend = OpteeBackend> {
+    db: Arc<RwLock<SecureStorageDb<C, B>>>,
+    clock: Arc<dyn Fn() -> Result<u64> + Send + Sync>,
 }
 
 impl SecureStorageClient {
     pub fn open(db_name: &str) -> Result<Self> {
-        Ok(Self {
-            db: Arc::new(RwLock::new(SecureStorageDb::open(db_name.to_string())?)),
-        })
+        Self::open_with_codec(db_name, DefaultCodec::default())
+    }
+
+    pub fn open_in_storage(db_name: &str, storage: ObjectStorageConstants) -> Result<Self> {
+        Ok(Self::from_db(SecureStorageDb::open_in_storage(
+            db_name.to_string(),
+            storage,
+        )?))
+    }
+
+    // Open the database and upgrade the tables stored at an older schema
+    // version with the registered migrations. If a migration fails, the
+    // database is left untouched and the error is returned.
+    pub fn open_with_migrations(db_name: &str, migrations: Migrations) -> Result<Self> {
+        Self::open_with_codec_and_migrations(db_name, DefaultCodec::default(), migrations)
+    }
+}
+
+impl<C: Codec> SecureStorageClient<C> {
+    pub fn open_with_codec(db_name: &str, codec: C) -> Result<Self> {
+        Ok(Self::from_db(SecureStorageDb::open_with_codec(
+            db_name.to_string(),
+            codec,
+        )?))
+    }
+
+    pub fn open_with_codec_and_migrations(
+        db_name: &str,
+        codec: C,
+        migrations: Migrations<C>,
+    ) -> Result<Self> {
+        let mut db = SecureStorageDb::open_with_codec(db_name.to_string(), codec)?;
+        migrations.apply(&mut db)?;
+        Ok(Self::from_db(db))
+    }
+}
+
+impl<C: Codec, B: StorageBackend> SecureStorageClient<C, B> {
+    // Bind a client to an opened database, e.g. one opened with
+    // SecureStorageDb::open_with_backend().
+    pub fn from_db(db: SecureStorageDb<C, B>) -> Self {
+        Self {
+            db: Arc::new(RwLock::new(db)),
+            clock: Arc::new(ta_time_now),
+        }
+    }
+
+    // Tell the expiry of the records with `clock` rather than with the TA
+    // persistent time, e.g. to test them on the host.
+    pub fn with_clock(mut self, clock: impl Fn() -> Result<u64> + Send + Sync + 'static) -> Self {
+        self.clock = Arc::new(clock);
+        self
     }
 
     pub fn get<V>(&self, key: &V::Key) -> Result<V>
@@ -47,24 +108,27 @@ impl SecureStorageClient {
     {
         let key = key.to_string();
         let storage_key = V::concat_key(&key);
-        let value = self
-            .db
-            .read()
-            .map_err(|_| anyhow!("Failed to acquire read lock"))?
-            .get(&storage_key)?;
-        Ok(bincode::deserialize(&value)?)
+        let db = self.read_latest()?;
+        check_schema_version::<V, C, B>(&db)?;
+        let value = db.codec().decode(&db.get(&storage_key)?)?;
+        if self.expired(&value, &mut None)? {
+            return Err(Error::KeyNotFound(storage_key));
+        }
+        Ok(value)
     }
 
     pub fn put<V>(&self, value: &V) -> Result<()>
     where
         V: Storable + serde::Serialize,
     {
+        let mut db = self.db.write()?;
+        db.reload()?;
+        let mut transaction = Transaction::new();
+        record_schema_version::<V, C, B>(&db, &mut transaction)?;
         let key = value.storage_key();
-        let value = bincode::serialize(value)?;
-        self.db
-            .write()
-            .map_err(|_| anyhow!("Failed to acquire write lock"))?
-            .put(key, value)?;
+        let index_entries = value.index_entries();
+        transaction.put_indexed(key, db.codec().encode(value)?, index_entries);
+        db.commit(transaction)?;
         Ok(())
     }
 
@@ -75,29 +139,654 @@ impl SecureStorageClient {
     {
         let key = key.to_string();
         let storage_key = V::concat_key(&key);
-        self.db
-            .write()
-            .map_err(|_| anyhow!("Failed to acquire write lock"))?
-            .delete(&storage_key)?;
+        self.db.write()?.delete(&storage_key)?;
         Ok(())
     }
 
+    // Return the record along with its version, see SecureStorageDb::version(),
+    // to update it later with put_if_version(). The record is read as last
+    // committed by any client of the database.
+    pub fn get_versioned<V>(&self, key: &V::Key) -> Result<(V, u64)>
+    where
+        V: Storable + serde::de::DeserializeOwned,
+        V::Key: ToString,
+    {
+        let storage_key = V::concat_key(&key.to_string());
+        let mut db = self.db.write()?;
+        db.reload()?;
+        check_schema_version::<V, C, B>(&db)?;
+        let value = db.codec().decode(&db.get(&storage_key)?)?;
+        if self.expired(&value, &mut None)? {
+            return Err(Error::KeyNotFound(storage_key));
+        }
+        Ok((value, db.version(&storage_key)?))
+    }
+
+    // Put the record only if it is still at `version`, 0 if it must not
+    // exist yet, and return its new version. Fails with Error::Conflict if it
+    // was updated in the meantime, through this client or any other one.
+    pub fn put_if_version<V>(&self, value: &V, version: u64) -> Result<u64>
+    where
+        V: Storable + serde::Serialize,
+    {
+        let mut transaction = self.begin();
+        transaction.put_if_version(value, version)?;
+        transaction.commit()?;
+        Ok(version + 1)
+    }
+
+    // Read the record, update it with `f` and put it back, unless it was
+    // updated by another client in between, in which case this fails with
+    // Error::Conflict; see retry_on_conflict(). Return the updated record.
+    pub fn update_with<V, F>(&self, key: &V::Key, f: F) -> Result<V>
+    where
+        V: Storable + serde::Serialize + serde::de::DeserializeOwned,
+        V::Key: ToString,
+        F: FnOnce(&mut V) -> Result<()>,
+    {
+        let (mut value, version) = self.get_versioned::<V>(key)?;
+        let storage_key = value.storage_key();
+        f(&mut value)?;
+        if value.storage_key() != storage_key {
+            return Err(Error::InvalidArgument(
+                "update_with must not change the key",
+            ));
+        }
+        self.put_if_version(&value, version)?;
+        Ok(value)
+    }
+
+    // Return the records whose secondary index `index` equals `value`, ordered
+    // by key.
+    pub fn find_by<V, I>(&self, index: &str, value: &I) -> Result<Vec<V>>
+    where
+        V: Storable + serde::de::DeserializeOwned,
+        I: IndexValue + ?Sized,
+    {
+        let entry = index_entry(V::table_name(), index, &value.index_value());
+        self.find::<V>(index, &entry, |e| e == entry)
+    }
+
+    // Return the records whose secondary index `index` is within `range`,
+    // ordered by index value then key.
+    pub fn find_range<V, I>(&self, index: &str, range: impl RangeBounds<I>) -> Result<Vec<V>>
+    where
+        V: Storable + serde::de::DeserializeOwned,
+        I: IndexValue,
+    {
+        let prefix = index_prefix(V::table_name(), index);
+        let bound = |value: &I| index_entry(V::table_name(), index, &value.index_value());
+        let (start, excluded) = match range.start_bound() {
+            Bound::Included(value) => (bound(value), None),
+            Bound::Excluded(value) => (bound(value), Some(bound(value))),
+            Bound::Unbounded => (prefix.clone(), None),
+        };
+        let end = match range.end_bound() {
+            Bound::Included(value) => Bound::Included(bound(value)),
+            Bound::Excluded(value) => Bound::Excluded(bound(value)),
+            Bound::Unbounded => Bound::Unbounded,
+        };
+        self.find::<V>(index, &start, |entry| {
+            entry.starts_with(&prefix)
+                && excluded.as_deref() != Some(entry)
+                && match &end {
+                    Bound::Included(end) => entry <= end.as_str(),
+                    Bound::Excluded(end) => entry < end.as_str(),
+                    Bound::Unbounded => true,
+                }
+        })
+    }
+
+    pub fn begin(&self) -> ClientTransaction<'_, C, B> {
+        ClientTransaction {
+            client: self,
+            transaction: Transaction::new(),
+        }
+    }
+
+    fn find<V>(&self, index: &str, start: &str, in_range: impl Fn(&str) -> bool) -> Result<Vec<V>>
+    where
+        V: Storable + serde::de::DeserializeOwned,
+    {
+        if !V::index_names().contains(&index) {
+            return Err(Error::NoIndex {
+                table: V::table_name(),
+                index: index.to_string(),
+            });
+        }
+        let db = self.read_latest()?;
+        check_schema_version::<V, C, B>(&db)?;
+        let mut result = Vec::new();
+        let mut now = None;
+        for (_entry, key) in db.scan_index_entries(start, in_range)? {
+            let value = db.codec().decode(&db.get(&key)?)?;
+            if !self.expired(&value, &mut now)? {
+                result.push(value);
+            }
+        }
+        Ok(result)
+    }
+
     pub fn list_entries<V>(&self) -> Result<HashMap<V::Key, V>>
     where
         V: Storable + serde::de::DeserializeOwned,
         V::Key: TryFrom<String> + Eq + Hash,
     {
-        let map = self
-            .db
-            .read()
-            .map_err(|_| anyhow!("Failed to acquire read lock"))?
-            .list_entries_with_prefix(V::table_name())?;
+        let db = self.read_latest()?;
+        check_schema_version::<V, C, B>(&db)?;
+        let map = db.list_entries_with_prefix(V::table_name())?;
         let mut result = HashMap::new();
+        let mut now = None;
         for (_k, v) in map {
-            let value: V = bincode::deserialize(&v)?;
+            let value: V = db.codec().decode(&v)?;
+            if self.expired(&value, &mut now)? {
+                continue;
+            }
             let key = value.unique_id();
             result.insert(key, value);
         }
         Ok(result)
     }
+
+    // Delete the expired records of all the tables in a single transaction,
+    // and return how many were deleted.
+    pub fn purge_expired(&self) -> Result<usize> {
+        let now = (self.clock)()?;
+        let mut db = self.db.write()?;
+        db.reload()?;
+        let end = expiry_entry(now);
+        let mut transaction = Transaction::new();
+        for (_entry, key) in db.scan_index_entries(EXPIRY_PREFIX, |entry| {
+            entry.starts_with(EXPIRY_PREFIX) && entry <= end.as_str()
+        })? {
+            transaction.delete(key);
+        }
+        let purged = transaction.len();
+        db.commit(transaction)?;
+        Ok(purged)
+    }
+
+    // Lock the database for reading as last committed by any client. A commit
+    // deletes the index pages it replaced, which the index roots loaded
+    // before it may still refer to.
+    fn read_latest(&self) -> Result<ReadGuard<'_, SecureStorageDb<C, B>>> {
+        self.db.write()?.reload()?;
+        self.db.read()
+    }
+
+    // Whether the record expired, reading the clock once for all the records
+    // sharing `now`.
+    fn expired<V: Storable>(&self, value: &V, now: &mut Option<u64>) -> Result<bool> {
+        let expires_at = match value.expires_at() {
+            Some(expires_at) => expires_at,
+            None => return Ok(false),
+        };
+        let now = match *now {
+            Some(now) => now,
+            None => *now.insert((self.clock)()?),
+        };
+        Ok(expires_at <= now)
+    }
+
+    // See SecureStorageDb::export_backup(), select the tables with
+    // Storable::table_name().
+    pub fn export_backup(
+        &self,
+        cipher: &impl BackupCipher,
+        tables: BackupTables,
+    ) -> Result<Vec<u8>> {
+        self.read_latest()?.export_backup(cipher, tables)
+    }
+
+    // See SecureStorageDb::import_backup(). Tables restored at an older schema
+    // version are migrated the next time the database is opened with
+    // migrations.
+    pub fn import_backup(
+        &self,
+        cipher: &impl BackupCipher,
+        backup: &[u8],
+        tables: BackupTables,
+    ) -> Result<()> {
+        self.db.write()?.import_backup(cipher, backup, tables)
+    }
+}
+
+// Run `f` until it does not fail with Error::Conflict, at most `attempts`
+// times, e.g. to apply an update on the latest version of a record:
+//
+//     retry_on_conflict(3, || {
+//         client.update_with::<Counter, _>(&id, |counter| {
+//             counter.value += 1;
+//             Ok(())
+//         })
+//     })?;
+pub fn retry_on_conflict<T>(attempts: usize, mut f: impl FnMut() -> Result<T>) -> Result<T> {
+    let mut attempt = 1;
+    loop {
+        match f() {
+            Err(Error::Conflict { .. }) if attempt < attempts => attempt += 1,
+            result => return result,
+        }
+    }
+}
+
+// ClientTransaction batches puts and deletes of Storable values, possibly of
+// different tables, and commits them atomically with SecureStorageDb::commit().
+// Nothing is written until commit() is called.
+
+pub struct ClientTransaction<'a, C: Codec = DefaultCodec, B: StorageBackend = OpteeBackend> {
+    client: &'a SecureStorageClient<C, B>,
+    transaction: Transaction,
+}
+
+impl<C: Codec, B: StorageBackend> ClientTransaction<'_, C, B> {
+    pub fn put<V>(&mut self, value: &V) -> Result<()>
+    where
+        V: Storable + serde::Serialize,
+    {
+        let db = self.client.read_latest()?;
+        record_schema_version::<V, C, B>(&db, &mut self.transaction)?;
+        let key = value.storage_key();
+        let index_entries = value.index_entries();
+        self.transaction
+            .put_indexed(key, db.codec().encode(value)?, index_entries);
+        Ok(())
+    }
+
+    // Put the record only if it is still at `version` when the transaction
+    // is committed, see SecureStorageClient::put_if_version().
+    pub fn put_if_version<V>(&mut self, value: &V, version: u64) -> Result<()>
+    where
+        V: Storable + serde::Serialize,
+    {
+        self.transaction
+            .expect_version(value.storage_key(), version);
+        self.put(value)
+    }
+
+    pub fn delete_entry<V>(&mut self, key: &V::Key)
+    where
+        V: Storable,
+        V::Key: ToString,
+    {
+        let key = key.to_string();
+        self.transaction.delete(V::concat_key(&key));
+    }
+
+    pub fn commit(self) -> Result<()> {
+        self.client.db.write()?.commit(self.transaction)
+    }
+}
+
+// Records of V can only be read from a table stored at the schema version of
+// V; older tables must be upgraded with Migrations first.
+fn check_schema_version<V: Storable, C: Codec, B: StorageBackend>(
+    db: &SecureStorageDb<C, B>,
+) -> Result<()> {
+    let stored = db.schema_version(V::table_name());
+    if stored != V::schema_version() {
+        return Err(schema_version_error::<V>(stored));
+    }
+    Ok(())
+}
+
+// Writing the first record of a table records its schema version; writing to
+// a table stored at another version fails like reading from it. The database
+// must have been reloaded, as another client may have written to the table.
+fn record_schema_version<V: Storable, C: Codec, B: StorageBackend>(
+    db: &SecureStorageDb<C, B>,
+    transaction: &mut Transaction,
+) -> Result<()> {
+    if db.schema_version(V::table_name()) == V::schema_version() {
+        return Ok(());
+    }
+    if !db.scan_prefix(&V::concat_key(""), None, 1)?.keys.is_empty() {
+        return Err(schema_version_error::<V>(
+            db.schema_version(V::table_name()),
+        ));
+    }
+    transaction.set_schema_version(V::table_name().to_string(), V::schema_version());
+    Ok(())
+}
+
+fn schema_version_error<V: Storable>(stored: u32) -> Error {
+    Error::SchemaVersion {
+        table: V::table_name().to_string(),
+        stored,
+        expected: V::schema_version(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Bincode, MemoryBackend};
+    use core::sync::atomic::{AtomicU64, Ordering};
+    use optee_utee_mock::{
+        object::SERIAL_TEST_LOCK,
+        time::{set_global_time_mock, MockTimeController},
+    };
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct Account {
+        id: u32,
+        owner: String,
+        balance: i64,
+    }
+
+    impl Storable for Account {
+        type Key = u32;
+
+        fn unique_id(&self) -> u32 {
+            self.id
+        }
+
+        fn index_names() -> &'static [&'static str] {
+            &["owner", "balance"]
+        }
+
+        fn index_values(&self) -> Vec<Vec<u8>> {
+            vec![self.owner.index_value(), self.balance.index_value()]
+        }
+    }
+
+    fn account(id: u32, owner: &str, balance: i64) -> Account {
+        Account {
+            id,
+            owner: owner.to_string(),
+            balance,
+        }
+    }
+
+    fn open(backend: &MemoryBackend) -> SecureStorageClient<Bincode, MemoryBackend> {
+        SecureStorageClient::from_db(
+            SecureStorageDb::open_with_backend("db".to_string(), Bincode, backend.clone()).unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_find() {
+        let backend = MemoryBackend::new();
+        let client = open(&backend);
+        let mut transaction = client.begin();
+        transaction.put(&account(1, "alice", -5)).unwrap();
+        transaction.put(&account(2, "bob", 10)).unwrap();
+        transaction.put(&account(3, "alice", 20)).unwrap();
+        transaction.commit().unwrap();
+        client.put(&account(2, "bob", 0)).unwrap();
+
+        let client = open(&backend);
+        assert_eq!(
+            client.find_by::<Account, _>("owner", "alice").unwrap(),
+            vec![account(1, "alice", -5), account(3, "alice", 20)]
+        );
+        assert_eq!(
+            client
+                .find_range::<Account, i64>("balance", -5..=0)
+                .unwrap(),
+            vec![account(1, "alice", -5), account(2, "bob", 0)]
+        );
+        assert_eq!(
+            client.find_range::<Account, i64>("balance", 10..).unwrap(),
+            vec![account(3, "alice", 20)]
+        );
+        assert!(client.find_by::<Account, _>("id", &1u32).is_err());
+
+        client.delete_entry::<Account>(&3).unwrap();
+        assert_eq!(
+            client.find_by::<Account, _>("owner", "alice").unwrap(),
+            vec![account(1, "alice", -5)]
+        );
+    }
+
+    #[test]
+    fn test_other_client() {
+        let backend = MemoryBackend::new();
+        let client = open(&backend);
+        let other = open(&backend);
+        client.put(&account(1, "alice", 0)).unwrap();
+        other.put(&account(2, "bob", 10)).unwrap();
+
+        // the index pages loaded by client were replaced by other
+        assert_eq!(client.get::<Account>(&1).unwrap(), account(1, "alice", 0));
+        assert_eq!(client.get::<Account>(&2).unwrap(), account(2, "bob", 10));
+        other
+            .put(&Nonce {
+                id: "1".to_string(),
+                expires_at: None,
+            })
+            .unwrap();
+        assert_eq!(client.list_entries::<Nonce>().unwrap().len(), 1);
+        other.put(&account(1, "alice", 5)).unwrap();
+        assert_eq!(
+            client.find_by::<Account, _>("owner", "alice").unwrap(),
+            vec![account(1, "alice", 5)]
+        );
+        other.delete_entry::<Account>(&2).unwrap();
+        assert!(matches!(
+            client.get::<Account>(&2),
+            Err(Error::KeyNotFound(_))
+        ));
+    }
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct Session {
+        id: u32,
+    }
+
+    impl Storable for Session {
+        type Key = u32;
+
+        fn unique_id(&self) -> u32 {
+            self.id
+        }
+
+        fn schema_version() -> u32 {
+            2
+        }
+    }
+
+    #[test]
+    fn test_other_client_new_table() {
+        let backend = MemoryBackend::new();
+        let client = open(&backend);
+        let other = open(&backend);
+        client.put(&account(1, "alice", 0)).unwrap();
+        other.put(&account(2, "bob", 10)).unwrap();
+        // the first record of a table is checked against the index pages
+        // written by other, not the ones loaded by client
+        client.put(&Session { id: 1 }).unwrap();
+        assert_eq!(other.get::<Session>(&1).unwrap(), Session { id: 1 });
+
+        let backend = MemoryBackend::new();
+        let client = open(&backend);
+        let other = open(&backend);
+        client.put(&account(1, "alice", 0)).unwrap();
+        other.put(&account(2, "bob", 10)).unwrap();
+        let mut transaction = client.begin();
+        transaction.put(&Session { id: 1 }).unwrap();
+        transaction.commit().unwrap();
+        assert_eq!(other.get::<Session>(&1).unwrap(), Session { id: 1 });
+    }
+
+    #[test]
+    fn test_versions() {
+        let backend = MemoryBackend::new();
+        let client = open(&backend);
+        assert_eq!(
+            client.put_if_version(&account(1, "alice", 0), 0).unwrap(),
+            1
+        );
+        assert!(matches!(
+            client.put_if_version(&account(1, "alice", 5), 0),
+            Err(Error::Conflict {
+                expected: 0,
+                actual: 1,
+                ..
+            })
+        ));
+        client.put(&account(1, "alice", 10)).unwrap();
+        let (value, version) = client.get_versioned::<Account>(&1).unwrap();
+        assert_eq!((value, version), (account(1, "alice", 10), 2));
+
+        // another client of the same database updates the record in between
+        let other = open(&backend);
+        let updated = other
+            .update_with::<Account, _>(&1, |account| {
+                account.balance += 1;
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(updated, account(1, "alice", 11));
+        assert!(matches!(
+            client.put_if_version(&account(1, "bob", 10), version),
+            Err(Error::Conflict {
+                expected: 2,
+                actual: 3,
+                ..
+            })
+        ));
+        let mut transaction = client.begin();
+        transaction.put(&account(2, "bob", 0)).unwrap();
+        transaction
+            .put_if_version(&account(1, "alice", 0), version)
+            .unwrap();
+        assert!(matches!(transaction.commit(), Err(Error::Conflict { .. })));
+        assert!(client.get::<Account>(&2).is_err());
+        assert!(client
+            .update_with::<Account, _>(&1, |account| {
+                account.id = 2;
+                Ok(())
+            })
+            .is_err());
+
+        // the versions are kept by the index updates and removed with the key
+        client.put(&account(1, "carol", 11)).unwrap();
+        assert_eq!(client.get_versioned::<Account>(&1).unwrap().1, 4);
+        client.delete_entry::<Account>(&1).unwrap();
+        assert_eq!(
+            client.put_if_version(&account(1, "alice", 0), 0).unwrap(),
+            1
+        );
+    }
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct Nonce {
+        id: String,
+        expires_at: Option<u64>,
+    }
+
+    impl Storable for Nonce {
+        type Key = String;
+
+        fn unique_id(&self) -> String {
+            self.id.clone()
+        }
+
+        fn expires_at(&self) -> Option<u64> {
+            self.expires_at
+        }
+    }
+
+    #[test]
+    fn test_expiry() {
+        let _lock = SERIAL_TEST_LOCK.lock();
+        let mut time_mock = MockTimeController::new();
+        time_mock.expect_TEE_GetTAPersistentTime_not_set();
+        set_global_time_mock(time_mock);
+        let backend = MemoryBackend::new();
+        let now = Arc::new(AtomicU64::new(100));
+        let clock = now.clone();
+        let client = open(&backend).with_clock(move || Ok(clock.load(Ordering::SeqCst)));
+        let nonce = |id: &str, expires_at| Nonce {
+            id: id.to_string(),
+            expires_at,
+        };
+        client.put(&nonce("1", Some(110))).unwrap();
+        client.put(&nonce("2", Some(120))).unwrap();
+        client.put(&nonce("3", None)).unwrap();
+        client.put(&nonce("4", Some(150))).unwrap();
+        // extending the expiry replaces the previous one
+        client.put(&nonce("4", Some(200))).unwrap();
+        assert_eq!(
+            client.get::<Nonce>(&"1".to_string()).unwrap(),
+            nonce("1", Some(110))
+        );
+
+        now.store(120, Ordering::SeqCst);
+        assert!(matches!(
+            client.get::<Nonce>(&"1".to_string()),
+            Err(Error::KeyNotFound(_))
+        ));
+        assert!(client.get_versioned::<Nonce>(&"2".to_string()).is_err());
+        assert_eq!(
+            client.get::<Nonce>(&"3".to_string()).unwrap(),
+            nonce("3", None)
+        );
+        assert_eq!(client.list_entries::<Nonce>().unwrap().len(), 2);
+
+        now.store(160, Ordering::SeqCst);
+        assert_eq!(client.purge_expired().unwrap(), 2);
+        assert_eq!(client.purge_expired().unwrap(), 0);
+        let client = open(&backend).with_clock(|| Ok(1000));
+        assert!(client.get::<Nonce>(&"1".to_string()).is_err());
+        assert_eq!(client.purge_expired().unwrap(), 1);
+        assert_eq!(client.list_entries::<Nonce>().unwrap().len(), 1);
+
+        // without the TA time, the expiry of the records cannot be told
+        client.put(&nonce("5", Some(2000))).unwrap();
+        let client = open(&backend);
+        assert_eq!(
+            client.get::<Nonce>(&"3".to_string()).unwrap(),
+            nonce("3", None)
+        );
+        assert!(matches!(
+            client.get::<Nonce>(&"5".to_string()),
+            Err(Error::TaTime(_))
+        ));
+        assert!(matches!(client.purge_expired(), Err(Error::TaTime(_))));
+        assert_eq!(
+            open(&backend)
+                .with_clock(|| Ok(1000))
+                .get::<Nonce>(&"5".to_string())
+                .unwrap(),
+            nonce("5", Some(2000))
+        );
+    }
+
+    #[test]
+    fn test_retry_on_conflict() {
+        let backend = MemoryBackend::new();
+        let client = open(&backend);
+        let other = open(&backend);
+        client.put(&account(1, "alice", 0)).unwrap();
+        let increment = |account: &mut Account| {
+            account.balance += 1;
+            Ok(())
+        };
+
+        let mut attempts = 0;
+        let result = retry_on_conflict(3, || {
+            attempts += 1;
+            let (mut value, version) = client.get_versioned::<Account>(&1)?;
+            if attempts == 1 {
+                other.update_with::<Account, _>(&1, increment)?;
+            }
+            increment(&mut value)?;
+            client.put_if_version(&value, version)
+        });
+        assert_eq!(result.unwrap(), 3);
+        assert_eq!(attempts, 2);
+        assert_eq!(client.get::<Account>(&1).unwrap().balance, 2);
+
+        let result = retry_on_conflict(2, || -> Result<()> {
+            Err(Error::Conflict {
+                key: String::new(),
+                expected: 0,
+                actual: 1,
+            })
+        });
+        assert!(matches!(result, Err(Error::Conflict { .. })));
+    }
 }
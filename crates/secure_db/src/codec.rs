@@ -0,0 +1,216 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::Result;
+use alloc::vec::Vec;
+use serde::{de::DeserializeOwned, Serialize};
+
+// Codec is the serialization format of a database, used for the records as
+// well as for the index, the journal and the schema versions.
+// It is chosen when the database is created and recorded along with it, see
+// SecureStorageDb::open_with_codec(); DefaultCodec is Bincode with std, and
+// Postcard without std.
+//
+// Bincode needs std; Cbor and Json are behind the cargo features "cbor" and
+// "json".
+
+pub trait Codec: Clone + Send + Sync + 'static {
+    // Identifies the format in the database, must never change.
+    const NAME: &'static str;
+
+    fn encode<T: Serialize + ?Sized>(&self, value: &T) -> Result<Vec<u8>>;
+    fn decode<T: DeserializeOwned>(&self, data: &[u8]) -> Result<T>;
+}
+
+#[cfg(feature = "std")]
+pub type DefaultCodec = Bincode;
+#[cfg(not(feature = "std"))]
+pub type DefaultCodec = Postcard;
+
+// bincode 1.x, compact and fast, but not self-describing.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Bincode;
+
+#[cfg(feature = "std")]
+impl Codec for Bincode {
+    const NAME: &'static str = "bincode";
+
+    fn encode<T: Serialize + ?Sized>(&self, value: &T) -> Result<Vec<u8>> {
+        Ok(bincode::serialize(value)?)
+    }
+
+    fn decode<T: DeserializeOwned>(&self, data: &[u8]) -> Result<T> {
+        Ok(bincode::deserialize(data)?)
+    }
+}
+
+// postcard, the most compact with its variable length integers.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Postcard;
+
+impl Codec for Postcard {
+    const NAME: &'static str = "postcard";
+
+    fn encode<T: Serialize + ?Sized>(&self, value: &T) -> Result<Vec<u8>> {
+        Ok(postcard::to_allocvec(value)?)
+    }
+
+    fn decode<T: DeserializeOwned>(&self, data: &[u8]) -> Result<T> {
+        Ok(postcard::from_bytes(data)?)
+    }
+}
+
+// CBOR (RFC 8949), compact and self-describing.
+#[cfg(feature = "cbor")]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Cbor;
+
+#[cfg(feature = "cbor")]
+impl Codec for Cbor {
+    const NAME: &'static str = "cbor";
+
+    fn encode<T: Serialize + ?Sized>(&self, value: &T) -> Result<Vec<u8>> {
+        let mut data = Vec::new();
+        ciborium::into_writer(value, &mut data).map_err(|e| crate::Error::Codec(e.to_string()))?;
+        Ok(data)
+    }
+
+    fn decode<T: DeserializeOwned>(&self, data: &[u8]) -> Result<T> {
+        ciborium::from_reader(data).map_err(|e| crate::Error::Codec(e.to_string()))
+    }
+}
+
+// JSON, self-describing and human readable.
+#[cfg(feature = "json")]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Json;
+
+#[cfg(feature = "json")]
+impl Codec for Json {
+    const NAME: &'static str = "json";
+
+    fn encode<T: Serialize + ?Sized>(&self, value: &T) -> Result<Vec<u8>> {
+        Ok(serde_json::to_vec(value)?)
+    }
+
+    fn decode<T: DeserializeOwned>(&self, data: &[u8]) -> Result<T> {
+        Ok(serde_json::from_slice(data)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FaultyBackend, MemoryBackend, SecureStorageDb, StorageBackend};
+    use alloc::{
+        format,
+        string::{String, ToString},
+        vec,
+    };
+    use serde::Deserialize;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Record {
+        id: u32,
+        owner: String,
+        seed: Vec<u8>,
+    }
+
+    fn record(id: u32) -> Record {
+        Record {
+            id,
+            owner: format!("owner{}", id % 3),
+            seed: vec![id as u8; 32],
+        }
+    }
+
+    // Store records spanning several index pages, their secondary index
+    // entries and the schema version of their table with `codec`, then
+    // interrupt a commit once its journal is stored, which is completed when
+    // the database is opened again.
+    fn round_trip<C: Codec>(codec: C) {
+        let backend = FaultyBackend::new(MemoryBackend::new());
+        let open = || {
+            SecureStorageDb::open_with_backend("db".to_string(), codec.clone(), backend.clone())
+                .unwrap()
+        };
+        let mut db = open();
+        let mut transaction = db.begin();
+        for id in 0..500 {
+            let record = record(id);
+            transaction.put_indexed(
+                format!("t#{:03}", id),
+                codec.encode(&record).unwrap(),
+                vec![format!("t#owner#{}", record.owner)],
+            );
+        }
+        transaction.set_schema_version("t".to_string(), 2);
+        db.commit(transaction).unwrap();
+
+        let mut transaction = db.begin();
+        transaction.delete("t#000".to_string());
+        transaction.put_indexed(
+            "t#500".to_string(),
+            codec.encode(&record(500)).unwrap(),
+            vec!["t#owner#owner2".to_string()],
+        );
+        transaction.set_schema_version("t".to_string(), 3);
+        backend.fail_after(1);
+        assert!(db.commit(transaction).is_err());
+        backend.heal();
+
+        let db = open();
+        assert!(backend.list(b"db.journal").unwrap().is_empty());
+        let page = db.scan_prefix("t#", None, usize::MAX).unwrap();
+        assert_eq!(
+            page.keys,
+            (1..=500)
+                .map(|id| format!("t#{:03}", id))
+                .collect::<Vec<_>>()
+        );
+        for id in [1, 250, 500] {
+            let value = db.get(&format!("t#{:03}", id)).unwrap();
+            assert_eq!(codec.decode::<Record>(&value).unwrap(), record(id));
+        }
+        assert_eq!(db.schema_version("t"), 3);
+        let owners = db
+            .scan_index_entries("t#owner#owner2", |entry| {
+                entry.starts_with("t#owner#owner2")
+            })
+            .unwrap();
+        assert_eq!(owners.len(), 167);
+        assert!(owners.contains(&("t#owner#owner2".to_string(), "t#500".to_string())));
+    }
+
+    #[test]
+    fn test_postcard() {
+        round_trip(Postcard);
+    }
+
+    #[cfg(feature = "cbor")]
+    #[test]
+    fn test_cbor() {
+        round_trip(Cbor);
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn test_json() {
+        round_trip(Json);
+    }
+}
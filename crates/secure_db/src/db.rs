@@ -15,102 +15,971 @@
 // specific language governing permissions and limitations
 // under the License.
 
-use crate::{delete_from_secure_storage, load_from_secure_storage, save_in_secure_storage};
-use anyhow::{bail, ensure, Result};
+use crate::{
+    backup::{self, BackupContents, RecordBackup, TableBackup},
+    index::Index,
+    table_of,
+    transaction::{Journal, Operation},
+    BackupCipher, BackupTables, Codec, DefaultCodec, Error, HashMap, KeyPage, OpteeBackend, Result,
+    StorageBackend, Transaction,
+};
+use alloc::{
+    collections::{BTreeMap, BTreeSet},
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::ops::Bound;
 use hashbrown::HashSet;
-use std::collections::HashMap;
+use optee_utee::ObjectStorageConstants;
+use zeroize::Zeroizing;
 
 // SecureStorageDb is a key-value storage for TA to easily store and retrieve data.
-// First we store the key list in the secure storage, named as db_name.
+// First we store the key index in the secure storage, as a B-tree of pages
+// named after db_name (see Index).
 // Then we store the each key-value pairs in the secure storage.
+//
+// Secondary index entries are kept in a second index, named after
+// "$db_name.secondary", which maps each entry to the keys holding it, and each
+// key to its entries so that they can be replaced without reading the old
+// value.
+//
+// The schema version of each table is stored in an object named as
+// "$db_name.schema"; tables which are not listed there are at version 0.
+//
+// All of the above is serialized with the Codec of the database, whose name is
+// stored in an object named as "$db_name.codec" when the database is created.
+// Databases created before the codec was selectable have no such object and
+// use Bincode, which is the default codec with std.
+//
+// Every update goes through a Transaction: the operations are first written
+// to a journal object, named as "$db_name.journal", then applied to the
+// key-value pairs and the index, and the journal is deleted once the index is
+// stored. If the TA is interrupted in between, the journal is replayed the
+// next time the database is opened, so an update is either fully applied or
+// not at all.
+//
+// The objects are stored by a StorageBackend, as OP-TEE persistent objects by
+// default. A record is stored in an object named as its key, so the object ids
+// above cannot be used as keys.
 
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub struct SecureStorageDb {
+pub struct SecureStorageDb<C: Codec = DefaultCodec, B: StorageBackend = OpteeBackend> {
     name: String,
-    key_list: HashSet<String>,
+    index: Index<C, B>,
+    secondary: Index<C, B>,
+    schema: BTreeMap<String, u32>,
+    codec: C,
+    backend: B,
 }
 
 impl SecureStorageDb {
     pub fn open(name: String) -> Result<Self> {
-        match load_from_secure_storage(name.as_bytes())? {
-            Some(data) => {
-                let key_list = bincode::deserialize(&data)?;
-                Ok(Self { name, key_list })
-            }
-            None => {
+        Self::open_with_codec(name, DefaultCodec::default())
+    }
+
+    // Open the database in the given storage, see OpteeBackend for placing
+    // some of its tables in another storage.
+    pub fn open_in_storage(name: String, storage: ObjectStorageConstants) -> Result<Self> {
+        Self::open_with_backend(name, DefaultCodec::default(), OpteeBackend::new(storage))
+    }
+}
+
+impl<C: Codec> SecureStorageDb<C> {
+    // Open the database, or create it if it does not exist, with the given
+    // serialization format. Opening an existing database with another codec
+    // than the one it was created with fails.
+    pub fn open_with_codec(name: String, codec: C) -> Result<Self> {
+        Self::open_with_backend(name, codec, OpteeBackend::default())
+    }
+}
+
+impl<C: Codec, B: StorageBackend> SecureStorageDb<C, B> {
+    // Open the database, or create it if it does not exist, in the given
+    // backend.
+    pub fn open_with_backend(name: String, codec: C, backend: B) -> Result<Self> {
+        check_codec::<C, B>(&name, &backend)?;
+        let index = match Index::load(&name, codec.clone(), backend.clone())? {
+            Some(index) => index,
+            None => match backend.load(name.as_bytes())? {
+                // Databases created before the index was paged store their
+                // whole key list as a single object named as db_name. Move it
+                // into the index; the object is only deleted once the index is
+                // stored, so an interrupted upgrade is simply done again.
+                // Such a database is only opened with Bincode, see
+                // check_codec().
+                Some(data) => {
+                    let key_list: HashSet<String> = codec.decode(&data)?;
+                    let index = Index::create(&name, codec.clone(), backend.clone(), key_list)?;
+                    backend.delete(name.as_bytes())?;
+                    index
+                }
                 // create new db
-                Ok(Self {
-                    name,
-                    // Note: `std::collections::HashSet` was replaced with
-                    // `hashbrown::HashSet`, due to a write permission fault
-                    // observed during testing. The exact cause of the issue is
-                    // unclear, but using `hashbrown::HashSet` resolves it.
-                    key_list: HashSet::new(),
-                })
-            }
-        }
+                None => Index::new(&name, codec.clone(), backend.clone()),
+            },
+        };
+        let secondary_name = format!("{}.secondary", name);
+        let secondary = match Index::load(&secondary_name, codec.clone(), backend.clone())? {
+            Some(secondary) => secondary,
+            None => Index::new(&secondary_name, codec.clone(), backend.clone()),
+        };
+        let schema = match backend.load(format!("{}.schema", name).as_bytes())? {
+            Some(data) => codec.decode(&data)?,
+            None => BTreeMap::new(),
+        };
+        let mut db = Self {
+            name,
+            index,
+            secondary,
+            schema,
+            codec,
+            backend,
+        };
+        // complete the transaction that was interrupted, if any
+        db.recover()?;
+        Ok(db)
     }
 
     pub fn put(&mut self, key: String, value: Vec<u8>) -> Result<()> {
-        match save_in_secure_storage(key.as_bytes(), &value) {
-            Ok(_) => {
-                self.key_list.insert(key);
-                self.store_key_list()?;
-            }
-            Err(e) => {
-                bail!("[+] SecureStorage::insert(): save error: {}", e);
-            }
-        };
-        Ok(())
+        let mut transaction = self.begin();
+        transaction.put(key, value);
+        self.commit(transaction)
     }
 
     pub fn get(&self, key: &str) -> Result<Vec<u8>> {
-        ensure!(self.key_list.contains(key), "Key not found in key list");
-        match load_from_secure_storage(key.as_bytes()) {
-            Ok(Some(data)) => Ok(data),
-            Ok(None) => bail!("[+] SecureStorage::get(): object not found in db"),
-            Err(e) => {
-                bail!("[+] SecureStorage::get(): load error: {}", e);
-            }
+        if !self.index.contains(key)? {
+            return Err(Error::KeyNotFound(key.to_string()));
         }
+        self.load(key)
     }
 
     pub fn delete(&mut self, key: &str) -> Result<()> {
-        // ensure key must exist
-        ensure!(self.key_list.contains(key), "Key not found in key list");
-        match delete_from_secure_storage(key.as_bytes()) {
-            Ok(_) => {
-                self.key_list.remove(key);
-                self.store_key_list()?;
+        // the key must exist, which commit() checks
+        let mut transaction = self.begin();
+        transaction.delete(key.to_string());
+        self.commit(transaction)
+    }
+
+    pub fn clear(&mut self) -> Result<()> {
+        let mut transaction = self.begin();
+        for key in self.keys(Bound::Unbounded, |_| true)? {
+            transaction.delete(key);
+        }
+        self.commit(transaction)
+    }
+
+    pub fn begin(&self) -> Transaction {
+        Transaction::new()
+    }
+
+    // Apply all the operations of the transaction, in order, or none of them.
+    // A delete of a key which is not in the database, taking the earlier
+    // operations of the transaction into account, fails the whole transaction
+    // before anything is written, as does a key which is not at the version
+    // the transaction expects.
+    pub fn commit(&mut self, transaction: Transaction) -> Result<()> {
+        let (mut operations, expected_versions) = transaction.into_parts();
+        if operations.is_empty() && expected_versions.is_empty() {
+            return Ok(());
+        }
+        // Another instance of the database may have committed since this one
+        // was opened, and a journal left by a commit that failed half-way
+        // must be replayed before it is overwritten by this one.
+        self.reload()?;
+        self.recover()?;
+
+        for (key, expected) in expected_versions {
+            let actual = self.version(&key)?;
+            if actual != expected {
+                return Err(Error::Conflict {
+                    key,
+                    expected,
+                    actual,
+                });
             }
-            Err(e) => {
-                bail!("[+] SecureStorage::delete(): delete error: {}", e);
+        }
+
+        let mut changes = BTreeMap::new();
+        for operation in &operations {
+            match operation {
+                Operation::Put(key, _, index_entries) => {
+                    if self.is_internal_id(key) {
+                        return Err(Error::InvalidKey(key.clone()));
+                    }
+                    if !index_entries.is_empty()
+                        && (key.contains(SEPARATOR)
+                            || index_entries.iter().any(|entry| entry.contains(SEPARATOR)))
+                    {
+                        return Err(Error::InvalidKey(key.clone()));
+                    }
+                    changes.insert(key.clone(), true);
+                }
+                Operation::Delete(key) => {
+                    let present = match changes.get(key) {
+                        Some(present) => *present,
+                        None => self.index.contains(key)?,
+                    };
+                    if !present {
+                        return Err(Error::KeyNotFound(key.clone()));
+                    }
+                    changes.insert(key.clone(), false);
+                }
+                Operation::SetSchemaVersion(..) | Operation::SetVersion(..) => {}
+            }
+        }
+        // Every put bumps the version of the key. The versions are resolved
+        // here rather than when the journal is replayed, so that replaying it
+        // again stores the same versions.
+        let mut put_keys = BTreeSet::new();
+        for operation in &operations {
+            if let Operation::Put(key, ..) = operation {
+                put_keys.insert(key.clone());
+            }
+        }
+        for key in put_keys {
+            if changes.get(&key) == Some(&true) {
+                let version = self.version(&key)? + 1;
+                operations.push(Operation::SetVersion(key, version));
             }
+        }
+
+        let journal = Journal { operations };
+        self.backend
+            .save(self.journal_id().as_bytes(), &self.codec.encode(&journal)?)?;
+        self.replay(journal)
+    }
+
+    // Return up to `limit` keys within the range, in ascending order, without
+    // loading their values. Pass the `next_cursor` of the returned page to
+    // get the following keys.
+    pub fn scan_range(
+        &self,
+        start: Bound<&str>,
+        end: Bound<&str>,
+        cursor: Option<&str>,
+        limit: usize,
+    ) -> Result<KeyPage> {
+        let in_range = |key: &str| match end {
+            Bound::Included(end) => key <= end,
+            Bound::Excluded(end) => key < end,
+            Bound::Unbounded => true,
         };
-        Ok(())
+        self.index.scan(start, in_range, cursor, limit)
     }
 
-    pub fn clear(&mut self) -> Result<()> {
-        for key in self.key_list.clone() {
-            self.delete(&key)?;
+    // Return up to `limit` keys starting with `prefix`, in ascending order,
+    // without loading their values. Pass the `next_cursor` of the returned
+    // page to get the following keys.
+    pub fn scan_prefix(&self, prefix: &str, cursor: Option<&str>, limit: usize) -> Result<KeyPage> {
+        self.index.scan(
+            Bound::Included(prefix),
+            |key| key.starts_with(prefix),
+            cursor,
+            limit,
+        )
+    }
+
+    // The serialization format of the database.
+    pub fn codec(&self) -> &C {
+        &self.codec
+    }
+
+    // The schema version of the records of the table, as last recorded by
+    // Transaction::set_schema_version().
+    pub fn schema_version(&self, table: &str) -> u32 {
+        self.schema.get(table).copied().unwrap_or(0)
+    }
+
+    // The version of the record of the key, which every commit putting the
+    // key increments: 0 if the key is not in the database, and 1 for the
+    // records stored before the versions were.
+    pub fn version(&self, key: &str) -> Result<u64> {
+        match self.stored_version(key)? {
+            Some(version) => Ok(version),
+            None => Ok(self.index.contains(key)? as u64),
+        }
+    }
+
+    // Load the index roots and the schema versions again, as stored by the
+    // last commit of any instance of the database. Reads otherwise see the
+    // database as of the last commit or reload of this instance, and may fail
+    // with Error::MissingObject once another instance deleted the index pages
+    // it replaced.
+    pub fn reload(&mut self) -> Result<()> {
+        if let Some(index) = Index::load(&self.name, self.codec.clone(), self.backend.clone())? {
+            self.index = index;
+        }
+        let secondary_name = format!("{}.secondary", self.name);
+        if let Some(secondary) =
+            Index::load(&secondary_name, self.codec.clone(), self.backend.clone())?
+        {
+            self.secondary = secondary;
+        }
+        if let Some(data) = self.backend.load(self.schema_id().as_bytes())? {
+            self.schema = self.codec.decode(&data)?;
         }
         Ok(())
     }
 
+    // Return the (entry, key) pairs of the secondary index, ordered by entry
+    // then key, from the first entry not less than `start` while `in_range`
+    // holds for the entry.
+    pub fn scan_index_entries(
+        &self,
+        start: &str,
+        in_range: impl Fn(&str) -> bool,
+    ) -> Result<Vec<(String, String)>> {
+        let start = format!("f{}{}", SEPARATOR, start);
+        let in_range = |entry: &str| match split_forward_entry(entry) {
+            Some((entry, _)) => in_range(entry),
+            None => false,
+        };
+        let page = self
+            .secondary
+            .scan(Bound::Included(&start), in_range, None, usize::MAX)?;
+        Ok(page
+            .keys
+            .iter()
+            .filter_map(|entry| split_forward_entry(entry))
+            .map(|(entry, key)| (entry.to_string(), key.to_string()))
+            .collect())
+    }
+
+    // Export the records of the selected tables, with their secondary index
+    // entries and the schema versions of the tables, as a backup sealed with
+    // `cipher`, see backup.rs.
+    pub fn export_backup(
+        &self,
+        cipher: &impl BackupCipher,
+        tables: BackupTables,
+    ) -> Result<Vec<u8>> {
+        let table_backup = |name: &str| TableBackup {
+            name: name.to_string(),
+            schema_version: self.schema_version(name),
+            records: Vec::new(),
+        };
+        // the selected tables are exported even if they are empty, so that
+        // importing them empties them as well
+        let mut selected: BTreeMap<String, TableBackup> = match tables {
+            BackupTables::All => self.schema.keys().map(|name| name.as_str()).collect(),
+            BackupTables::Only(names) => names.to_vec(),
+        }
+        .into_iter()
+        .map(|name| (name.to_string(), table_backup(name)))
+        .collect();
+        for key in self.keys(Bound::Unbounded, |_| true)? {
+            if !tables.contains(table_of(&key)) {
+                continue;
+            }
+            let table = selected
+                .entry(table_of(&key).to_string())
+                .or_insert_with(|| table_backup(table_of(&key)));
+            table.records.push(RecordBackup {
+                value: Zeroizing::new(self.load(&key)?),
+                index_entries: self.index_entries(&key)?,
+                key,
+            });
+        }
+        let contents = BackupContents {
+            codec: C::NAME.to_string(),
+            tables: selected.into_values().collect(),
+        };
+        backup::seal(cipher, &contents)
+    }
+
+    // Restore the selected tables of a backup made by export_backup(),
+    // possibly on another device, in a single transaction. The restored
+    // tables are replaced by their contents in the backup, the other tables
+    // are left as they are. The backup must have been exported from a
+    // database with the same codec.
+    pub fn import_backup(
+        &mut self,
+        cipher: &impl BackupCipher,
+        backup: &[u8],
+        tables: BackupTables,
+    ) -> Result<()> {
+        // the restored tables are emptied from the latest index, which
+        // another client may have changed since it was loaded
+        self.reload()?;
+        let contents = backup::open(cipher, backup)?;
+        if contents.codec != C::NAME {
+            return Err(Error::CodecMismatch {
+                stored: contents.codec,
+                expected: C::NAME,
+            });
+        }
+        if let BackupTables::Only(names) = tables {
+            if let Some(name) = names
+                .iter()
+                .find(|name| !contents.tables.iter().any(|table| table.name == **name))
+            {
+                return Err(Error::BackupFormat(format!(
+                    "table {} is not in the backup",
+                    name
+                )));
+            }
+        }
+
+        let restored: Vec<TableBackup> = contents
+            .tables
+            .into_iter()
+            .filter(|table| tables.contains(&table.name))
+            .collect();
+        let mut keys = BTreeSet::new();
+        for table in &restored {
+            for record in &table.records {
+                if table_of(&record.key) != table.name {
+                    return Err(Error::BackupFormat(format!(
+                        "key {} is not in table {}",
+                        record.key, table.name
+                    )));
+                }
+                keys.insert(record.key.as_str());
+            }
+        }
+        let mut transaction = self.begin();
+        for key in self.keys(Bound::Unbounded, |_| true)? {
+            if restored.iter().any(|table| table.name == table_of(&key))
+                && !keys.contains(key.as_str())
+            {
+                transaction.delete(key);
+            }
+        }
+        for table in restored {
+            for record in table.records {
+                transaction.put_indexed(record.key, record.value.to_vec(), record.index_entries);
+            }
+            transaction.set_schema_version(table.name, table.schema_version);
+        }
+        self.commit(transaction)
+    }
+
     pub fn list_entries_with_prefix(&self, prefix: &str) -> Result<HashMap<String, Vec<u8>>> {
         let mut result = HashMap::new();
-        for key in &self.key_list {
-            if key.starts_with(prefix) {
-                let value = self.get(key)?;
-                result.insert(key.clone(), value);
-            }
+        for key in self.keys(Bound::Included(prefix), |key| key.starts_with(prefix))? {
+            let value = self.load(&key)?;
+            result.insert(key, value);
         }
         Ok(result)
     }
 
-    fn store_key_list(&self) -> Result<()> {
-        let key_list = bincode::serialize(&self.key_list)?;
-        save_in_secure_storage(self.name.as_bytes(), &key_list)?;
+    fn keys(&self, start: Bound<&str>, in_range: impl Fn(&str) -> bool) -> Result<Vec<String>> {
+        Ok(self.index.scan(start, in_range, None, usize::MAX)?.keys)
+    }
+
+    fn load(&self, key: &str) -> Result<Vec<u8>> {
+        self.backend
+            .load(key.as_bytes())?
+            .ok_or_else(|| Error::MissingObject(key.to_string()))
+    }
+
+    // The secondary index entries of the key, as stored.
+    fn index_entries(&self, key: &str) -> Result<Vec<String>> {
+        let prefix = reverse_entry(key, "");
+        let page = self.secondary.scan(
+            Bound::Included(&prefix),
+            |entry| entry.starts_with(&prefix),
+            None,
+            usize::MAX,
+        )?;
+        Ok(page
+            .keys
+            .iter()
+            .map(|entry| entry[prefix.len()..].to_string())
+            .collect())
+    }
+
+    fn stored_version(&self, key: &str) -> Result<Option<u64>> {
+        let prefix = version_entry(key, "");
+        let page = self.secondary.scan(
+            Bound::Included(&prefix),
+            |entry| entry.starts_with(&prefix),
+            None,
+            usize::MAX,
+        )?;
+        Ok(page
+            .keys
+            .iter()
+            .find_map(|entry| parse_version_entry(&prefix, entry)))
+    }
+
+    fn schema_id(&self) -> String {
+        format!("{}.schema", self.name)
+    }
+
+    fn journal_id(&self) -> String {
+        format!("{}.journal", self.name)
+    }
+
+    // Whether `key` is the object id of the index, the journal, the schema
+    // versions or the codec of the database, which a record would overwrite.
+    fn is_internal_id(&self, key: &str) -> bool {
+        let is_index_id = |id: &str| id == "index" || id.starts_with("index.");
+        match key
+            .strip_prefix(self.name.as_str())
+            .and_then(|id| id.strip_prefix('.'))
+        {
+            Some(id) => {
+                matches!(id, "journal" | "schema" | "codec")
+                    || is_index_id(id)
+                    || id.strip_prefix("secondary.").map_or(false, is_index_id)
+            }
+            None => false,
+        }
+    }
+
+    fn recover(&mut self) -> Result<()> {
+        match self.backend.load(self.journal_id().as_bytes())? {
+            Some(data) => {
+                self.replay(self.codec.decode(&data)?)?;
+                // the interrupted commit may have left index pages behind
+                self.index.collect_garbage()?;
+                self.secondary.collect_garbage()
+            }
+            None => Ok(()),
+        }
+    }
+
+    fn replay(&mut self, journal: Journal) -> Result<()> {
+        let mut changes = BTreeMap::new();
+        let mut secondary_changes = BTreeMap::new();
+        let mut schema = self.schema.clone();
+        for operation in journal.operations {
+            match operation {
+                Operation::Put(key, value, index_entries) => {
+                    self.backend.save(key.as_bytes(), &value)?;
+                    self.reindex(&key, &index_entries, &mut secondary_changes)?;
+                    changes.insert(key, true);
+                }
+                Operation::Delete(key) => {
+                    self.backend.delete(key.as_bytes())?;
+                    self.reindex(&key, &[], &mut secondary_changes)?;
+                    self.set_version(&key, None, &mut secondary_changes)?;
+                    changes.insert(key, false);
+                }
+                Operation::SetSchemaVersion(table, version) => {
+                    schema.insert(table, version);
+                }
+                Operation::SetVersion(key, version) => {
+                    self.set_version(&key, Some(version), &mut secondary_changes)?;
+                }
+            }
+        }
+        self.index.apply(&changes)?;
+        self.secondary.apply(&secondary_changes)?;
+        if schema != self.schema {
+            self.backend
+                .save(self.schema_id().as_bytes(), &self.codec.encode(&schema)?)?;
+            self.schema = schema;
+        }
+        self.backend.delete(self.journal_id().as_bytes())
+    }
+
+    // Replace the secondary index entries of `key`, as stored and updated by
+    // the pending `changes`, with `index_entries`.
+    fn reindex(
+        &self,
+        key: &str,
+        index_entries: &[String],
+        changes: &mut BTreeMap<String, bool>,
+    ) -> Result<()> {
+        let prefix = reverse_entry(key, "");
+        for reverse in self.pending_entries(&prefix, changes)? {
+            let index_entry = &reverse[prefix.len()..];
+            changes.insert(forward_entry(index_entry, key), false);
+            changes.insert(reverse, false);
+        }
+        for index_entry in index_entries {
+            changes.insert(forward_entry(index_entry, key), true);
+            changes.insert(reverse_entry(key, index_entry), true);
+        }
+        Ok(())
+    }
+
+    // Replace the version entry of `key`, as stored and updated by the pending
+    // `changes`, with the one of `version`, if any.
+    fn set_version(
+        &self,
+        key: &str,
+        version: Option<u64>,
+        changes: &mut BTreeMap<String, bool>,
+    ) -> Result<()> {
+        let prefix = version_entry(key, "");
+        for entry in self.pending_entries(&prefix, changes)? {
+            if parse_version_entry(&prefix, &entry).is_some() {
+                changes.insert(entry, false);
+            }
+        }
+        if let Some(version) = version {
+            changes.insert(version_entry(key, &format!("{:016x}", version)), true);
+        }
         Ok(())
     }
+
+    // The entries of the secondary index starting with `prefix`, as stored
+    // and updated by the pending `changes`.
+    fn pending_entries(
+        &self,
+        prefix: &str,
+        changes: &BTreeMap<String, bool>,
+    ) -> Result<BTreeSet<String>> {
+        let mut current: BTreeSet<String> = self
+            .secondary
+            .scan(
+                Bound::Included(prefix),
+                |entry| entry.starts_with(prefix),
+                None,
+                usize::MAX,
+            )?
+            .keys
+            .into_iter()
+            .collect();
+        for (entry, present) in changes.range(prefix.to_string()..) {
+            if !entry.starts_with(prefix) {
+                break;
+            }
+            if *present {
+                current.insert(entry.clone());
+            } else {
+                current.remove(entry);
+            }
+        }
+        Ok(current)
+    }
+}
+
+const LEGACY_CODEC: &str = "bincode";
+
+// A database records its codec when it is created. One without the record is
+// either new, or was created with Bincode before the codec was selectable.
+fn check_codec<C: Codec, B: StorageBackend>(name: &str, backend: &B) -> Result<()> {
+    let codec_id = format!("{}.codec", name);
+    let stored = match backend.load(codec_id.as_bytes())? {
+        Some(data) => String::from_utf8_lossy(&data).into_owned(),
+        None if Index::<C, B>::is_stored(name, backend)?
+            || backend.load(name.as_bytes())?.is_some() =>
+        {
+            LEGACY_CODEC.to_string()
+        }
+        None => {
+            backend.save(codec_id.as_bytes(), C::NAME.as_bytes())?;
+            return Ok(());
+        }
+    };
+    if stored != C::NAME {
+        return Err(Error::CodecMismatch {
+            stored,
+            expected: C::NAME,
+        });
+    }
+    Ok(())
+}
+
+// Keys of the secondary index: "f\0$ENTRY\0$KEY" to find the keys by entry and
+// "r\0$KEY\0$ENTRY" to find the entries of a key. It also holds the version
+// of each key as "v\0$KEY\0$VERSION", with the version as 16 hex digits.
+
+const SEPARATOR: char = '\0';
+
+fn forward_entry(index_entry: &str, key: &str) -> String {
+    format!("f{}{}{}{}", SEPARATOR, index_entry, SEPARATOR, key)
+}
+
+fn reverse_entry(key: &str, index_entry: &str) -> String {
+    format!("r{}{}{}{}", SEPARATOR, key, SEPARATOR, index_entry)
+}
+
+fn version_entry(key: &str, version: &str) -> String {
+    format!("v{}{}{}{}", SEPARATOR, key, SEPARATOR, version)
+}
+
+// The version of a version entry of the key given by `prefix`, or None if the
+// entry is the one of another key which starts with the key and a NUL.
+fn parse_version_entry(prefix: &str, entry: &str) -> Option<u64> {
+    let version = entry.strip_prefix(prefix)?;
+    if version.len() != 16 {
+        return None;
+    }
+    u64::from_str_radix(version, 16).ok()
+}
+
+fn split_forward_entry(entry: &str) -> Option<(&str, &str)> {
+    entry
+        .strip_prefix('f')?
+        .strip_prefix(SEPARATOR)?
+        .split_once(SEPARATOR)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Bincode, FaultyBackend, MemoryBackend};
+
+    fn open<B: StorageBackend>(backend: &B) -> SecureStorageDb<Bincode, B> {
+        SecureStorageDb::open_with_backend("db".to_string(), Bincode, backend.clone()).unwrap()
+    }
+
+    fn entries<B: StorageBackend>(db: &SecureStorageDb<Bincode, B>) -> Vec<(String, Vec<u8>)> {
+        let mut entries: Vec<_> = db
+            .list_entries_with_prefix("")
+            .unwrap()
+            .into_iter()
+            .collect();
+        entries.sort();
+        entries
+    }
+
+    #[test]
+    fn test_put_get_delete() {
+        let backend = MemoryBackend::new();
+        let mut db = open(&backend);
+        db.put("a".to_string(), b"1".to_vec()).unwrap();
+        db.put("b".to_string(), b"2".to_vec()).unwrap();
+        db.put("a".to_string(), b"3".to_vec()).unwrap();
+        assert_eq!(db.get("a").unwrap(), b"3");
+        db.delete("b").unwrap();
+        assert!(db.get("b").is_err());
+        assert!(db.delete("b").is_err());
+
+        let mut db = open(&backend);
+        assert_eq!(entries(&db), vec![("a".to_string(), b"3".to_vec())]);
+        db.clear().unwrap();
+        assert!(entries(&db).is_empty());
+        assert!(backend.load(b"a").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_internal_ids() {
+        let backend = MemoryBackend::new();
+        let mut db = open(&backend);
+        let mut transaction = db.begin();
+        transaction.put_indexed("a".to_string(), b"1".to_vec(), vec!["e".to_string()]);
+        db.commit(transaction).unwrap();
+        for key in [
+            "db.journal",
+            "db.schema",
+            "db.codec",
+            "db.index",
+            "db.index.0",
+            "db.secondary.index",
+            "db.secondary.index.0",
+        ] {
+            assert!(matches!(
+                db.put(key.to_string(), b"2".to_vec()),
+                Err(Error::InvalidKey(_))
+            ));
+        }
+        // the metadata and the index are left intact
+        let mut db = open(&backend);
+        assert_eq!(entries(&db), vec![("a".to_string(), b"1".to_vec())]);
+        assert_eq!(
+            backend.load(b"db.codec").unwrap().unwrap(),
+            Bincode::NAME.as_bytes()
+        );
+        assert_eq!(db.index_entries("a").unwrap(), vec!["e".to_string()]);
+
+        // keys which only look alike are records
+        for key in ["db.journals", "db.indexes", "db.secondary", "other.journal"] {
+            db.put(key.to_string(), b"3".to_vec()).unwrap();
+            assert_eq!(db.get(key).unwrap(), b"3");
+        }
+    }
+
+    #[test]
+    fn test_commit_is_atomic() {
+        let backend = MemoryBackend::new();
+        let mut db = open(&backend);
+        db.put("a".to_string(), b"1".to_vec()).unwrap();
+
+        let mut transaction = db.begin();
+        transaction.put("b".to_string(), b"2".to_vec());
+        transaction.delete("a".to_string());
+        transaction.delete("a".to_string());
+        assert!(db.commit(transaction).is_err());
+        assert_eq!(entries(&db), vec![("a".to_string(), b"1".to_vec())]);
+
+        let mut transaction = db.begin();
+        transaction.put("b".to_string(), b"2".to_vec());
+        transaction.delete("a".to_string());
+        transaction.put("a".to_string(), b"3".to_vec());
+        db.commit(transaction).unwrap();
+        assert_eq!(
+            entries(&open(&backend)),
+            vec![
+                ("a".to_string(), b"3".to_vec()),
+                ("b".to_string(), b"2".to_vec())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_interrupted_commit() {
+        let commit = |db: &mut SecureStorageDb<Bincode, FaultyBackend<MemoryBackend>>| {
+            let mut transaction = db.begin();
+            transaction.put_indexed("b".to_string(), b"2".to_vec(), vec!["t#i#01".to_string()]);
+            transaction.delete("a".to_string());
+            transaction.set_schema_version("t".to_string(), 1);
+            db.commit(transaction)
+        };
+        let setup = || {
+            let backend = FaultyBackend::new(MemoryBackend::new());
+            let mut db = open(&backend);
+            db.put("a".to_string(), b"1".to_vec()).unwrap();
+            (backend, db)
+        };
+
+        let (backend, mut db) = setup();
+        let start = backend.writes();
+        commit(&mut db).unwrap();
+        let writes = backend.writes() - start;
+        let committed = backend.inner().clone();
+
+        for fail_after in 0..writes {
+            let (backend, mut db) = setup();
+            backend.fail_after(fail_after);
+            assert!(commit(&mut db).is_err());
+            backend.heal();
+            let recovered = open(&backend);
+            // once the journal is stored, the commit is completed on open
+            let expected = match fail_after {
+                0 => setup().0.inner().clone(),
+                _ => committed.clone(),
+            };
+            let expected_db = open(&expected);
+            assert_eq!(entries(&recovered), entries(&expected_db));
+            assert_eq!(
+                recovered.schema_version("t"),
+                expected_db.schema_version("t")
+            );
+            assert_eq!(
+                recovered.scan_index_entries("", |_| true).unwrap(),
+                expected_db.scan_index_entries("", |_| true).unwrap()
+            );
+            // no journal nor orphan index pages are left behind
+            for prefix in [&b"db.journal"[..], b"db.index.", b"db.secondary.index."] {
+                assert_eq!(
+                    backend.list(prefix).unwrap().len(),
+                    expected.list(prefix).unwrap().len()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_scan() {
+        let backend = MemoryBackend::new();
+        let mut db = open(&backend);
+        let mut transaction = db.begin();
+        for i in 0..1000 {
+            transaction.put(format!("k{:04}", i), Vec::new());
+        }
+        transaction.put("other".to_string(), Vec::new());
+        db.commit(transaction).unwrap();
+
+        let db = open(&backend);
+        let mut keys = Vec::new();
+        let mut cursor = None;
+        loop {
+            let page = db.scan_prefix("k", cursor.as_deref(), 300).unwrap();
+            keys.extend(page.keys);
+            cursor = page.next_cursor;
+            if cursor.is_none() {
+                break;
+            }
+        }
+        assert_eq!(
+            keys,
+            (0..1000).map(|i| format!("k{:04}", i)).collect::<Vec<_>>()
+        );
+
+        let page = db
+            .scan_range(Bound::Excluded("k0997"), Bound::Unbounded, None, 10)
+            .unwrap();
+        assert_eq!(page.keys, vec!["k0998", "k0999", "other"]);
+        assert_eq!(page.next_cursor, None);
+    }
+
+    #[test]
+    fn test_secondary_index() {
+        let backend = MemoryBackend::new();
+        let mut db = open(&backend);
+        let mut transaction = db.begin();
+        transaction.put_indexed("a".to_string(), Vec::new(), vec!["t#i#01".to_string()]);
+        transaction.put_indexed("b".to_string(), Vec::new(), vec!["t#i#02".to_string()]);
+        db.commit(transaction).unwrap();
+
+        let mut transaction = db.begin();
+        transaction.put_indexed("a".to_string(), Vec::new(), vec!["t#i#02".to_string()]);
+        transaction.delete("b".to_string());
+        db.commit(transaction).unwrap();
+        assert_eq!(
+            open(&backend).scan_index_entries("", |_| true).unwrap(),
+            vec![("t#i#02".to_string(), "a".to_string())]
+        );
+
+        let mut transaction = db.begin();
+        transaction.put_indexed("a\0b".to_string(), Vec::new(), vec!["t#i#03".to_string()]);
+        assert!(db.commit(transaction).is_err());
+    }
+
+    #[test]
+    fn test_legacy_key_list() {
+        let backend = MemoryBackend::new();
+        let keys: HashSet<String> = ["a", "b"].iter().map(|key| key.to_string()).collect();
+        backend
+            .save(b"db", &bincode::serialize(&keys).unwrap())
+            .unwrap();
+        backend.save(b"a", b"1").unwrap();
+        backend.save(b"b", b"2").unwrap();
+
+        let db = open(&backend);
+        assert_eq!(
+            entries(&db),
+            vec![
+                ("a".to_string(), b"1".to_vec()),
+                ("b".to_string(), b"2".to_vec())
+            ]
+        );
+        assert!(backend.load(b"db").unwrap().is_none());
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    struct OtherCodec;
+
+    impl Codec for OtherCodec {
+        const NAME: &'static str = "other";
+
+        fn encode<T: serde::Serialize + ?Sized>(&self, value: &T) -> Result<Vec<u8>> {
+            Bincode.encode(value)
+        }
+
+        fn decode<T: serde::de::DeserializeOwned>(&self, data: &[u8]) -> Result<T> {
+            Bincode.decode(data)
+        }
+    }
+
+    #[test]
+    fn test_codec_mismatch() {
+        let backend = MemoryBackend::new();
+        open(&backend).put("a".to_string(), Vec::new()).unwrap();
+        assert!(
+            SecureStorageDb::open_with_backend("db".to_string(), OtherCodec, backend.clone())
+                .is_err()
+        );
+
+        // a database without the codec record was created with Bincode
+        backend.delete(b"db.codec").unwrap();
+        assert!(
+            SecureStorageDb::open_with_backend("db".to_string(), OtherCodec, backend.clone())
+                .is_err()
+        );
+        open(&backend);
+
+        let db = SecureStorageDb::open_with_backend("new".to_string(), OtherCodec, backend.clone());
+        assert!(db.is_ok());
+        assert!(
+            SecureStorageDb::open_with_backend("new".to_string(), Bincode, backend.clone())
+                .is_err()
+        );
+    }
 }
@@ -0,0 +1,269 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use alloc::{boxed::Box, string::String};
+use core::fmt;
+use optee_utee::ErrorKind;
+
+// Error is the error of every secure_db operation. With std, it implements
+// std::error::Error, so that it converts into anyhow::Error and the like.
+
+#[derive(Debug)]
+pub enum Error {
+    // An operation on an OP-TEE persistent object failed.
+    Storage(optee_utee::Error),
+    // An operation of a StorageBackend failed otherwise.
+    Backend(String),
+    // The key is not in the database.
+    KeyNotFound(String),
+    // An object of the database is missing from the storage.
+    MissingObject(String),
+    // The data cannot be serialized or deserialized with the codec.
+    Codec(String),
+    // The database was created with another codec.
+    CodecMismatch {
+        stored: String,
+        expected: &'static str,
+    },
+    // The table is stored at another schema version than the record type.
+    SchemaVersion {
+        table: String,
+        stored: u32,
+        expected: u32,
+    },
+    // No migration is registered from the stored version of the table.
+    NoMigration {
+        table: String,
+        from_version: u32,
+    },
+    // A record failed to migrate, nothing was written.
+    Migration {
+        key: String,
+        from_version: u32,
+        source: Box<Error>,
+    },
+    // The record type has no secondary index of this name.
+    NoIndex {
+        table: &'static str,
+        index: String,
+    },
+    // Keys must not be the object ids of the database itself, see
+    // SecureStorageDb, and indexed keys and index entries must not contain
+    // NUL.
+    InvalidKey(String),
+    // An argument is out of range, such as a zero scan limit.
+    InvalidArgument(&'static str),
+    // The key is not at the version expected by the transaction, it was
+    // updated since it was read.
+    Conflict {
+        key: String,
+        expected: u64,
+        actual: u64,
+    },
+    // The TA persistent time is not set, needs to be set again, or overflows;
+    // it is needed to tell whether a record expired.
+    TaTime(optee_utee::Error),
+    // The data is not a backup, or the backup lacks a table to import.
+    BackupFormat(String),
+    // The backup has a newer format version than this version of secure_db
+    // can read.
+    BackupVersion(u16),
+    // The backup was altered, or sealed with another key.
+    BackupAuthentication,
+    // The lock of the database was poisoned by a panic.
+    Poisoned,
+    // An error raised by the application, e.g. by a migration.
+    Custom(String),
+}
+
+pub type Result<T> = core::result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Storage(e) => write!(f, "Secure storage error: {}", e),
+            Error::Backend(e) => write!(f, "Storage backend error: {}", e),
+            Error::KeyNotFound(key) => write!(f, "Key not found in key list: {}", key),
+            Error::MissingObject(id) => write!(f, "Object not found in db: {}", id),
+            Error::Codec(e) => write!(f, "Serialization error: {}", e),
+            Error::CodecMismatch { stored, expected } => write!(
+                f,
+                "Database is stored with codec {}, not {}",
+                stored, expected
+            ),
+            Error::SchemaVersion {
+                table,
+                stored,
+                expected,
+            } => write!(
+                f,
+                "Table {} is stored at schema version {}, expected {}",
+                table, stored, expected
+            ),
+            Error::NoMigration {
+                table,
+                from_version,
+            } => write!(
+                f,
+                "No migration of table {} from schema version {}",
+                table, from_version
+            ),
+            Error::Migration {
+                key,
+                from_version,
+                source,
+            } => write!(
+                f,
+                "Failed to migrate {} from schema version {}: {}",
+                key, from_version, source
+            ),
+            Error::NoIndex { table, index } => {
+                write!(f, "No index {} on table {}", index, table)
+            }
+            Error::InvalidKey(key) => {
+                write!(f, "Reserved key, or indexed key or entry with NUL: {}", key)
+            }
+            Error::InvalidArgument(e) => write!(f, "Invalid argument: {}", e),
+            Error::Conflict {
+                key,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "Conflicting update of {}: expected version {}, found {}",
+                key, expected, actual
+            ),
+            Error::TaTime(e) => write!(f, "TA persistent time unavailable: {}", e),
+            Error::BackupFormat(e) => write!(f, "Invalid backup: {}", e),
+            Error::BackupVersion(version) => {
+                write!(f, "Unsupported backup format version {}", version)
+            }
+            Error::BackupAuthentication => write!(f, "Backup authentication failed"),
+            Error::Poisoned => write!(f, "Database lock poisoned"),
+            Error::Custom(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Storage(e) | Error::TaTime(e) => Some(e),
+            Error::Migration { source, .. } => Some(source.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl From<optee_utee::Error> for Error {
+    fn from(e: optee_utee::Error) -> Self {
+        Error::Storage(e)
+    }
+}
+
+// Converts into the error returned by the entry points of the TA, printing
+// the error to the trace output. The errors of OP-TEE keep their code.
+impl From<Error> for optee_utee::Error {
+    fn from(error: Error) -> Self {
+        optee_utee::trace_println!("[-] {}", error);
+        error.tee_error().with_context(alloc::format!("{}", error))
+    }
+}
+
+impl Error {
+    fn tee_error(&self) -> optee_utee::Error {
+        let kind = match self {
+            Error::Storage(e) | Error::TaTime(e) => return e.clone(),
+            Error::Migration { source, .. } => return source.tee_error(),
+            Error::KeyNotFound(_) | Error::MissingObject(_) => ErrorKind::ItemNotFound,
+            Error::Codec(_)
+            | Error::CodecMismatch { .. }
+            | Error::SchemaVersion { .. }
+            | Error::NoMigration { .. }
+            | Error::BackupFormat(_)
+            | Error::BackupVersion(_) => ErrorKind::BadFormat,
+            Error::NoIndex { .. } | Error::InvalidKey(_) | Error::InvalidArgument(_) => {
+                ErrorKind::BadParameters
+            }
+            Error::Conflict { .. } => ErrorKind::AccessConflict,
+            Error::BackupAuthentication => ErrorKind::MacInvalid,
+            Error::Poisoned => ErrorKind::BadState,
+            Error::Backend(_) | Error::Custom(_) => ErrorKind::Generic,
+        };
+        kind.into()
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<bincode::Error> for Error {
+    fn from(e: bincode::Error) -> Self {
+        Error::Codec(e.to_string())
+    }
+}
+
+impl From<postcard::Error> for Error {
+    fn from(e: postcard::Error) -> Self {
+        Error::Codec(alloc::format!("{}", e))
+    }
+}
+
+#[cfg(feature = "json")]
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Self {
+        Error::Codec(alloc::format!("{}", e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+
+    #[test]
+    fn test_tee_error() {
+        let storage = optee_utee::Error::new(ErrorKind::StorageNoSpace);
+        assert_eq!(
+            Error::Storage(storage.clone()).tee_error().kind(),
+            ErrorKind::StorageNoSpace
+        );
+        let migration = Error::Migration {
+            key: "T#1".to_string(),
+            from_version: 1,
+            source: Box::new(Error::Storage(storage)),
+        };
+        assert_eq!(migration.tee_error().kind(), ErrorKind::StorageNoSpace);
+        assert_eq!(
+            Error::KeyNotFound("T#1".to_string()).tee_error().kind(),
+            ErrorKind::ItemNotFound
+        );
+        assert_eq!(
+            Error::Conflict {
+                key: "T#1".to_string(),
+                expected: 1,
+                actual: 2
+            }
+            .tee_error()
+            .kind(),
+            ErrorKind::AccessConflict
+        );
+        assert_eq!(
+            Error::Custom("failed".to_string()).tee_error().kind(),
+            ErrorKind::Generic
+        );
+    }
+}
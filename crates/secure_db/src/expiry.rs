@@ -0,0 +1,54 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::{Error, Result};
+use alloc::{format, string::String};
+use optee_utee::Time;
+
+// A record expires when Storable::expires_at() returns a time, in seconds of
+// the TA persistent time, which is not in the future. Expired records are no
+// longer returned by SecureStorageClient, and are deleted by
+// SecureStorageClient::purge_expired().
+//
+// The TA persistent time is not set until the TA sets it with
+// Time::set_ta_time(), and needs to be set again when OP-TEE detects that it
+// was rolled back. Until then, whether a record expired cannot be told, so
+// reading a record which carries an expiry fails with Error::TaTime, and
+// purge_expired() deletes nothing; records without an expiry are not affected.
+//
+// The expiry of each record is kept in the secondary index as an entry
+// "#expiry#$EXPIRES_AT", with the time as 16 hex digits, so that expired
+// records are found without reading them. Table names never start with "#",
+// so this entry cannot be mistaken for an index of a table.
+
+pub(crate) const EXPIRY_PREFIX: &str = "#expiry#";
+
+// The current TA persistent time, in seconds.
+pub fn ta_time_now() -> Result<u64> {
+    let mut time = Time::new();
+    time.ta_time().map_err(Error::TaTime)?;
+    Ok(time.seconds as u64)
+}
+
+// The TA persistent time in `seconds`, to set as the expiry of a record.
+pub fn expires_in(seconds: u64) -> Result<u64> {
+    Ok(ta_time_now()?.saturating_add(seconds))
+}
+
+pub(crate) fn expiry_entry(expires_at: u64) -> String {
+    format!("{}{:016x}", EXPIRY_PREFIX, expires_at)
+}
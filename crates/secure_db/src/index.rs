@@ -0,0 +1,316 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::{Codec, Error, Result, StorageBackend};
+use alloc::{
+    collections::{btree_map, BTreeMap, BTreeSet},
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::ops::Bound;
+use serde::{Deserialize, Serialize};
+
+// Index is the ordered set of keys of a SecureStorageDb, stored as a two level
+// B-tree across several objects:
+// - the root, named as "$db_name.index", lists the pages with their first key,
+// - each page, named as "$db_name.index.$page_id", holds up to PAGE_CAPACITY
+//   sorted keys.
+// Only the root is kept in memory. A lookup reads one page, and an update only
+// rewrites the pages it touches and the root.
+//
+// Pages are copy-on-write: an updated page is stored under a new id and the
+// root is switched to the new pages in a single write, so the index in the
+// secure storage is consistent at any time. The replaced pages are deleted
+// afterwards; pages left over by an interrupted update are removed by
+// collect_garbage().
+
+const PAGE_CAPACITY: usize = 128;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+struct Root {
+    pages: Vec<PageRef>,
+    next_page_id: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct PageRef {
+    id: u64,
+    first_key: String,
+}
+
+// KeyPage is one page of the keys returned by a scan, in ascending order.
+// Pass `next_cursor` to the following scan to continue after the last key;
+// it is None once the scan is complete.
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct KeyPage {
+    pub keys: Vec<String>,
+    pub next_cursor: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Index<C: Codec, B: StorageBackend> {
+    name: String,
+    root: Root,
+    codec: C,
+    backend: B,
+}
+
+impl<C: Codec, B: StorageBackend> Index<C, B> {
+    // An empty index, which is stored by the first update.
+    pub(crate) fn new(db_name: &str, codec: C, backend: B) -> Self {
+        Self {
+            name: db_name.to_string(),
+            root: Root::default(),
+            codec,
+            backend,
+        }
+    }
+
+    // Load the index of the database, or None if it has never been stored.
+    pub(crate) fn load(db_name: &str, codec: C, backend: B) -> Result<Option<Self>> {
+        let mut index = Self::new(db_name, codec, backend);
+        match index.backend.load(index.root_id().as_bytes())? {
+            Some(data) => {
+                index.root = index.codec.decode(&data)?;
+                Ok(Some(index))
+            }
+            None => Ok(None),
+        }
+    }
+
+    // Whether the index of the database has ever been stored.
+    pub(crate) fn is_stored(db_name: &str, backend: &B) -> Result<bool> {
+        Ok(backend.load(root_id(db_name).as_bytes())?.is_some())
+    }
+
+    // Build and store the index of the given keys.
+    pub(crate) fn create(
+        db_name: &str,
+        codec: C,
+        backend: B,
+        keys: impl IntoIterator<Item = String>,
+    ) -> Result<Self> {
+        let mut index = Self::new(db_name, codec, backend);
+        index.apply(&keys.into_iter().map(|key| (key, true)).collect())?;
+        if index.root.pages.is_empty() {
+            index.store_root(&index.root)?;
+        }
+        Ok(index)
+    }
+
+    pub(crate) fn contains(&self, key: &str) -> Result<bool> {
+        match self.locate(key) {
+            Some(position) => {
+                let keys = self.load_page(self.root.pages[position].id)?;
+                Ok(keys.binary_search_by(|k| k.as_str().cmp(key)).is_ok())
+            }
+            None => Ok(false),
+        }
+    }
+
+    // Insert the keys mapped to true and remove those mapped to false.
+    // The in-memory index is only updated once the new root is stored.
+    pub(crate) fn apply(&mut self, changes: &BTreeMap<String, bool>) -> Result<()> {
+        let mut dirty: BTreeMap<usize, BTreeSet<String>> = BTreeMap::new();
+        let mut changed = BTreeSet::new();
+        for (key, present) in changes {
+            // the first key of an empty index goes to a new page
+            let position = self.locate(key).unwrap_or(0);
+            let keys = match dirty.entry(position) {
+                btree_map::Entry::Occupied(entry) => entry.into_mut(),
+                btree_map::Entry::Vacant(entry) => {
+                    let keys = match self.root.pages.get(position) {
+                        Some(page) => self.load_page(page.id)?.into_iter().collect(),
+                        None => BTreeSet::new(),
+                    };
+                    entry.insert(keys)
+                }
+            };
+            let updated = if *present {
+                keys.insert(key.clone())
+            } else {
+                keys.remove(key)
+            };
+            if updated {
+                changed.insert(position);
+            }
+        }
+        if changed.is_empty() {
+            return Ok(());
+        }
+
+        let mut root = Root {
+            pages: Vec::new(),
+            next_page_id: self.root.next_page_id,
+        };
+        let mut written = Vec::new();
+        let mut replaced = Vec::new();
+        let slots = self.root.pages.len().max(1);
+        for position in 0..slots {
+            let page = self.root.pages.get(position);
+            if !changed.contains(&position) {
+                root.pages.extend(page.cloned());
+                continue;
+            }
+            replaced.extend(page.map(|page| page.id));
+            let keys: Vec<String> = dirty
+                .remove(&position)
+                .unwrap_or_default()
+                .into_iter()
+                .collect();
+            // split an overflowing page into evenly filled pages, drop an
+            // empty one
+            let count = keys.len().div_ceil(PAGE_CAPACITY);
+            if count == 0 {
+                continue;
+            }
+            for chunk in keys.chunks(keys.len().div_ceil(count)) {
+                let id = root.next_page_id;
+                root.next_page_id += 1;
+                root.pages.push(PageRef {
+                    id,
+                    first_key: chunk[0].clone(),
+                });
+                written.push((id, chunk.to_vec()));
+            }
+        }
+
+        for (id, keys) in &written {
+            self.backend
+                .save(self.page_id(*id).as_bytes(), &self.codec.encode(keys)?)?;
+        }
+        self.store_root(&root)?;
+        self.root = root;
+        for id in replaced {
+            self.backend.delete(self.page_id(id).as_bytes())?;
+        }
+        Ok(())
+    }
+
+    // Return up to `limit` keys, in ascending order, from `start` while
+    // `in_range` holds, skipping the keys up to and including `cursor`.
+    pub(crate) fn scan(
+        &self,
+        start: Bound<&str>,
+        in_range: impl Fn(&str) -> bool,
+        cursor: Option<&str>,
+        limit: usize,
+    ) -> Result<KeyPage> {
+        if limit == 0 {
+            return Err(Error::InvalidArgument("scan limit must not be zero"));
+        }
+        let lower = match (start, cursor) {
+            (Bound::Unbounded, Some(cursor)) => Bound::Excluded(cursor),
+            (Bound::Included(key) | Bound::Excluded(key), Some(cursor)) if cursor >= key => {
+                Bound::Excluded(cursor)
+            }
+            (start, _) => start,
+        };
+        let first_position = match lower {
+            Bound::Included(key) | Bound::Excluded(key) => self.locate(key).unwrap_or(0),
+            Bound::Unbounded => 0,
+        };
+
+        let mut result = KeyPage::default();
+        for page in self.root.pages.iter().skip(first_position) {
+            for key in self.load_page(page.id)? {
+                let above_lower = match lower {
+                    Bound::Included(lower) => key.as_str() >= lower,
+                    Bound::Excluded(lower) => key.as_str() > lower,
+                    Bound::Unbounded => true,
+                };
+                if !above_lower {
+                    continue;
+                }
+                if !in_range(&key) {
+                    return Ok(result);
+                }
+                if result.keys.len() == limit {
+                    result.next_cursor = result.keys.last().cloned();
+                    return Ok(result);
+                }
+                result.keys.push(key);
+            }
+        }
+        Ok(result)
+    }
+
+    // Delete the pages which are not referenced by the root, left over by an
+    // update that was interrupted after or before the root was stored.
+    pub(crate) fn collect_garbage(&self) -> Result<()> {
+        let prefix = self.page_id_prefix();
+        let referenced: BTreeSet<u64> = self.root.pages.iter().map(|page| page.id).collect();
+        let mut orphans = Vec::new();
+        for object_id in self.backend.list(prefix.as_bytes())? {
+            let id = core::str::from_utf8(&object_id[prefix.len()..])
+                .ok()
+                .and_then(|id| id.parse::<u64>().ok());
+            match id {
+                Some(id) if !referenced.contains(&id) => orphans.push(object_id),
+                _ => {}
+            }
+        }
+        for object_id in orphans {
+            self.backend.delete(&object_id)?;
+        }
+        Ok(())
+    }
+
+    // Position of the page which holds `key` if it is present, which is the
+    // last page whose first key is not greater than `key`.
+    fn locate(&self, key: &str) -> Option<usize> {
+        if self.root.pages.is_empty() {
+            return None;
+        }
+        let position = self
+            .root
+            .pages
+            .partition_point(|page| page.first_key.as_str() <= key);
+        Some(position.saturating_sub(1))
+    }
+
+    fn load_page(&self, id: u64) -> Result<Vec<String>> {
+        let data = self
+            .backend
+            .load(self.page_id(id).as_bytes())?
+            .ok_or_else(|| Error::MissingObject(self.page_id(id)))?;
+        self.codec.decode(&data)
+    }
+
+    fn store_root(&self, root: &Root) -> Result<()> {
+        self.backend
+            .save(self.root_id().as_bytes(), &self.codec.encode(root)?)
+    }
+
+    fn root_id(&self) -> String {
+        root_id(&self.name)
+    }
+
+    fn page_id_prefix(&self) -> String {
+        format!("{}.index.", self.name)
+    }
+
+    fn page_id(&self, id: u64) -> String {
+        format!("{}{}", self.page_id_prefix(), id)
+    }
+}
+
+fn root_id(db_name: &str) -> String {
+    format!("{}.index", db_name)
+}
@@ -15,11 +15,45 @@
 // specific language governing permissions and limitations
 // under the License.
 
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+// The HashMap of the API is the one of std, or of hashbrown without std.
+#[cfg(not(feature = "std"))]
+use hashbrown::HashMap;
+#[cfg(feature = "std")]
+use std::collections::HashMap;
+
 mod backend;
 pub use backend::*;
+mod backup;
+pub use backup::{BackupCipher, BackupKey, BackupTables, BACKUP_VERSION};
 mod client;
 pub use client::*;
+mod codec;
+pub use codec::*;
 mod db;
 pub use db::*;
+mod error;
+pub use error::{Error, Result};
+mod expiry;
+pub use expiry::{expires_in, ta_time_now};
+mod index;
+pub use index::KeyPage;
+mod migration;
+pub use migration::Migrations;
 mod storable;
 pub use storable::*;
+pub use secure_db_macros::Storable;
+mod sync;
+mod transaction;
+pub use transaction::Transaction;
+
+// Used by the code generated by #[derive(Storable)], which must build with
+// and without std.
+#[doc(hidden)]
+pub mod __private {
+    pub use alloc::vec;
+    pub use alloc::vec::Vec;
+}
@@ -0,0 +1,285 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::{Codec, DefaultCodec, Error, Result, SecureStorageDb, Storable, StorageBackend};
+use alloc::{
+    boxed::Box,
+    collections::BTreeMap,
+    string::{String, ToString},
+    vec::Vec,
+};
+
+// Migrations is a registry of the functions which upgrade the records of a
+// table from an older schema version to the current one, given by
+// Storable::schema_version() of the record type.
+//
+// For example, once a field is added to Wallet and its schema version is
+// bumped to 1:
+//
+//     let mut migrations = Migrations::new();
+//     migrations.register::<Wallet, _>(0, |data| {
+//         let old: WalletV0 = bincode::deserialize(data)?;
+//         Ok(Wallet::from(old))
+//     });
+//     let client = SecureStorageClient::open_with_migrations("db", migrations)?;
+//
+// The migrations are applied when the database is opened, all of them in a
+// single transaction: if any record fails to migrate, nothing is written and
+// the old records stay as they are. The migrated records are serialized with
+// the codec C of the database.
+
+type MigrateFn<C> = Box<dyn Fn(&[u8], &C) -> Result<(Vec<u8>, Vec<String>)>>;
+
+struct TableMigrations<C> {
+    prefix: String,
+    version: u32,
+    steps: BTreeMap<u32, MigrateFn<C>>,
+}
+
+pub struct Migrations<C: Codec = DefaultCodec> {
+    tables: BTreeMap<&'static str, TableMigrations<C>>,
+}
+
+impl<C: Codec> Default for Migrations<C> {
+    fn default() -> Self {
+        Self {
+            tables: BTreeMap::new(),
+        }
+    }
+}
+
+impl<C: Codec> Migrations<C> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Register the function which converts a serialized record of the table
+    // of V at `from_version` into a record of the current version.
+    pub fn register<V, F>(&mut self, from_version: u32, migrate: F) -> &mut Self
+    where
+        V: Storable + serde::Serialize,
+        F: Fn(&[u8]) -> Result<V> + 'static,
+    {
+        let table = self
+            .tables
+            .entry(V::table_name())
+            .or_insert_with(|| TableMigrations {
+                prefix: V::concat_key(""),
+                version: V::schema_version(),
+                steps: BTreeMap::new(),
+            });
+        table.steps.insert(
+            from_version,
+            Box::new(move |data, codec| {
+                let value = migrate(data)?;
+                Ok((codec.encode(&value)?, value.index_entries()))
+            }),
+        );
+        self
+    }
+
+    // Upgrade every registered table which is stored at an older version.
+    pub fn apply<B: StorageBackend>(&self, db: &mut SecureStorageDb<C, B>) -> Result<()> {
+        let mut transaction = db.begin();
+        for (table, migrations) in &self.tables {
+            let stored = db.schema_version(table);
+            if stored == migrations.version {
+                continue;
+            }
+            if stored > migrations.version {
+                return Err(Error::SchemaVersion {
+                    table: table.to_string(),
+                    stored,
+                    expected: migrations.version,
+                });
+            }
+            // an empty table, e.g. in a new database, has nothing to
+            // migrate, and gets its version on its first write
+            if db.scan_prefix(&migrations.prefix, None, 1)?.keys.is_empty() {
+                continue;
+            }
+            let migrate = migrations
+                .steps
+                .get(&stored)
+                .ok_or_else(|| Error::NoMigration {
+                    table: table.to_string(),
+                    from_version: stored,
+                })?;
+            for (key, value) in db.list_entries_with_prefix(&migrations.prefix)? {
+                let (value, index_entries) =
+                    migrate(&value, db.codec()).map_err(|e| Error::Migration {
+                        key: key.clone(),
+                        from_version: stored,
+                        source: Box::new(e),
+                    })?;
+                transaction.put_indexed(key, value, index_entries);
+            }
+            transaction.set_schema_version(table.to_string(), migrations.version);
+        }
+        db.commit(transaction)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Bincode, MemoryBackend, SecureStorageClient};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Serialize, Deserialize)]
+    struct UserV0 {
+        id: String,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct User {
+        id: String,
+        age: u8,
+    }
+
+    impl Storable for User {
+        type Key = String;
+
+        fn unique_id(&self) -> String {
+            self.id.clone()
+        }
+
+        fn schema_version() -> u32 {
+            1
+        }
+    }
+
+    // Only stored from version 1 on, so no migration from version 0 is
+    // registered.
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Account {
+        id: String,
+    }
+
+    impl Storable for Account {
+        type Key = String;
+
+        fn unique_id(&self) -> String {
+            self.id.clone()
+        }
+
+        fn schema_version() -> u32 {
+            2
+        }
+    }
+
+    fn setup(backend: &MemoryBackend) {
+        let mut db =
+            SecureStorageDb::open_with_backend("db".to_string(), Bincode, backend.clone()).unwrap();
+        let mut transaction = db.begin();
+        for id in ["alice", "bob"] {
+            let user = UserV0 { id: id.to_string() };
+            transaction.put(User::concat_key(id), bincode::serialize(&user).unwrap());
+        }
+        db.commit(transaction).unwrap();
+    }
+
+    fn migrate(
+        backend: &MemoryBackend,
+        migrations: Migrations,
+    ) -> Result<SecureStorageClient<Bincode, MemoryBackend>> {
+        let mut db =
+            SecureStorageDb::open_with_backend("db".to_string(), Bincode, backend.clone())?;
+        migrations.apply(&mut db)?;
+        Ok(SecureStorageClient::from_db(db))
+    }
+
+    #[test]
+    fn test_migration() {
+        let backend = MemoryBackend::new();
+        setup(&backend);
+        let client = migrate(&backend, Migrations::new()).unwrap();
+        assert!(client.get::<User>(&"alice".to_string()).is_err());
+
+        let mut migrations = Migrations::new();
+        migrations.register::<User, _>(0, |data| {
+            let old: UserV0 = bincode::deserialize(data)?;
+            Ok(User {
+                id: old.id,
+                age: 18,
+            })
+        });
+        let client = migrate(&backend, migrations).unwrap();
+        assert_eq!(
+            client.get::<User>(&"bob".to_string()).unwrap(),
+            User {
+                id: "bob".to_string(),
+                age: 18
+            }
+        );
+        assert_eq!(client.list_entries::<User>().unwrap().len(), 2);
+
+        // a new database has no records to migrate
+        let backend = MemoryBackend::new();
+        let mut migrations = Migrations::new();
+        migrations.register::<Account, _>(1, |_| unreachable!());
+        let client = migrate(&backend, migrations).unwrap();
+        let account = Account {
+            id: "alice".to_string(),
+        };
+        client.put(&account).unwrap();
+        assert_eq!(
+            client.get::<Account>(&"alice".to_string()).unwrap(),
+            account
+        );
+    }
+
+    #[test]
+    fn test_failed_migration() {
+        let backend = MemoryBackend::new();
+        setup(&backend);
+        let before = backend.list(b"").unwrap();
+        let data: Vec<_> = before.iter().map(|id| backend.load(id).unwrap()).collect();
+
+        let mut migrations = Migrations::new();
+        migrations.register::<User, _>(0, |data| {
+            let old: UserV0 = bincode::deserialize(data)?;
+            if old.id == "bob" {
+                return Err(Error::Custom("bob is too old".to_string()));
+            }
+            Ok(User {
+                id: old.id,
+                age: 18,
+            })
+        });
+        assert!(matches!(
+            migrate(&backend, migrations),
+            Err(Error::Migration {
+                from_version: 0,
+                ..
+            })
+        ));
+        assert_eq!(backend.list(b"").unwrap(), before);
+        let after: Vec<_> = before.iter().map(|id| backend.load(id).unwrap()).collect();
+        assert_eq!(after, data);
+
+        let mut migrations = Migrations::new();
+        migrations.register::<User, _>(2, |_| unreachable!());
+        assert!(matches!(
+            migrate(&backend, migrations),
+            Err(Error::NoMigration {
+                from_version: 0,
+                ..
+            })
+        ));
+    }
+}
@@ -15,7 +15,14 @@
 // specific language governing permissions and limitations
 // under the License.
 
-use std::hash::Hash;
+use crate::expiry::expiry_entry;
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use core::hash::Hash;
 
 // For each key-value data, the storage key is "$TABLE_NAME#$KEY"
 // For example, if we store the Data whose type is Structure named
@@ -31,7 +38,7 @@ pub trait Storable {
 
     fn table_name() -> &'static str {
         // keeps the last part of the path
-        std::any::type_name::<Self>()
+        core::any::type_name::<Self>()
             .split("::")
             .last()
             .unwrap_or("WRONG_TABLE_NAME")
@@ -49,4 +56,137 @@ pub trait Storable {
     fn concat_key(key: &str) -> String {
         format!("{}{}{}", Self::table_name(), CONCAT, key)
     }
+
+    // Version of the layout of the serialized records, declared with
+    // `#[storable(version = N)]` when deriving Storable. Bump it whenever the
+    // serialized form changes, and register a migration from the previous
+    // versions, see Migrations.
+    fn schema_version() -> u32 {
+        0
+    }
+
+    // Names of the secondary indexes of the table, declared with
+    // `#[storable(index)]` when deriving Storable.
+    fn index_names() -> &'static [&'static str] {
+        &[]
+    }
+
+    // Values of the secondary indexes of the record, encoded with IndexValue,
+    // in the order of index_names().
+    fn index_values(&self) -> Vec<Vec<u8>> {
+        Vec::new()
+    }
+
+    // Time at which the record expires, in seconds of the TA persistent time,
+    // declared with `#[storable(expires_at)]` when deriving Storable. See
+    // expiry.rs.
+    fn expires_at(&self) -> Option<u64> {
+        None
+    }
+
+    // Secondary index entries of the record, "$TABLE_NAME#$INDEX#$VALUE"
+    // with the value hex encoded, see index_entry(), and its expiry if any.
+    fn index_entries(&self) -> Vec<String> {
+        Self::index_names()
+            .iter()
+            .zip(self.index_values())
+            .map(|(index, value)| index_entry(Self::table_name(), index, &value))
+            .chain(self.expires_at().map(expiry_entry))
+            .collect()
+    }
+}
+
+// The hex encoding keeps the byte order of the values, so that entries of the
+// same index sort by value, and keeps separators out of the entries.
+pub(crate) fn index_entry(table: &str, index: &str, value: &[u8]) -> String {
+    let mut entry = index_prefix(table, index);
+    for byte in value {
+        entry.push_str(&format!("{:02x}", byte));
+    }
+    entry
+}
+
+// The table of a storage key, "" for the keys which are not in a table.
+pub(crate) fn table_of(storage_key: &str) -> &str {
+    storage_key
+        .split_once(CONCAT)
+        .map_or("", |(table, _)| table)
+}
+
+pub(crate) fn index_prefix(table: &str, index: &str) -> String {
+    format!("{}{}{}{}", table, CONCAT, index, CONCAT)
 }
+
+// IndexValue encodes the value of a secondary index field into bytes whose
+// lexicographic order is the order of the values, so that range queries on
+// the index return the records in value order.
+
+pub trait IndexValue {
+    fn index_value(&self) -> Vec<u8>;
+}
+
+impl<T: IndexValue + ?Sized> IndexValue for &T {
+    fn index_value(&self) -> Vec<u8> {
+        (**self).index_value()
+    }
+}
+
+impl IndexValue for str {
+    fn index_value(&self) -> Vec<u8> {
+        self.as_bytes().to_vec()
+    }
+}
+
+impl IndexValue for String {
+    fn index_value(&self) -> Vec<u8> {
+        self.as_bytes().to_vec()
+    }
+}
+
+impl IndexValue for [u8] {
+    fn index_value(&self) -> Vec<u8> {
+        self.to_vec()
+    }
+}
+
+impl IndexValue for Vec<u8> {
+    fn index_value(&self) -> Vec<u8> {
+        self.clone()
+    }
+}
+
+impl IndexValue for bool {
+    fn index_value(&self) -> Vec<u8> {
+        vec![*self as u8]
+    }
+}
+
+macro_rules! impl_index_value_unsigned {
+    ($($ty:ty),*) => {
+        $(
+            impl IndexValue for $ty {
+                fn index_value(&self) -> Vec<u8> {
+                    self.to_be_bytes().to_vec()
+                }
+            }
+        )*
+    };
+}
+
+// Flipping the sign bit orders negative values before positive ones.
+macro_rules! impl_index_value_signed {
+    ($($ty:ty => $unsigned:ty),*) => {
+        $(
+            impl IndexValue for $ty {
+                fn index_value(&self) -> Vec<u8> {
+                    ((*self as $unsigned) ^ (1 << (<$unsigned>::BITS - 1)))
+                        .to_be_bytes()
+                        .to_vec()
+                }
+            }
+        )*
+    };
+}
+
+impl_index_value_unsigned!(u8, u16, u32, u64, u128);
+impl_index_value_signed!(i8 => u8, i16 => u16, i32 => u32, i64 => u64, i128 => u128);
@@ -0,0 +1,66 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::Result;
+
+// RwLock is std::sync::RwLock with std, which fails with Error::Poisoned once
+// a panic occurred while it was held, and a spin lock without std.
+
+#[cfg(feature = "std")]
+mod imp {
+    pub(crate) type Inner<T> = std::sync::RwLock<T>;
+    pub(crate) type ReadGuard<'a, T> = std::sync::RwLockReadGuard<'a, T>;
+    pub(crate) type WriteGuard<'a, T> = std::sync::RwLockWriteGuard<'a, T>;
+}
+
+#[cfg(not(feature = "std"))]
+mod imp {
+    pub(crate) type Inner<T> = spin::RwLock<T>;
+    pub(crate) type ReadGuard<'a, T> = spin::RwLockReadGuard<'a, T>;
+    pub(crate) type WriteGuard<'a, T> = spin::RwLockWriteGuard<'a, T>;
+}
+
+pub(crate) use imp::{ReadGuard, WriteGuard};
+
+#[derive(Debug, Default)]
+pub(crate) struct RwLock<T>(imp::Inner<T>);
+
+impl<T> RwLock<T> {
+    pub(crate) fn new(value: T) -> Self {
+        Self(imp::Inner::new(value))
+    }
+
+    #[cfg(feature = "std")]
+    pub(crate) fn read(&self) -> Result<ReadGuard<'_, T>> {
+        self.0.read().map_err(|_| crate::Error::Poisoned)
+    }
+
+    #[cfg(feature = "std")]
+    pub(crate) fn write(&self) -> Result<WriteGuard<'_, T>> {
+        self.0.write().map_err(|_| crate::Error::Poisoned)
+    }
+
+    #[cfg(not(feature = "std"))]
+    pub(crate) fn read(&self) -> Result<ReadGuard<'_, T>> {
+        Ok(self.0.read())
+    }
+
+    #[cfg(not(feature = "std"))]
+    pub(crate) fn write(&self) -> Result<WriteGuard<'_, T>> {
+        Ok(self.0.write())
+    }
+}
@@ -0,0 +1,101 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use alloc::{string::String, vec::Vec};
+use serde::{Deserialize, Serialize};
+
+// Transaction is a batch of puts and deletes which is committed atomically by
+// SecureStorageDb::commit().
+// Operations are only recorded here, nothing is written to the secure storage
+// until the transaction is committed. Dropping a transaction discards it.
+//
+// A transaction can also expect some keys to be at a given version, see
+// SecureStorageDb::version(); the commit then fails with Error::Conflict if
+// any of them was updated in the meantime.
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Transaction {
+    operations: Vec<Operation>,
+    expected_versions: Vec<(String, u64)>,
+}
+
+impl Transaction {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn put(&mut self, key: String, value: Vec<u8>) {
+        self.put_indexed(key, value, Vec::new());
+    }
+
+    // Put a value along with its secondary index entries, which replace the
+    // entries of the previous value of the key.
+    pub fn put_indexed(&mut self, key: String, value: Vec<u8>, index_entries: Vec<String>) {
+        self.operations
+            .push(Operation::Put(key, value, index_entries));
+    }
+
+    pub fn delete(&mut self, key: String) {
+        self.operations.push(Operation::Delete(key));
+    }
+
+    // Record the schema version of the records of a table, see
+    // SecureStorageDb::schema_version().
+    pub fn set_schema_version(&mut self, table: String, version: u32) {
+        self.operations
+            .push(Operation::SetSchemaVersion(table, version));
+    }
+
+    // Commit the transaction only if the key is at `version` when it is
+    // committed, 0 for a key which must not exist.
+    pub fn expect_version(&mut self, key: String, version: u64) {
+        self.expected_versions.push((key, version));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.operations.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.operations.len()
+    }
+
+    pub(crate) fn into_parts(self) -> (Vec<Operation>, Vec<(String, u64)>) {
+        (self.operations, self.expected_versions)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) enum Operation {
+    Put(String, Vec<u8>, Vec<String>),
+    Delete(String),
+    SetSchemaVersion(String, u32),
+    // Appended by SecureStorageDb::commit() for each key put by the
+    // transaction.
+    SetVersion(String, u64),
+}
+
+// Journal is the write-ahead record of a committed transaction.
+// It is stored before any object of the transaction is touched, and holds
+// everything needed to redo the transaction. Replaying a journal is
+// idempotent, so an interrupted commit, or an interrupted replay, is completed
+// by replaying it again when the database is opened.
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct Journal {
+    pub(crate) operations: Vec<Operation>,
+}
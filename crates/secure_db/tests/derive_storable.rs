@@ -0,0 +1,113 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use secure_db::{
+    Bincode, Error, IndexValue, MemoryBackend, SecureStorageClient, SecureStorageDb, Storable,
+};
+use serde::{Deserialize, Serialize};
+
+// libutee is not linked on the host: the clients are given a clock instead of
+// the TA persistent time, which only the mock provides.
+use optee_utee_mock as _;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Storable)]
+#[storable(version = 2)]
+struct Session {
+    #[storable(key)]
+    id: String,
+    #[storable(index)]
+    user: String,
+    #[storable(index)]
+    level: u32,
+    #[storable(expires_at)]
+    expires_at: Option<u64>,
+    token: Vec<u8>,
+}
+
+fn session(id: &str, user: &str, level: u32, expires_at: Option<u64>) -> Session {
+    Session {
+        id: id.to_string(),
+        user: user.to_string(),
+        level,
+        expires_at,
+        token: id.as_bytes().to_vec(),
+    }
+}
+
+fn open(backend: &MemoryBackend, now: u64) -> SecureStorageClient<Bincode, MemoryBackend> {
+    let db =
+        SecureStorageDb::open_with_backend("db".to_string(), Bincode, backend.clone()).unwrap();
+    SecureStorageClient::from_db(db).with_clock(move || Ok(now))
+}
+
+#[test]
+fn test_derived_impl() {
+    let value = session("1", "alice", 3, Some(100));
+    assert_eq!(value.unique_id(), "1");
+    assert_eq!(Session::schema_version(), 2);
+    assert_eq!(Session::index_names(), &["user", "level"]);
+    assert_eq!(
+        value.index_values(),
+        vec!["alice".index_value(), 3u32.index_value()]
+    );
+    assert_eq!(value.expires_at(), Some(100));
+    assert_eq!(session("2", "bob", 0, None).expires_at(), None);
+}
+
+#[test]
+fn test_find_by() {
+    let backend = MemoryBackend::new();
+    let client = open(&backend, 50);
+    let mut transaction = client.begin();
+    transaction.put(&session("1", "alice", 3, None)).unwrap();
+    transaction.put(&session("2", "bob", 1, Some(100))).unwrap();
+    transaction
+        .put(&session("3", "alice", 1, Some(60)))
+        .unwrap();
+    transaction.commit().unwrap();
+
+    let client = open(&backend, 50);
+    assert_eq!(
+        client.find_by::<Session, _>("user", "alice").unwrap(),
+        vec![
+            session("1", "alice", 3, None),
+            session("3", "alice", 1, Some(60))
+        ]
+    );
+    assert_eq!(
+        client.find_by::<Session, _>("level", &1u32).unwrap(),
+        vec![
+            session("2", "bob", 1, Some(100)),
+            session("3", "alice", 1, Some(60))
+        ]
+    );
+    assert!(matches!(
+        client.find_by::<Session, _>("token", &b"1"[..]),
+        Err(Error::NoIndex { .. })
+    ));
+
+    // the records are left out once they expired
+    let client = open(&backend, 60);
+    assert_eq!(
+        client.find_by::<Session, _>("level", &1u32).unwrap(),
+        vec![session("2", "bob", 1, Some(100))]
+    );
+
+    // the table is stored at the declared schema version
+    let db = SecureStorageDb::open_with_backend("db".to_string(), Bincode, backend).unwrap();
+    assert_eq!(db.schema_version(Session::table_name()), 2);
+}
@@ -17,7 +17,7 @@
 
 use optee_teec::ParamNone;
 use optee_teec::{Context, ErrorKind, Operation, Uuid};
-use proto::{Command, UUID};
+use proto::{AppError, Command, UUID};
 
 fn main() -> optee_teec::Result<()> {
     test_error_handling();
@@ -46,6 +46,13 @@ fn test_error_handling() {
         .invoke_command(Command::ReturnGenericError as u32, &mut operation)
         .expect_err("generic error");
     assert_eq!(e.kind(), ErrorKind::Generic);
+    assert_eq!(e.app_error::<AppError>(), None);
+
+    // Test application error invocation returns the error defined by the TA.
+    let e = session
+        .invoke_command(Command::ReturnAppError as u32, &mut operation)
+        .expect_err("application error");
+    assert_eq!(e.app_error::<AppError>(), Some(AppError::PinLocked));
 
     println!("Test passed");
 }
@@ -16,7 +16,7 @@
 // under the License.
 
 #![no_std]
-use num_enum::{FromPrimitive, IntoPrimitive};
+use num_enum::{FromPrimitive, IntoPrimitive, TryFromPrimitive};
 
 #[derive(FromPrimitive, IntoPrimitive)]
 #[repr(u32)]
@@ -25,10 +25,24 @@ pub enum Command {
     ReturnSuccess,
     // Ask the TA to simply return `TEE_ERROR_GENERIC` when handling this command.
     ReturnGenericError,
+    // Ask the TA to return the application error `AppError::PinLocked` when
+    // handling this command.
+    ReturnAppError,
     #[default]
     Unknown,
 }
 
+// Errors defined by the TA. The TA returns them with `Error::app()`, in the
+// range of codes reserved for applications, and the host decodes them with
+// `Error::app_error()`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, TryFromPrimitive, IntoPrimitive)]
+#[repr(u16)]
+pub enum AppError {
+    // 0 and 1 are reserved, see `optee_utee::APP_ERROR_CODES`.
+    WalletNotFound = 2,
+    PinLocked = 3,
+}
+
 // If Uuid::parse_str() returns an InvalidLength error, there may be an extra
 // newline in your uuid.txt file. You can remove it by running 
 // `truncate -s 36 uuid.txt`.
@@ -28,7 +28,7 @@ use optee_utee::{
     ta_close_session, ta_create, ta_destroy, ta_invoke_command, ta_open_session, trace_println,
 };
 use optee_utee::{Error, ErrorKind, Parameters, Result};
-use proto::Command;
+use proto::{AppError, Command};
 
 pub struct SessionContext {
     _stuff_on_heap: Vec<u8>,
@@ -74,6 +74,7 @@ fn invoke_command(
     match Command::from(cmd_id) {
         Command::ReturnSuccess => Ok(()),
         Command::ReturnGenericError => Err(Error::new(ErrorKind::Generic)),
+        Command::ReturnAppError => Err(Error::app(AppError::PinLocked)),
         _ => Err(Error::new(ErrorKind::NotSupported)),
     }
 }
@@ -68,21 +68,14 @@ fn invoke_command(cmd_id: u32, _params: &mut Parameters) -> Result<()> {
     }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
-pub struct ExampleData {
-    pub id: String,
-    pub data: Vec<u8>,
-}
-
 // Any structure that implements Storable can be stored in the secure db.
 // Any Key type can be used as unique id as long as it implements
 // TryFrom<String> + ToString
-impl Storable for ExampleData {
-    type Key = String;
-
-    fn unique_id(&self) -> Self::Key {
-        self.id.clone()
-    }
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Storable)]
+pub struct ExampleData {
+    #[storable(key)]
+    pub id: String,
+    pub data: Vec<u8>,
 }
 
 pub fn test() -> anyhow::Result<()> {
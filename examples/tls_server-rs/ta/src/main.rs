@@ -68,49 +68,35 @@ fn destroy() {
 }
 
 #[ta_invoke_command]
-fn invoke_command(cmd_id: u32, params: &mut Parameters) -> Result<()> {
+fn invoke_command(cmd_id: u32, params: &mut Parameters) -> anyhow::Result<()> {
     trace_println!("[+] TA invoke command");
     let session_id = unsafe { params.0.as_value()?.a() };
     trace_println!("[+] session id: {}", session_id);
     match Command::from(cmd_id) {
         Command::NewTlsSession => {
             trace_println!("[+] new_tls_session");
-            new_tls_session(session_id).map_err(|e| {
-                trace_println!("[-] Failed to create TLS session: {:?}", e);
-                Error::new(ErrorKind::Generic)
-            })
+            new_tls_session(session_id).context("Failed to create TLS session")
         }
         Command::DoTlsRead => {
             let mut p1 = unsafe { params.1.as_memref()? };
             let buffer = p1.buffer();
             trace_println!("[+] do_tls_read");
-            do_tls_read(session_id, buffer).map_err(|e| {
-                trace_println!("[-] Failed to read TLS data: {:?}", e);
-                Error::new(ErrorKind::Generic)
-            })
+            do_tls_read(session_id, buffer).context("Failed to read TLS data")
         }
         Command::DoTlsWrite => {
             trace_println!("[+] do_tls_write");
             let mut p1 = unsafe { params.1.as_memref()? };
             let mut p2 = unsafe { params.2.as_value()? };
             let buffer = p1.buffer();
-            do_tls_write(session_id, buffer)
-                .map(|n| {
-                    p2.set_a(n as u32);
-                })
-                .map_err(|e| {
-                    trace_println!("[-] Failed to write TLS data: {:?}", e);
-                    Error::new(ErrorKind::Generic)
-                })
+            let n = do_tls_write(session_id, buffer).context("Failed to write TLS data")?;
+            p2.set_a(n as u32);
+            Ok(())
         }
         Command::CloseTlsSession => {
             trace_println!("[+] close_tls_session");
-            close_tls_session(session_id).map_err(|e| {
-                trace_println!("[-] Failed to close TLS session: {:?}", e);
-                Error::new(ErrorKind::Generic)
-            })
+            close_tls_session(session_id).context("Failed to close TLS session")
         }
-        _ => Err(Error::new(ErrorKind::BadParameters)),
+        _ => Err(Error::new(ErrorKind::BadParameters).into()),
     }
 }
 
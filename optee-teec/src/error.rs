@@ -17,7 +17,9 @@
 
 use crate::raw;
 use num_enum::{FromPrimitive, IntoPrimitive};
+use std::convert::TryFrom;
 use std::fmt;
+use std::ops::RangeInclusive;
 
 /// A specialized [`Result`](https://doc.rust-lang.org/std/result/enum.Result.html)
 /// type for TEE operations.
@@ -41,9 +43,19 @@ pub type Result<T> = std::result::Result<T, Error>;
 #[derive(Clone)]
 pub struct Error {
     kind: ErrorKind,
+    code: u32,
     origin: Option<ErrorOrigin>,
 }
 
+/// The range of error codes of the errors defined by Trusted Applications, the same as
+/// [`optee_utee::APP_ERROR_CODES`][utee], whose documentation describes the encoding.
+///
+/// [utee]: https://docs.rs/optee-utee/latest/optee_utee/constant.APP_ERROR_CODES.html
+pub const APP_ERROR_CODES: RangeInclusive<u32> = 0x8000_0002..=0x8000_FFFF;
+
+// The raw code of the application error code 0.
+const APP_ERROR_BASE: u32 = 0x8000_0000;
+
 /// A list specifying general categories of TEE client error and its
 /// corresponding code in OP-TEE client library.
 #[derive(
@@ -127,7 +139,7 @@ impl ErrorKind {
 
 impl Error {
     pub fn new(kind: ErrorKind) -> Error {
-        Error { kind, origin: None }
+        Error::from(kind)
     }
     /// Creates a new instance of an `Error` from a particular TEE error code.
     ///
@@ -142,6 +154,7 @@ impl Error {
     pub fn from_raw_error(code: u32) -> Error {
         Error {
             kind: ErrorKind::from(code),
+            code,
             origin: None,
         }
     }
@@ -171,12 +184,42 @@ impl Error {
 
     /// Returns raw code of this error.
     pub fn raw_code(&self) -> u32 {
-        self.kind.into()
+        self.code
     }
 
     /// Returns corresponding error message of this error.
     pub fn message(&self) -> &str {
-        self.kind().as_str()
+        match self.app_code() {
+            Some(_) => "Error returned by the Trusted Application with `optee_utee::Error::app`.",
+            None => self.kind().as_str(),
+        }
+    }
+
+    /// Returns `n` for an error returned by the TA as `optee_utee::Error::app(n)`, see
+    /// [`APP_ERROR_CODES`]. The [`ErrorKind`] of such an error is `Unknown`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use optee_teec::{Error, ErrorKind};
+    ///
+    /// let error = Error::from_raw_error(0x8000_0002);
+    /// assert_eq!(error.app_code(), Some(2));
+    /// assert_eq!(error.kind(), ErrorKind::Unknown);
+    /// assert_eq!(Error::new(ErrorKind::Security).app_code(), None);
+    /// ```
+    pub fn app_code(&self) -> Option<u16> {
+        if APP_ERROR_CODES.contains(&self.code) {
+            Some((self.code - APP_ERROR_BASE) as u16)
+        } else {
+            None
+        }
+    }
+
+    /// Returns [`app_code`](Self::app_code) converted into `E`, usually the error enum of the
+    /// proto crate shared with the TA, or `None` if it is not a valid `E`.
+    pub fn app_error<E: TryFrom<u16>>(&self) -> Option<E> {
+        self.app_code().and_then(|code| E::try_from(code).ok())
     }
 }
 
@@ -207,7 +250,11 @@ impl std::error::Error for Error {
 impl From<ErrorKind> for Error {
     #[inline]
     fn from(kind: ErrorKind) -> Error {
-        Error { kind, origin: None }
+        Error {
+            kind,
+            code: kind.into(),
+            origin: None,
+        }
     }
 }
 
@@ -221,3 +268,54 @@ pub enum ErrorOrigin {
     #[default]
     UNKNOWN,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    enum AppError {
+        NotFound,
+        PinLocked,
+    }
+
+    impl TryFrom<u16> for AppError {
+        type Error = ();
+
+        fn try_from(code: u16) -> std::result::Result<Self, ()> {
+            match code {
+                2 => Ok(AppError::NotFound),
+                3 => Ok(AppError::PinLocked),
+                _ => Err(()),
+            }
+        }
+    }
+
+    #[test]
+    fn test_app_error() {
+        let error = Error::from_raw_error(*APP_ERROR_CODES.start());
+        assert_eq!(error.kind(), ErrorKind::Unknown);
+        assert_eq!(error.app_code(), Some(2));
+        assert_eq!(error.app_error(), Some(AppError::NotFound));
+        let error = Error::from_raw_error(APP_ERROR_CODES.start() + 1);
+        assert_eq!(error.app_error(), Some(AppError::PinLocked));
+
+        // a valid application code which is not a valid `E`
+        let error = Error::from_raw_error(*APP_ERROR_CODES.end());
+        assert_eq!(error.app_code(), Some(u16::MAX));
+        assert_eq!(error.app_error::<AppError>(), None);
+        assert_ne!(error.message(), ErrorKind::Unknown.as_str());
+
+        // the codes around the range, including the two codes of OP-TEE below
+        // it, are not application errors
+        for code in [0x8000_0000, 0x8000_0001, APP_ERROR_CODES.end() + 1] {
+            let error = Error::from_raw_error(code);
+            assert_eq!(error.app_code(), None);
+            assert_eq!(error.app_error::<AppError>(), None);
+            assert_eq!(error.message(), ErrorKind::Unknown.as_str());
+        }
+        let error = Error::new(ErrorKind::Security);
+        assert_eq!(error.app_code(), None);
+        assert_eq!(error.message(), ErrorKind::Security.as_str());
+    }
+}
@@ -16,7 +16,7 @@
 // under the License.
 
 pub use self::context::Context;
-pub use self::error::{Error, ErrorKind, ErrorOrigin, Result};
+pub use self::error::{Error, ErrorKind, ErrorOrigin, Result, APP_ERROR_CODES};
 pub use self::extension::*;
 pub use self::operation::Operation;
 pub use self::parameter::{Param, ParamNone, ParamTmpRef, ParamType, ParamTypes, ParamValue};
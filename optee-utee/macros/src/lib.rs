@@ -20,7 +20,9 @@ extern crate proc_macro;
 
 #[cfg(not(target_os = "optee"))]
 use alloc::vec::Vec;
+use core::ops::RangeInclusive;
 use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
 use quote::quote;
 use syn::parse_macro_input;
 use syn::spanned::Spanned;
@@ -33,6 +35,8 @@ use syn::spanned::Spanned;
 /// #[ta_crate]
 /// fn ta_crate() -> Result<()> { }
 /// ```
+///
+/// The error type can be any type implementing `Into<optee_utee::Error>`.
 #[proc_macro_attribute]
 pub fn ta_create(_args: TokenStream, input: TokenStream) -> TokenStream {
     let f = parse_macro_input!(input as syn::ItemFn);
@@ -52,7 +56,7 @@ pub fn ta_create(_args: TokenStream, input: TokenStream) -> TokenStream {
     if !valid_signature {
         return syn::parse::Error::new(
             f.span(),
-            "`#[ta_crate]` function must have signature `fn() -> optee_utee::Result<()>`",
+            "`#[ta_crate]` function must have signature `fn() -> Result<(), E>` with `E: Into<optee_utee::Error>`",
         )
         .to_compile_error()
         .into();
@@ -63,7 +67,7 @@ pub fn ta_create(_args: TokenStream, input: TokenStream) -> TokenStream {
         pub extern "C" fn TA_CreateEntryPoint() -> optee_utee_sys::TEE_Result {
             match #ident() {
                 Ok(_) => optee_utee_sys::TEE_SUCCESS,
-                Err(e) => e.raw_code()
+                Err(e) => Into::<optee_utee::Error>::into(e).raw_code()
             }
         }
 
@@ -134,10 +138,25 @@ pub fn ta_destroy(_args: TokenStream, input: TokenStream) -> TokenStream {
 /// #[ta_open_session]
 /// fn open_session(params: &mut Parameters, sess_ctx: &mut T) -> Result<()> { }
 /// ```
+///
+/// The error type can be any type implementing `Into<optee_utee::Error>`, such
+/// as `anyhow::Error` with the `anyhow` feature of optee-utee.
+///
+/// In std TAs, `#[ta_open_session(catch_unwind)]` returns `TEE_ERROR_GENERIC`
+/// instead of killing the TA instance when the function panics, and
+/// `#[ta_open_session(catch_unwind = CODE)]` returns `CODE`, a GlobalPlatform
+/// error code or one of `optee_utee::APP_ERROR_CODES`, see
+/// `optee_utee::unwind`.
 #[proc_macro_attribute]
-pub fn ta_open_session(_args: TokenStream, input: TokenStream) -> TokenStream {
+pub fn ta_open_session(args: TokenStream, input: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(args as syn::AttributeArgs);
     let f = parse_macro_input!(input as syn::ItemFn);
     let ident = &f.ident;
+    let catch_unwind = match parse_catch_unwind("ta_open_session", &args, true) {
+        Ok(catch_unwind) => catch_unwind,
+        Err(e) => return e.to_compile_error().into(),
+    };
+    let requires_std = requires_std(catch_unwind);
 
     // check the function signature
     let valid_signature = f.constness.is_none()
@@ -160,23 +179,28 @@ pub fn ta_open_session(_args: TokenStream, input: TokenStream) -> TokenStream {
     }
 
     match f.decl.inputs.len() {
-        1 => quote!(
-            #[no_mangle]
-            pub extern "C" fn TA_OpenSessionEntryPoint(
-                param_types: u32,
-                params: &mut [optee_utee_sys::TEE_Param; 4],
-                sess_ctx: *mut *mut c_void,
-            ) -> optee_utee_sys::TEE_Result {
-                let mut parameters = Parameters::from_raw(params, param_types);
-                match #ident(&mut parameters) {
-                    Ok(_) => optee_utee_sys::TEE_SUCCESS,
-                    Err(e) => e.raw_code()
+        1 => {
+            let call = catch_unwind_call(catch_unwind, quote!(#ident(&mut parameters)), quote!());
+            quote!(
+                #[no_mangle]
+                pub extern "C" fn TA_OpenSessionEntryPoint(
+                    param_types: u32,
+                    params: &mut [optee_utee_sys::TEE_Param; 4],
+                    sess_ctx: *mut *mut c_void,
+                ) -> optee_utee_sys::TEE_Result {
+                    let mut parameters = Parameters::from_raw(params, param_types);
+                    match #call {
+                        Ok(_) => optee_utee_sys::TEE_SUCCESS,
+                        Err(e) => Into::<optee_utee::Error>::into(e).raw_code()
+                    }
                 }
-            }
 
-            #f
-        )
-        .into(),
+                #f
+
+                #requires_std
+            )
+            .into()
+        }
 
         2 => {
             let input_types: Vec<_> = f
@@ -192,6 +216,13 @@ pub fn ta_open_session(_args: TokenStream, input: TokenStream) -> TokenStream {
                 &syn::Type::Reference(ref r) => &r.elem,
                 _ => unreachable!(),
             };
+            // The context may be inconsistent after a panic, so it is leaked
+            // rather than dropped.
+            let call = catch_unwind_call(
+                catch_unwind,
+                quote!(#ident(&mut parameters, &mut ctx)),
+                quote!(core::mem::forget(ctx);),
+            );
 
             quote!(
                 // To eliminate the clippy error: this public function might dereference a raw pointer but is not marked `unsafe`
@@ -204,17 +235,19 @@ pub fn ta_open_session(_args: TokenStream, input: TokenStream) -> TokenStream {
                 ) -> optee_utee_sys::TEE_Result {
                     let mut parameters = Parameters::from_raw(params, param_types);
                     let mut ctx: #ctx_type = Default::default();
-                    match #ident(&mut parameters, &mut ctx) {
+                    match #call {
                         Ok(_) =>
                         {
                             *sess_ctx = Box::into_raw(Box::new(ctx)) as _;
                             optee_utee_sys::TEE_SUCCESS
                         }
-                        Err(e) => e.raw_code()
+                        Err(e) => Into::<optee_utee::Error>::into(e).raw_code()
                     }
                 }
 
                 #f
+
+                #requires_std
             )
             .into()
         }
@@ -234,10 +267,19 @@ pub fn ta_open_session(_args: TokenStream, input: TokenStream) -> TokenStream {
 /// #[ta_close_session]
 /// fn close_session() { }
 /// ```
+///
+/// In std TAs, `#[ta_close_session(catch_unwind)]` prints a panic of the
+/// function instead of killing the TA instance, see `optee_utee::unwind`.
 #[proc_macro_attribute]
-pub fn ta_close_session(_args: TokenStream, input: TokenStream) -> TokenStream {
+pub fn ta_close_session(args: TokenStream, input: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(args as syn::AttributeArgs);
     let f = parse_macro_input!(input as syn::ItemFn);
     let ident = &f.ident;
+    let catch_unwind = match parse_catch_unwind("ta_close_session", &args, false) {
+        Ok(catch_unwind) => catch_unwind,
+        Err(e) => return e.to_compile_error().into(),
+    };
+    let requires_std = requires_std(catch_unwind);
 
     // check the function signature
     let valid_signature = f.constness.is_none()
@@ -264,15 +306,23 @@ pub fn ta_close_session(_args: TokenStream, input: TokenStream) -> TokenStream {
     }
 
     match f.decl.inputs.len() {
-        0 => quote!(
-            #[no_mangle]
-            pub extern "C" fn TA_CloseSessionEntryPoint(sess_ctx: *mut c_void) {
-                #ident();
-            }
+        0 => {
+            let call = match catch_unwind {
+                Some(_) => quote!(let _ = optee_utee::unwind::catch(|| #ident());),
+                None => quote!(#ident();),
+            };
+            quote!(
+                #[no_mangle]
+                pub extern "C" fn TA_CloseSessionEntryPoint(sess_ctx: *mut c_void) {
+                    #call
+                }
 
-            #f
-        )
-        .into(),
+                #f
+
+                #requires_std
+            )
+            .into()
+        }
         1 => {
             let input_types: Vec<_> = f
                 .decl
@@ -287,6 +337,18 @@ pub fn ta_close_session(_args: TokenStream, input: TokenStream) -> TokenStream {
                 &syn::Type::Reference(ref r) => &r.elem,
                 _ => unreachable!(),
             };
+            let call = match catch_unwind {
+                Some(_) => quote!(
+                    let _ = optee_utee::unwind::catch(move || {
+                        #ident(&mut b);
+                        drop(b);
+                    });
+                ),
+                None => quote!(
+                    #ident(&mut b);
+                    drop(b);
+                ),
+            };
 
             quote!(
                 // To eliminate the clippy error: this public function might dereference a raw pointer but is not marked `unsafe`
@@ -297,11 +359,15 @@ pub fn ta_close_session(_args: TokenStream, input: TokenStream) -> TokenStream {
                         panic!("sess_ctx is null");
                     }
                     let mut b = Box::from_raw(sess_ctx as *mut #t);
-                    #ident(&mut b);
-                    drop(b);
+                    // the command entry point may have poisoned the session,
+                    // whose context may then be reallocated to the next one
+                    optee_utee::unwind::clear(sess_ctx);
+                    #call
                 }
 
                 #f
+
+                #requires_std
             )
             .into()
         }
@@ -320,11 +386,31 @@ pub fn ta_close_session(_args: TokenStream, input: TokenStream) -> TokenStream {
 ///
 /// #[ta_invoke_command]
 /// fn invoke_command(cmd_id: u32, params: &mut Parameters) -> Result<()> { }
+///
+/// #[ta_invoke_command]
+/// fn invoke_command(cmd_id: u32, params: &mut Parameters) -> anyhow::Result<()> { }
 /// ```
+///
+/// The error type can be any type implementing `Into<optee_utee::Error>`, such
+/// as `anyhow::Error` with the `anyhow` feature of optee-utee.
+///
+/// In std TAs, `#[ta_invoke_command(catch_unwind)]` returns
+/// `TEE_ERROR_GENERIC` instead of killing the TA instance when the function
+/// panics, and `#[ta_invoke_command(catch_unwind = CODE)]` returns `CODE`, a
+/// GlobalPlatform error code or one of `optee_utee::APP_ERROR_CODES`. The
+/// session context may be inconsistent after a panic, so the session is
+/// poisoned: its next commands return `TEE_ERROR_BAD_STATE` until it is
+/// closed, see `optee_utee::unwind`.
 #[proc_macro_attribute]
-pub fn ta_invoke_command(_args: TokenStream, input: TokenStream) -> TokenStream {
+pub fn ta_invoke_command(args: TokenStream, input: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(args as syn::AttributeArgs);
     let f = parse_macro_input!(input as syn::ItemFn);
     let ident = &f.ident;
+    let catch_unwind = match parse_catch_unwind("ta_invoke_command", &args, true) {
+        Ok(catch_unwind) => catch_unwind,
+        Err(e) => return e.to_compile_error().into(),
+    };
+    let requires_std = requires_std(catch_unwind);
 
     // check the function signature
     let valid_signature = f.constness.is_none()
@@ -347,26 +433,35 @@ pub fn ta_invoke_command(_args: TokenStream, input: TokenStream) -> TokenStream
     }
 
     match f.decl.inputs.len() {
-        2 => quote!(
-            #[no_mangle]
-            pub extern "C" fn TA_InvokeCommandEntryPoint(
-                sess_ctx: *mut c_void,
-                cmd_id: u32,
-                param_types: u32,
-                params: &mut [optee_utee_sys::TEE_Param; 4],
-            ) -> optee_utee_sys::TEE_Result {
-                let mut parameters = Parameters::from_raw(params, param_types);
-                match #ident(cmd_id, &mut parameters) {
-                    Ok(_) => {
-                        optee_utee_sys::TEE_SUCCESS
-                    },
-                    Err(e) => e.raw_code()
+        2 => {
+            let call = catch_unwind_call(
+                catch_unwind,
+                quote!(#ident(cmd_id, &mut parameters)),
+                quote!(),
+            );
+            quote!(
+                #[no_mangle]
+                pub extern "C" fn TA_InvokeCommandEntryPoint(
+                    sess_ctx: *mut c_void,
+                    cmd_id: u32,
+                    param_types: u32,
+                    params: &mut [optee_utee_sys::TEE_Param; 4],
+                ) -> optee_utee_sys::TEE_Result {
+                    let mut parameters = Parameters::from_raw(params, param_types);
+                    match #call {
+                        Ok(_) => {
+                            optee_utee_sys::TEE_SUCCESS
+                        },
+                        Err(e) => Into::<optee_utee::Error>::into(e).raw_code()
+                    }
                 }
-            }
 
-            #f
-        )
-        .into(),
+                #f
+
+                #requires_std
+            )
+            .into()
+        }
         3 => {
             let input_types: Vec<_> = f
                 .decl
@@ -381,6 +476,20 @@ pub fn ta_invoke_command(_args: TokenStream, input: TokenStream) -> TokenStream
                 &syn::Type::Reference(ref r) => &r.elem,
                 _ => unreachable!(),
             };
+            let poisoned_check = match catch_unwind {
+                Some(_) => quote!(if optee_utee::unwind::is_poisoned(sess_ctx) {
+                    return optee_utee_sys::TEE_ERROR_BAD_STATE;
+                }),
+                None => quote!(),
+            };
+            let call = catch_unwind_call(
+                catch_unwind,
+                quote!(#ident(&mut b, cmd_id, &mut parameters)),
+                quote!(
+                    core::mem::forget(b);
+                    optee_utee::unwind::poison(sess_ctx);
+                ),
+            );
 
             quote!(
                 // To eliminate the clippy error: this public function might dereference a raw pointer but is not marked `unsafe`
@@ -395,24 +504,112 @@ pub fn ta_invoke_command(_args: TokenStream, input: TokenStream) -> TokenStream
                     if sess_ctx.is_null() {
                         return optee_utee_sys::TEE_ERROR_SECURITY;
                     }
+                    #poisoned_check
                     let mut parameters = Parameters::from_raw(params, param_types);
                     let mut b = Box::from_raw(sess_ctx as *mut #t);
-                    match #ident(&mut b, cmd_id, &mut parameters) {
+                    match #call {
                         Ok(_) => {
                             core::mem::forget(b);
                             optee_utee_sys::TEE_SUCCESS
                         },
                         Err(e) => {
                             core::mem::forget(b);
-                            e.raw_code()
+                            Into::<optee_utee::Error>::into(e).raw_code()
                         }
                     }
                 }
 
                 #f
+
+                #requires_std
             )
             .into()
         }
         _ => unreachable!(),
     }
 }
+
+// Parses the arguments of the session entry-point macros, which are either
+// none, `catch_unwind`, or `catch_unwind = CODE` if `accepts_code`, and
+// returns the code returned when the function panics, if the panics are
+// caught. The code must be a GlobalPlatform error or an application error, as
+// the client would take any other code for a success or an unknown result.
+fn parse_catch_unwind(
+    macro_name: &str,
+    args: &[syn::NestedMeta],
+    accepts_code: bool,
+) -> syn::parse::Result<Option<u32>> {
+    const TEE_ERROR_GENERIC: u32 = 0xFFFF0000;
+    const TEE_ERROR_CODES: RangeInclusive<u64> = 0xFFFF_0000..=0xFFFF_FFFF;
+    // `optee_utee::APP_ERROR_CODES`
+    const APP_ERROR_CODES: RangeInclusive<u64> = 0x8000_0002..=0x8000_FFFF;
+
+    match args {
+        [] => Ok(None),
+        [syn::NestedMeta::Meta(syn::Meta::Word(ident))] if ident == "catch_unwind" => {
+            Ok(Some(TEE_ERROR_GENERIC))
+        }
+        [syn::NestedMeta::Meta(syn::Meta::NameValue(syn::MetaNameValue {
+            ident,
+            lit: syn::Lit::Int(code),
+            ..
+        }))] if ident == "catch_unwind" && accepts_code => {
+            if TEE_ERROR_CODES.contains(&code.value()) || APP_ERROR_CODES.contains(&code.value()) {
+                Ok(Some(code.value() as u32))
+            } else {
+                Err(syn::parse::Error::new(
+                    code.span(),
+                    "`catch_unwind = CODE` expects a GlobalPlatform error code \
+                     (0xFFFF0000..=0xFFFFFFFF) or an application error code (0x80000002..=0x8000FFFF)",
+                ))
+            }
+        }
+        _ if accepts_code => Err(syn::parse::Error::new(
+            args[0].span(),
+            format!(
+                "`#[{}]` expects no argument, `catch_unwind` or `catch_unwind = CODE`",
+                macro_name
+            ),
+        )),
+        _ => Err(syn::parse::Error::new(
+            args[0].span(),
+            format!(
+                "`#[{}]` expects no argument or `catch_unwind`, as it returns no code",
+                macro_name
+            ),
+        )),
+    }
+}
+
+// Wraps the call of the function in a `match` which, if the panics are
+// caught and the function panics, runs `on_panic` and returns the code from
+// the entry point.
+fn catch_unwind_call(
+    catch_unwind: Option<u32>,
+    call: TokenStream2,
+    on_panic: TokenStream2,
+) -> TokenStream2 {
+    match catch_unwind {
+        Some(code) => quote!(
+            match optee_utee::unwind::catch(|| #call) {
+                Some(result) => result,
+                None => {
+                    #on_panic
+                    return #code;
+                }
+            }
+        ),
+        None => call,
+    }
+}
+
+// Panics can only be caught when unwinding, in std TAs.
+fn requires_std(catch_unwind: Option<u32>) -> TokenStream2 {
+    match catch_unwind {
+        Some(_) => quote!(
+            #[cfg(not(target_os = "optee"))]
+            compile_error!("`catch_unwind` requires a std TA");
+        ),
+        None => quote!(),
+    }
+}
@@ -0,0 +1,348 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use std::ffi::c_void;
+use std::ptr::NonNull;
+use std::sync::RwLock;
+
+use crate::raw::{
+    self, TEE_Attribute, TEE_ObjectHandle, TEE_OperationHandle, TEE_OperationInfo, TEE_Result,
+};
+
+static GLOBAL_CRYPTO_MOCK: RwLock<Option<Box<dyn CryptoController + 'static>>> = RwLock::new(None);
+
+#[mockall::automock]
+// currently we just add functions that we need
+pub trait CryptoController: Send + Sync {
+    // Cryptographic Operations API - Generic Operation Functions
+    fn TEE_AllocateOperation(
+        &self,
+        operation: *mut TEE_OperationHandle,
+        algorithm: u32,
+        mode: u32,
+        maxKeySize: u32,
+    ) -> TEE_Result;
+    fn TEE_FreeOperation(&self, operation: TEE_OperationHandle);
+    fn TEE_GetOperationInfo(
+        &self,
+        operation: TEE_OperationHandle,
+        operationInfo: *mut TEE_OperationInfo,
+    );
+    fn TEE_ResetOperation(&self, operation: TEE_OperationHandle);
+    fn TEE_SetOperationKey(
+        &self,
+        operation: TEE_OperationHandle,
+        key: TEE_ObjectHandle,
+    ) -> TEE_Result;
+    fn TEE_CopyOperation(
+        &self,
+        dstOperation: TEE_OperationHandle,
+        srcOperation: TEE_OperationHandle,
+    );
+
+    // Cryptographic Operations API - Message Digest Functions
+    fn TEE_DigestUpdate(
+        &self,
+        operation: TEE_OperationHandle,
+        chunk: *const c_void,
+        chunkSize: usize,
+    );
+    fn TEE_DigestDoFinal(
+        &self,
+        operation: TEE_OperationHandle,
+        chunk: *const c_void,
+        chunkLen: usize,
+        hash: *mut c_void,
+        hashLen: *mut usize,
+    ) -> TEE_Result;
+
+    // Cryptographic Operations API - MAC Functions
+    fn TEE_MACInit(&self, operation: TEE_OperationHandle, IV: *const c_void, IVLen: usize);
+    fn TEE_MACUpdate(&self, operation: TEE_OperationHandle, chunk: *const c_void, chunkSize: usize);
+    fn TEE_MACComputeFinal(
+        &self,
+        operation: TEE_OperationHandle,
+        message: *const c_void,
+        messageLen: usize,
+        mac: *mut c_void,
+        macLen: *mut usize,
+    ) -> TEE_Result;
+
+    // Cryptographic Operations API - Authenticated Encryption Functions
+    fn TEE_AEInit(
+        &self,
+        operation: TEE_OperationHandle,
+        nonce: *const c_void,
+        nonceLen: usize,
+        tagLen: u32,
+        AADLen: usize,
+        payloadLen: usize,
+    ) -> TEE_Result;
+    fn TEE_AEUpdateAAD(
+        &self,
+        operation: TEE_OperationHandle,
+        AADdata: *const c_void,
+        AADdataLen: usize,
+    );
+    fn TEE_AEEncryptFinal(
+        &self,
+        operation: TEE_OperationHandle,
+        srcData: *const c_void,
+        srcLen: usize,
+        destData: *mut c_void,
+        destLen: *mut usize,
+        tag: *mut c_void,
+        tagLen: *mut usize,
+    ) -> TEE_Result;
+    fn TEE_AEDecryptFinal(
+        &self,
+        operation: TEE_OperationHandle,
+        srcData: *const c_void,
+        srcLen: usize,
+        destData: *mut c_void,
+        destLen: *mut usize,
+        tag: *mut c_void,
+        tagLen: usize,
+    ) -> TEE_Result;
+
+    // Cryptographic Operations API - Asymmetric Functions
+    fn TEE_AsymmetricDecrypt(
+        &self,
+        operation: TEE_OperationHandle,
+        params: *const TEE_Attribute,
+        paramCount: u32,
+        srcData: *const c_void,
+        srcLen: usize,
+        destData: *mut c_void,
+        destLen: *mut usize,
+    ) -> TEE_Result;
+    fn TEE_AsymmetricSignDigest(
+        &self,
+        operation: TEE_OperationHandle,
+        params: *const TEE_Attribute,
+        paramCount: u32,
+        digest: *const c_void,
+        digestLen: usize,
+        signature: *mut c_void,
+        signatureLen: *mut usize,
+    ) -> TEE_Result;
+    fn TEE_AsymmetricVerifyDigest(
+        &self,
+        operation: TEE_OperationHandle,
+        params: *const TEE_Attribute,
+        paramCount: u32,
+        digest: *const c_void,
+        digestLen: usize,
+        signature: *const c_void,
+        signatureLen: usize,
+    ) -> TEE_Result;
+
+    // Cryptographic Operations API - Key Derivation Functions
+    fn TEE_DeriveKey(
+        &self,
+        operation: TEE_OperationHandle,
+        params: *const TEE_Attribute,
+        paramCount: u32,
+        derivedKey: TEE_ObjectHandle,
+    );
+
+    // Cryptographic Operations API - Random Number Generation Functions
+    fn TEE_GenerateRandom(&self, randomBuffer: *mut c_void, randomBufferLen: usize);
+}
+
+pub fn set_global_crypto_mock(mock: impl CryptoController + 'static) {
+    let mut value = GLOBAL_CRYPTO_MOCK.write().unwrap();
+    value.replace(Box::new(mock));
+}
+
+fn with_global_crypto_mock<R, F: FnOnce(&dyn CryptoController) -> R>(f: F) -> R {
+    let mock = GLOBAL_CRYPTO_MOCK.read().unwrap();
+    let borrow = mock.as_ref().expect("Global Crypto Mock Not Set");
+    f(borrow.as_ref())
+}
+
+macro_rules! forward_to_mock {
+    ($fn_name:ident($($param:ident: $ty:ty),*) -> $ret:ty) => {
+        #[no_mangle]
+        extern "C" fn $fn_name($($param: $ty),*) -> $ret {
+            with_global_crypto_mock(|mock: &dyn CryptoController| {
+                mock.$fn_name($($param),*)
+            })
+        }
+    };
+}
+
+forward_to_mock!(TEE_AllocateOperation(
+    operation: *mut TEE_OperationHandle,
+    algorithm: u32,
+    mode: u32,
+    maxKeySize: u32
+) -> TEE_Result);
+forward_to_mock!(TEE_FreeOperation(operation: TEE_OperationHandle) -> ());
+forward_to_mock!(TEE_GetOperationInfo(
+    operation: TEE_OperationHandle,
+    operationInfo: *mut TEE_OperationInfo
+) -> ());
+forward_to_mock!(TEE_ResetOperation(operation: TEE_OperationHandle) -> ());
+forward_to_mock!(TEE_SetOperationKey(
+    operation: TEE_OperationHandle,
+    key: TEE_ObjectHandle
+) -> TEE_Result);
+forward_to_mock!(TEE_CopyOperation(
+    dstOperation: TEE_OperationHandle,
+    srcOperation: TEE_OperationHandle
+) -> ());
+
+forward_to_mock!(TEE_DigestUpdate(
+    operation: TEE_OperationHandle,
+    chunk: *const c_void,
+    chunkSize: usize
+) -> ());
+forward_to_mock!(TEE_DigestDoFinal(
+    operation: TEE_OperationHandle,
+    chunk: *const c_void,
+    chunkLen: usize,
+    hash: *mut c_void,
+    hashLen: *mut usize
+) -> TEE_Result);
+
+forward_to_mock!(TEE_MACInit(
+    operation: TEE_OperationHandle,
+    IV: *const c_void,
+    IVLen: usize
+) -> ());
+forward_to_mock!(TEE_MACUpdate(
+    operation: TEE_OperationHandle,
+    chunk: *const c_void,
+    chunkSize: usize
+) -> ());
+forward_to_mock!(TEE_MACComputeFinal(
+    operation: TEE_OperationHandle,
+    message: *const c_void,
+    messageLen: usize,
+    mac: *mut c_void,
+    macLen: *mut usize
+) -> TEE_Result);
+
+forward_to_mock!(TEE_AEInit(
+    operation: TEE_OperationHandle,
+    nonce: *const c_void,
+    nonceLen: usize,
+    tagLen: u32,
+    AADLen: usize,
+    payloadLen: usize
+) -> TEE_Result);
+forward_to_mock!(TEE_AEUpdateAAD(
+    operation: TEE_OperationHandle,
+    AADdata: *const c_void,
+    AADdataLen: usize
+) -> ());
+forward_to_mock!(TEE_AEEncryptFinal(
+    operation: TEE_OperationHandle,
+    srcData: *const c_void,
+    srcLen: usize,
+    destData: *mut c_void,
+    destLen: *mut usize,
+    tag: *mut c_void,
+    tagLen: *mut usize
+) -> TEE_Result);
+forward_to_mock!(TEE_AEDecryptFinal(
+    operation: TEE_OperationHandle,
+    srcData: *const c_void,
+    srcLen: usize,
+    destData: *mut c_void,
+    destLen: *mut usize,
+    tag: *mut c_void,
+    tagLen: usize
+) -> TEE_Result);
+
+forward_to_mock!(TEE_AsymmetricDecrypt(
+    operation: TEE_OperationHandle,
+    params: *const TEE_Attribute,
+    paramCount: u32,
+    srcData: *const c_void,
+    srcLen: usize,
+    destData: *mut c_void,
+    destLen: *mut usize
+) -> TEE_Result);
+forward_to_mock!(TEE_AsymmetricSignDigest(
+    operation: TEE_OperationHandle,
+    params: *const TEE_Attribute,
+    paramCount: u32,
+    digest: *const c_void,
+    digestLen: usize,
+    signature: *mut c_void,
+    signatureLen: *mut usize
+) -> TEE_Result);
+forward_to_mock!(TEE_AsymmetricVerifyDigest(
+    operation: TEE_OperationHandle,
+    params: *const TEE_Attribute,
+    paramCount: u32,
+    digest: *const c_void,
+    digestLen: usize,
+    signature: *const c_void,
+    signatureLen: usize
+) -> TEE_Result);
+
+forward_to_mock!(TEE_DeriveKey(
+    operation: TEE_OperationHandle,
+    params: *const TEE_Attribute,
+    paramCount: u32,
+    derivedKey: TEE_ObjectHandle
+) -> ());
+
+forward_to_mock!(TEE_GenerateRandom(
+    randomBuffer: *mut c_void,
+    randomBufferLen: usize
+) -> ());
+
+impl MockCryptoController {
+    // The handle is never dereferenced, only compared and passed back.
+    pub fn new_valid_test_operation() -> TEE_OperationHandle {
+        NonNull::dangling().as_ptr()
+    }
+
+    // Expect `times` operations of `algorithm` in `mode` to be allocated, and
+    // freed once dropped.
+    pub fn expect_TEE_AllocateOperation_success(
+        &mut self,
+        times: usize,
+        algorithm: u32,
+        mode: u32,
+        max_key_size: u32,
+    ) {
+        self.expect_TEE_AllocateOperation()
+            .withf(move |_, algo, op_mode, key_size| {
+                (*algo, *op_mode, *key_size) == (algorithm, mode, max_key_size)
+            })
+            .times(times)
+            .returning(|operation, _, _, _| {
+                unsafe {
+                    *operation = Self::new_valid_test_operation();
+                }
+                raw::TEE_SUCCESS
+            });
+        self.expect_TEE_FreeOperation()
+            .withf(|operation| *operation == Self::new_valid_test_operation())
+            .times(times)
+            .return_const(());
+    }
+    pub fn expect_TEE_AllocateOperation_fail_once(&mut self, code: TEE_Result) {
+        self.expect_TEE_AllocateOperation()
+            .return_once_st(move |_, _, _, _| code);
+    }
+}
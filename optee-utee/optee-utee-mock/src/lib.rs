@@ -15,9 +15,20 @@
 // specific language governing permissions and limitations
 // under the License.
 
+#[allow(non_snake_case)]
+pub mod crypto;
 #[allow(non_snake_case)]
 pub mod object;
+#[allow(non_snake_case)]
+pub mod time;
 
 // re-export some dependencies;
 pub use mockall;
 pub use optee_utee_sys as raw;
+
+// The trace output of the TA goes to the standard error of the test.
+#[no_mangle]
+extern "C" fn _utee_log(buf: *const core::ffi::c_void, len: usize) {
+    let buf = unsafe { core::slice::from_raw_parts(buf as *const u8, len) };
+    eprint!("{}", String::from_utf8_lossy(buf));
+}
@@ -19,7 +19,10 @@ use std::cell::UnsafeCell;
 use std::ffi::c_void;
 use std::sync::{Arc, Mutex, RwLock};
 
-use crate::raw::{self, TEE_ObjectHandle, TEE_ObjectType, TEE_Result};
+use crate::raw::{
+    self, TEE_Attribute, TEE_ObjectEnumHandle, TEE_ObjectHandle, TEE_ObjectInfo, TEE_ObjectType,
+    TEE_Result, TEE_Whence,
+};
 
 static GLOBAL_OBJECT_MOCK: RwLock<Option<Box<dyn ObjectController + 'static>>> = RwLock::new(None);
 pub static SERIAL_TEST_LOCK: Mutex<()> = Mutex::new(());
@@ -28,6 +31,18 @@ pub static SERIAL_TEST_LOCK: Mutex<()> = Mutex::new(());
 // currently we just add functions that we need
 pub trait ObjectController: Send + Sync {
     // Data and Key Storage API  - Generic Object Functions
+    fn TEE_GetObjectInfo1(
+        &self,
+        object: TEE_ObjectHandle,
+        objectInfo: *mut TEE_ObjectInfo,
+    ) -> TEE_Result;
+    fn TEE_GetObjectBufferAttribute(
+        &self,
+        object: TEE_ObjectHandle,
+        attributeID: u32,
+        buffer: *mut c_void,
+        size: *mut usize,
+    ) -> TEE_Result;
     fn TEE_CloseObject(&self, object: TEE_ObjectHandle);
 
     /* Data and Key Storage API  - Transient Object Functions */
@@ -37,6 +52,12 @@ pub trait ObjectController: Send + Sync {
         maxObjectSize: u32,
         object: *mut TEE_ObjectHandle,
     ) -> TEE_Result;
+    fn TEE_PopulateTransientObject(
+        &self,
+        object: TEE_ObjectHandle,
+        attrs: *const TEE_Attribute,
+        attrCount: u32,
+    ) -> TEE_Result;
 
     // Data and Key Storage API  - Persistent Object Functions
     fn TEE_OpenPersistentObject(
@@ -59,6 +80,40 @@ pub trait ObjectController: Send + Sync {
         object: *mut TEE_ObjectHandle,
     ) -> TEE_Result;
     fn TEE_CloseAndDeletePersistentObject1(&self, object: TEE_ObjectHandle) -> TEE_Result;
+
+    // Data and Key Storage API  - Persistent Object Enumeration Functions
+    fn TEE_AllocatePersistentObjectEnumerator(
+        &self,
+        objectEnumerator: *mut TEE_ObjectEnumHandle,
+    ) -> TEE_Result;
+    fn TEE_FreePersistentObjectEnumerator(&self, objectEnumerator: TEE_ObjectEnumHandle);
+    fn TEE_StartPersistentObjectEnumerator(
+        &self,
+        objectEnumerator: TEE_ObjectEnumHandle,
+        storageID: u32,
+    ) -> TEE_Result;
+    fn TEE_GetNextPersistentObject(
+        &self,
+        objectEnumerator: TEE_ObjectEnumHandle,
+        objectInfo: *mut TEE_ObjectInfo,
+        objectID: *mut c_void,
+        objectIDLen: *mut usize,
+    ) -> TEE_Result;
+
+    // Data and Key Storage API  - Data Stream Access Functions
+    fn TEE_ReadObjectData(
+        &self,
+        object: TEE_ObjectHandle,
+        buffer: *mut c_void,
+        size: usize,
+        count: *mut usize,
+    ) -> TEE_Result;
+    fn TEE_SeekObjectData(
+        &self,
+        object: TEE_ObjectHandle,
+        offset: i64,
+        whence: TEE_Whence,
+    ) -> TEE_Result;
 }
 
 pub fn set_global_object_mock(mock: impl ObjectController + 'static) {
@@ -83,6 +138,16 @@ macro_rules! forward_to_mock {
     };
 }
 
+forward_to_mock!(TEE_GetObjectInfo1(
+    object: TEE_ObjectHandle,
+    objectInfo: *mut TEE_ObjectInfo
+) -> TEE_Result);
+forward_to_mock!(TEE_GetObjectBufferAttribute(
+    object: TEE_ObjectHandle,
+    attributeID: u32,
+    buffer: *mut c_void,
+    size: *mut usize
+) -> TEE_Result);
 forward_to_mock!(TEE_CloseObject(object: TEE_ObjectHandle) -> ());
 
 forward_to_mock!(TEE_AllocateTransientObject(
@@ -90,6 +155,11 @@ forward_to_mock!(TEE_AllocateTransientObject(
     maxObjectSize: u32,
     object: *mut TEE_ObjectHandle
 ) -> TEE_Result);
+forward_to_mock!(TEE_PopulateTransientObject(
+    object: TEE_ObjectHandle,
+    attrs: *const TEE_Attribute,
+    attrCount: u32
+) -> TEE_Result);
 
 forward_to_mock!(TEE_OpenPersistentObject(
     storageID: u32,
@@ -112,6 +182,61 @@ forward_to_mock!(TEE_CloseAndDeletePersistentObject1(
     object: TEE_ObjectHandle
 ) -> TEE_Result);
 
+forward_to_mock!(TEE_AllocatePersistentObjectEnumerator(
+    objectEnumerator: *mut TEE_ObjectEnumHandle
+) -> TEE_Result);
+forward_to_mock!(TEE_FreePersistentObjectEnumerator(
+    objectEnumerator: TEE_ObjectEnumHandle
+) -> ());
+forward_to_mock!(TEE_StartPersistentObjectEnumerator(
+    objectEnumerator: TEE_ObjectEnumHandle,
+    storageID: u32
+) -> TEE_Result);
+forward_to_mock!(TEE_GetNextPersistentObject(
+    objectEnumerator: TEE_ObjectEnumHandle,
+    objectInfo: *mut TEE_ObjectInfo,
+    objectID: *mut c_void,
+    objectIDLen: *mut usize
+) -> TEE_Result);
+
+forward_to_mock!(TEE_ReadObjectData(
+    object: TEE_ObjectHandle,
+    buffer: *mut c_void,
+    size: usize,
+    count: *mut usize
+) -> TEE_Result);
+forward_to_mock!(TEE_SeekObjectData(
+    object: TEE_ObjectHandle,
+    offset: i64,
+    whence: TEE_Whence
+) -> TEE_Result);
+
+// The attribute functions only fill in the attribute, as libutee does, so
+// they are implemented rather than mocked.
+#[no_mangle]
+extern "C" fn TEE_InitRefAttribute(
+    attr: *mut TEE_Attribute,
+    attributeID: u32,
+    buffer: *const c_void,
+    length: usize,
+) {
+    unsafe {
+        (*attr).attributeID = attributeID;
+        (*attr).content.memref = raw::Memref {
+            buffer: buffer as *mut c_void,
+            size: length,
+        };
+    }
+}
+
+#[no_mangle]
+extern "C" fn TEE_InitValueAttribute(attr: *mut TEE_Attribute, attributeID: u32, a: u32, b: u32) {
+    unsafe {
+        (*attr).attributeID = attributeID;
+        (*attr).content.value = raw::Value { a, b };
+    }
+}
+
 type ValidTestHandle = Arc<UnsafeCell<raw::TEE_ObjectHandle>>;
 
 impl MockObjectController {
@@ -179,6 +304,16 @@ impl MockObjectController {
             .return_once_st(move |_| code);
     }
 
+    pub fn expect_TEE_GetObjectInfo1_success_once(&mut self, info: TEE_ObjectInfo) {
+        self.expect_TEE_GetObjectInfo1()
+            .return_once_st(move |_, obj_info| {
+                unsafe {
+                    *obj_info = info;
+                }
+                raw::TEE_SUCCESS
+            });
+    }
+
     pub fn expect_TEE_CloseObject_once(&mut self, exp_handle: ValidTestHandle) {
         self.expect_TEE_CloseObject().return_once_st(move |obj| {
             assert_eq!(obj, unsafe { *exp_handle.get() });
@@ -0,0 +1,61 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+use std::sync::RwLock;
+
+use crate::raw::{self, TEE_Result, TEE_Time};
+
+static GLOBAL_TIME_MOCK: RwLock<Option<Box<dyn TimeController + 'static>>> = RwLock::new(None);
+
+#[mockall::automock]
+// currently we just add functions that we need
+pub trait TimeController: Send + Sync {
+    // Date & Time API
+    fn TEE_GetTAPersistentTime(&self, time: *mut TEE_Time) -> TEE_Result;
+}
+
+pub fn set_global_time_mock(mock: impl TimeController + 'static) {
+    let mut value = GLOBAL_TIME_MOCK.write().unwrap();
+    value.replace(Box::new(mock));
+}
+
+fn with_global_time_mock<R, F: FnOnce(&dyn TimeController) -> R>(f: F) -> R {
+    let mock = GLOBAL_TIME_MOCK.read().unwrap();
+    let borrow = mock.as_ref().expect("Global Time Mock Not Set");
+    f(borrow.as_ref())
+}
+
+macro_rules! forward_to_mock {
+    ($fn_name:ident($($param:ident: $ty:ty),*) -> $ret:ty) => {
+        #[no_mangle]
+        extern "C" fn $fn_name($($param: $ty),*) -> $ret {
+            with_global_time_mock(|mock: &dyn TimeController| {
+                mock.$fn_name($($param),*)
+            })
+        }
+    };
+}
+
+forward_to_mock!(TEE_GetTAPersistentTime(time: *mut TEE_Time) -> TEE_Result);
+
+impl MockTimeController {
+    // Expect the TA persistent time to be read, and to have never been set,
+    // as on a device whose TA has not set it yet.
+    pub fn expect_TEE_GetTAPersistentTime_not_set(&mut self) {
+        self.expect_TEE_GetTAPersistentTime()
+            .return_const(raw::TEE_ERROR_TIME_NOT_SET);
+    }
+}
@@ -20,7 +20,7 @@ use core::{mem, ptr};
 
 use optee_utee_sys as raw;
 
-use crate::{Attribute, Error, GenericObject, Result, TransientObject};
+use crate::{Attribute, Error, ErrorKind, GenericObject, Result, TransientObject};
 
 /// Specify one of the available cryptographic operations.
 #[repr(u32)]
@@ -85,6 +85,11 @@ impl OperationInfo {
     pub fn max_key_size(&self) -> u32 {
         self.raw.maxKeySize
     }
+
+    /// Return the `algorithm` field of the raw structure `TEE_OperationInfo`.
+    pub fn algorithm(&self) -> u32 {
+        self.raw.algorithm
+    }
 }
 
 /// Every operation of [AE](AE), [Asymmetric](Asymmetric), [Cipher](Cipher),
@@ -543,7 +548,9 @@ impl Cipher {
     /// 1.4) [AesXts](AlgorithmId::AesXts): Tweak Value;
     /// 1.5) [AesCcm](AlgorithmId::AesCcm): Nonce Value;
     /// 1.6) [AesGcm](AlgorithmId::AesGcm): Nonce Value;
-    /// 1.7) [AesCbcNopad](AlgorithmId::AesCbcNopad): IV.
+    /// 1.7) [AesCbcNopad](AlgorithmId::AesCbcNopad): IV;
+    /// 1.8) [Sm4CbcNopad](AlgorithmId::Sm4CbcNopad): IV;
+    /// 1.9) [Sm4Ctr](AlgorithmId::Sm4Ctr): Initial Counter Value.
     ///
     /// # Panics
     ///
@@ -1315,7 +1322,7 @@ impl Asymmetric {
         let p: Vec<raw::TEE_Attribute> = params.iter().map(|p| p.raw()).collect();
         let mut res_size: usize = self.info().key_size() as usize;
         let mut res_vec: Vec<u8> = vec![0u8; res_size as usize];
-        match unsafe {
+        let mut code = unsafe {
             raw::TEE_AsymmetricEncrypt(
                 self.handle(),
                 p.as_ptr() as _,
@@ -1325,7 +1332,24 @@ impl Asymmetric {
                 res_vec.as_mut_ptr() as _,
                 &mut res_size,
             )
-        } {
+        };
+        // The ciphertext of some algorithms (e.g. SM2 PKE) grows with the
+        // plaintext, retry once with the size reported by the implementation.
+        if code == raw::TEE_ERROR_SHORT_BUFFER {
+            res_vec.resize(res_size, 0);
+            code = unsafe {
+                raw::TEE_AsymmetricEncrypt(
+                    self.handle(),
+                    p.as_ptr() as _,
+                    params.len() as u32,
+                    src.as_ptr() as _,
+                    src.len(),
+                    res_vec.as_mut_ptr() as _,
+                    &mut res_size,
+                )
+            };
+        }
+        match code {
             raw::TEE_SUCCESS => {
                 res_vec.truncate(res_size);
                 return Ok(res_vec);
@@ -1358,7 +1382,7 @@ impl Asymmetric {
         let p: Vec<raw::TEE_Attribute> = params.iter().map(|p| p.raw()).collect();
         let mut res_size: usize = self.info().key_size() as usize;
         let mut res_vec: Vec<u8> = vec![0u8; res_size as usize];
-        match unsafe {
+        let mut code = unsafe {
             raw::TEE_AsymmetricDecrypt(
                 self.handle(),
                 p.as_ptr() as _,
@@ -1368,7 +1392,22 @@ impl Asymmetric {
                 res_vec.as_mut_ptr() as _,
                 &mut res_size,
             )
-        } {
+        };
+        if code == raw::TEE_ERROR_SHORT_BUFFER {
+            res_vec.resize(res_size, 0);
+            code = unsafe {
+                raw::TEE_AsymmetricDecrypt(
+                    self.handle(),
+                    p.as_ptr() as _,
+                    params.len() as u32,
+                    src.as_ptr() as _,
+                    src.len(),
+                    res_vec.as_mut_ptr() as _,
+                    &mut res_size,
+                )
+            };
+        }
+        match code {
             raw::TEE_SUCCESS => {
                 res_vec.truncate(res_size as usize);
                 return Ok(res_vec);
@@ -1468,6 +1507,154 @@ impl Asymmetric {
         }
     }
 
+    /// Sign a message, hashing it first with the hash algorithm specified by
+    /// the algorithm of the operation.
+    ///
+    /// Supported algorithms are the `RsassaPkcs1V15*`, `RsassaPkcs1PssMgf1*`,
+    /// `DSA*` and `EcDsa*` ones of [AlgorithmId](AlgorithmId) which specify a
+    /// hash algorithm, and [Ed25519](AlgorithmId::Ed25519), which signs the
+    /// message itself.
+    ///
+    /// ECDSA signatures are returned as raw `r || s`, see
+    /// [ecdsa_raw_to_der](Self::ecdsa_raw_to_der) for the DER encoding.
+    ///
+    /// # Parameters
+    ///
+    /// 1) `params`: Optional operation parameters, e.g.
+    ///    [RsaPssSaltLength](crate::AttributeId::RsaPssSaltLength). For
+    ///    [Ed25519](AlgorithmId::Ed25519), the message is signed as pure
+    ///    Ed25519 unless [EddsaCtx](crate::AttributeId::EddsaCtx) selects
+    ///    Ed25519ctx, or [EddsaPrehash](crate::AttributeId::EddsaPrehash)
+    ///    selects Ed25519ph. In all three cases, `message` is the message
+    ///    itself.
+    /// 2) `message`: Input buffer containing the message.
+    ///
+    /// # Example
+    ///
+    /// ``` rust,no_run
+    /// # use optee_utee::{AlgorithmId, Asymmetric, OperationMode};
+    /// # use optee_utee::{AttributeId, AttributeMemref, TransientObject, TransientObjectType};
+    /// # fn main() -> optee_utee::Result<()> {
+    /// let key = TransientObject::allocate(TransientObjectType::Ed25519Keypair, 256)?;
+    /// key.generate_key(256, &[])?;
+    /// let operation = Asymmetric::allocate(AlgorithmId::Ed25519, OperationMode::Sign, 256)?;
+    /// operation.set_key(&key)?;
+    /// let context = AttributeMemref::from_ref(AttributeId::EddsaCtx, b"context");
+    /// let signature = operation.sign_message(&[context.into()], b"message")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// 1) `NotSupported`: If the algorithm of the operation is not supported.
+    /// 2) Any error returned by [Digest::do_final](Digest::do_final) or
+    ///    [sign_digest](Self::sign_digest).
+    ///
+    /// # Panics
+    ///
+    /// 1) Same as [sign_digest](Self::sign_digest).
+    pub fn sign_message(&self, params: &[Attribute], message: &[u8]) -> Result<Vec<u8>> {
+        let info = self.info();
+        let mut hash = [0u8; MAX_HASH_SIZE];
+        let input = message_input(info.algorithm(), message, &mut hash)?;
+        // Large enough for RSA and DSA, and for both coordinates of ECDSA and
+        // EdDSA.
+        let mut signature = vec![0u8; 2 * ((info.key_size() as usize + 7) / 8)];
+        let len = self.sign_digest(params, input, &mut signature)?;
+        signature.truncate(len);
+        Ok(signature)
+    }
+
+    /// Verify the signature of a message, hashing it first with the hash
+    /// algorithm specified by the algorithm of the operation.
+    ///
+    /// Supported algorithms and parameters are the same as for
+    /// [sign_message](Self::sign_message). ECDSA signatures are expected as
+    /// raw `r || s`, see [ecdsa_der_to_raw](Self::ecdsa_der_to_raw).
+    ///
+    /// # Errors
+    ///
+    /// 1) `SignatureInvalid`: If the signature is invalid.
+    /// 2) `NotSupported`: If the algorithm of the operation is not supported.
+    /// 3) Any error returned by [Digest::do_final](Digest::do_final).
+    ///
+    /// # Panics
+    ///
+    /// 1) Same as [verify_digest](Self::verify_digest).
+    pub fn verify_message(
+        &self,
+        params: &[Attribute],
+        message: &[u8],
+        signature: &[u8],
+    ) -> Result<()> {
+        let mut hash = [0u8; MAX_HASH_SIZE];
+        let input = message_input(self.info().algorithm(), message, &mut hash)?;
+        self.verify_digest(params, input, signature)
+    }
+
+    /// Convert a raw `r || s` ECDSA signature, as produced by
+    /// [sign_digest](Self::sign_digest), to the DER encoded `ECDSA-Sig-Value`
+    /// of RFC 3279.
+    ///
+    /// # Errors
+    ///
+    /// 1) `BadFormat`: If `raw` is empty, of odd length, or longer than the
+    ///    132 bytes of a P-521 signature.
+    pub fn ecdsa_raw_to_der(raw: &[u8]) -> Result<Vec<u8>> {
+        if raw.is_empty() || raw.len() % 2 != 0 || raw.len() > 2 * MAX_ECDSA_COORDINATE_SIZE {
+            return Err(Error::new(ErrorKind::BadFormat));
+        }
+        let (r, s) = raw.split_at(raw.len() / 2);
+        let mut body = Vec::with_capacity(raw.len() + 6);
+        der_push_integer(&mut body, r);
+        der_push_integer(&mut body, s);
+        let mut der = Vec::with_capacity(body.len() + 3);
+        der.push(DER_SEQUENCE);
+        der_push_length(&mut der, body.len());
+        der.extend_from_slice(&body);
+        Ok(der)
+    }
+
+    /// Convert a DER encoded `ECDSA-Sig-Value` of RFC 3279 to the raw
+    /// `r || s` signature expected by [verify_digest](Self::verify_digest).
+    ///
+    /// # Parameters
+    ///
+    /// 1) `der`: The DER encoded signature.
+    /// 2) `key_size`: The size of the curve in bits, e.g. 256 for P-256.
+    ///
+    /// # Errors
+    ///
+    /// 1) `BadFormat`: If `der` is not a valid signature for the curve.
+    pub fn ecdsa_der_to_raw(der: &[u8], key_size: usize) -> Result<Vec<u8>> {
+        let coordinate_size = (key_size + 7) / 8;
+        let (mut sequence, trailing) = der_read(der, DER_SEQUENCE)?;
+        if !trailing.is_empty() {
+            return Err(Error::new(ErrorKind::BadFormat));
+        }
+        let mut raw = vec![0u8; 2 * coordinate_size];
+        for coordinate in raw.chunks_mut(coordinate_size) {
+            let (integer, rest) = der_read(sequence, DER_INTEGER)?;
+            sequence = rest;
+            // Positive integers only, without superfluous leading zeros.
+            let value = match integer {
+                [0] => integer,
+                [0, next, ..] if next & 0x80 != 0 => &integer[1..],
+                [first, ..] if *first != 0 && first & 0x80 == 0 => integer,
+                _ => return Err(Error::new(ErrorKind::BadFormat)),
+            };
+            if value.len() > coordinate_size {
+                return Err(Error::new(ErrorKind::BadFormat));
+            }
+            coordinate[coordinate_size - value.len()..].copy_from_slice(value);
+        }
+        if !sequence.is_empty() {
+            return Err(Error::new(ErrorKind::BadFormat));
+        }
+        Ok(raw)
+    }
+
     /// Create an Asymmetric operation without any specific algorithm or other data.
     pub fn null() -> Self {
         Self(OperationHandle::null())
@@ -1508,6 +1695,68 @@ impl OpHandle for Asymmetric {
     }
 }
 
+// The largest output of the hash algorithms returned by
+// `AlgorithmId::digest_algorithm`, SHA-512.
+const MAX_HASH_SIZE: usize = 64;
+// The coordinate size of P-521, the largest supported curve.
+const MAX_ECDSA_COORDINATE_SIZE: usize = 66;
+
+const DER_INTEGER: u8 = 0x02;
+const DER_SEQUENCE: u8 = 0x30;
+
+// Return the input of `sign_digest` and `verify_digest` for `message`: the
+// message itself for Ed25519, otherwise its hash written to `hash`.
+fn message_input<'a>(
+    algorithm: u32,
+    message: &'a [u8],
+    hash: &'a mut [u8; MAX_HASH_SIZE],
+) -> Result<&'a [u8]> {
+    let algorithm = AlgorithmId::from_signature_algorithm(algorithm)
+        .ok_or(Error::new(ErrorKind::NotSupported))?;
+    if algorithm == AlgorithmId::Ed25519 {
+        return Ok(message);
+    }
+    let digest_algorithm = algorithm
+        .digest_algorithm()
+        .ok_or(Error::new(ErrorKind::NotSupported))?;
+    let len = Digest::allocate(digest_algorithm)?.do_final(message, hash)?;
+    Ok(&hash[..len])
+}
+
+fn der_push_length(der: &mut Vec<u8>, len: usize) {
+    // Signatures of the supported curves never exceed 255 bytes.
+    if len >= 0x80 {
+        der.push(0x81);
+    }
+    der.push(len as u8);
+}
+
+fn der_push_integer(der: &mut Vec<u8>, value: &[u8]) {
+    let start = value.iter().position(|b| *b != 0).unwrap_or(value.len());
+    let value = &value[start..];
+    let pad = value.first().map_or(true, |b| b & 0x80 != 0);
+    der.push(DER_INTEGER);
+    der_push_length(der, value.len() + pad as usize);
+    if pad {
+        der.push(0);
+    }
+    der.extend_from_slice(value);
+}
+
+// Read one element tagged `tag` from `der`, returning its content and the
+// remaining bytes.
+fn der_read(der: &[u8], tag: u8) -> Result<(&[u8], &[u8])> {
+    let (len, rest) = match der {
+        [t, 0x81, len, rest @ ..] if *t == tag && *len >= 0x80 => (*len as usize, rest),
+        [t, len, rest @ ..] if *t == tag && *len < 0x80 => (*len as usize, rest),
+        _ => return Err(Error::new(ErrorKind::BadFormat)),
+    };
+    if rest.len() < len {
+        return Err(Error::new(ErrorKind::BadFormat));
+    }
+    Ok(rest.split_at(len))
+}
+
 /// An operation for derive a shared key object.
 pub struct DeriveKey(OperationHandle);
 
@@ -1520,6 +1769,23 @@ impl DeriveKey {
     /// 1) `params`: For algorithm [DhDeriveSharedSecret][AlgorithmId::DhDeriveSharedSecret],
     ///    [DhPublicValue](../object/enum.AttributeId.html#variant.DhPublicValue) is required as
     ///    the passed in attribute.
+    ///    For algorithm [Sm2Kep][AlgorithmId::Sm2Kep], the following attributes are used:
+    /// 1.1) [EccPublicValueX](../object/enum.AttributeId.html#variant.EccPublicValueX) and
+    ///    [EccPublicValueY](../object/enum.AttributeId.html#variant.EccPublicValueY): the peer
+    ///    long-term public key, required;
+    /// 1.2) [EccEphemeralPublicValueX](../object/enum.AttributeId.html#variant.EccEphemeralPublicValueX)
+    ///    and [EccEphemeralPublicValueY](../object/enum.AttributeId.html#variant.EccEphemeralPublicValueY):
+    ///    the peer ephemeral public key, required;
+    /// 1.3) [Sm2IdInitiator](../object/enum.AttributeId.html#variant.Sm2IdInitiator) and
+    ///    [Sm2IdResponder](../object/enum.AttributeId.html#variant.Sm2IdResponder): the
+    ///    distinguishing identifiers of both parties, required;
+    /// 1.4) [Sm2KepUser](../object/enum.AttributeId.html#variant.Sm2KepUser): value attribute whose
+    ///    `a` is 0 for the initiator and 1 for the responder, optional (defaults to initiator);
+    /// 1.5) [Sm2KepConfirmationIn](../object/enum.AttributeId.html#variant.Sm2KepConfirmationIn):
+    ///    the confirmation value received from the peer, optional;
+    /// 1.6) [Sm2KepConfirmationOut](../object/enum.AttributeId.html#variant.Sm2KepConfirmationOut):
+    ///    a buffer created by [AttributeMemref::from_mut_ref](../object/struct.AttributeMemref.html#method.from_mut_ref)
+    ///    that is filled with the confirmation value to send to the peer, optional.
     /// 2) `object`: An uninitialized transient object to be filled with the derived key.
     ///
     /// # Example
@@ -1597,8 +1863,11 @@ impl DeriveKey {
     }
 
     /// Function usage is similar to [Digest::allocate](Digest::allocate).
-    /// Currently only supports [DhDeriveSharedSecret][AlgorithmId::DhDeriveSharedSecret] as
-    /// `algo`.
+    /// Currently supports [DhDeriveSharedSecret][AlgorithmId::DhDeriveSharedSecret],
+    /// [EcDhDeriveSharedSecret][AlgorithmId::EcDhDeriveSharedSecret],
+    /// [X25519][AlgorithmId::X25519], [Sm2Kep][AlgorithmId::Sm2Kep] and the HKDF, Concat KDF
+    /// and PBKDF2 extension algorithms as `algo`. For the latter, [Kdf](crate::Kdf) is easier
+    /// to use.
     pub fn allocate(algo: AlgorithmId, max_key_size: usize) -> Result<Self> {
         match OperationHandle::allocate(algo, OperationMode::Derive, max_key_size) {
             Ok(handle) => Ok(Self(handle)),
@@ -1621,6 +1890,69 @@ impl DeriveKey {
         self.0.set_key(object)
     }
 
+    /// Program the two keys required by [Sm2Kep](AlgorithmId::Sm2Kep).
+    ///
+    /// # Parameters
+    ///
+    /// 1) `object1`: The own long-term [Sm2KepKeypair](../object/enum.TransientObjectType.html#variant.Sm2KepKeypair).
+    /// 2) `object2`: The own ephemeral [Sm2KepKeypair](../object/enum.TransientObjectType.html#variant.Sm2KepKeypair).
+    ///
+    /// # Example
+    ///
+    /// ``` rust,no_run
+    /// # use optee_utee::{AttributeMemref, AttributeValue, AttributeId, TransientObject};
+    /// # use optee_utee::{TransientObjectType, DeriveKey, AlgorithmId};
+    /// # fn main() -> optee_utee::Result<()> {
+    /// # let (peer_x, peer_y, peer_eph_x, peer_eph_y) = ([0u8; 32], [0u8; 32], [0u8; 32], [0u8; 32]);
+    /// let key = TransientObject::allocate(TransientObjectType::Sm2KepKeypair, 256)?;
+    /// key.generate_key(256, &[])?;
+    /// let eph_key = TransientObject::allocate(TransientObjectType::Sm2KepKeypair, 256)?;
+    /// eph_key.generate_key(256, &[])?;
+    /// let operation = DeriveKey::allocate(AlgorithmId::Sm2Kep, 256)?;
+    /// operation.set_key_2(&key, &eph_key)?;
+    /// let mut shared = TransientObject::allocate(TransientObjectType::GenericSecret, 128)?;
+    /// let mut conf_out = [0u8; 32];
+    /// operation.derive(
+    ///     &[
+    ///         AttributeMemref::from_ref(AttributeId::EccPublicValueX, &peer_x).into(),
+    ///         AttributeMemref::from_ref(AttributeId::EccPublicValueY, &peer_y).into(),
+    ///         AttributeMemref::from_ref(AttributeId::EccEphemeralPublicValueX, &peer_eph_x).into(),
+    ///         AttributeMemref::from_ref(AttributeId::EccEphemeralPublicValueY, &peer_eph_y).into(),
+    ///         AttributeMemref::from_ref(AttributeId::Sm2IdInitiator, b"alice").into(),
+    ///         AttributeMemref::from_ref(AttributeId::Sm2IdResponder, b"bob").into(),
+    ///         AttributeValue::from_value(AttributeId::Sm2KepUser, 0, 0).into(),
+    ///         AttributeMemref::from_mut_ref(AttributeId::Sm2KepConfirmationOut, &mut conf_out).into(),
+    ///     ],
+    ///     &mut shared,
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Same as [Cipher::set_key_2](Cipher::set_key_2).
+    ///
+    /// # Panics
+    ///
+    /// 1) If operation is not a valid opened object.
+    /// 2) If object1 or object2 is not initialized.
+    /// 3) If the operation algorithm does not require two keys.
+    /// 4) If the type, size, or usage of any object is not compatible with the algorithm, mode, or size of the operation.
+    /// 5) If the Implementation detects any other error.
+    pub fn set_key_2<T: GenericObject, D: GenericObject>(
+        &self,
+        object1: &T,
+        object2: &D,
+    ) -> Result<()> {
+        match unsafe {
+            raw::TEE_SetOperationKey2(self.handle(), object1.handle(), object2.handle())
+        } {
+            raw::TEE_SUCCESS => Ok(()),
+            code => Err(Error::from_raw_error(code)),
+        }
+    }
+
     /// Function usage is similar to [Digest::copy](Digest::copy).
     pub fn copy<T: OpHandle>(&mut self, src: &T) {
         self.0.copy(src)
@@ -1663,6 +1995,7 @@ impl Random {
 }
 
 /// Algorithms that can be allocated as an crypto operation.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 #[repr(u32)]
 pub enum AlgorithmId {
     /// [Cipher](Cipher) supported algorithm.
@@ -1694,6 +2027,12 @@ pub enum AlgorithmId {
     /// [Mac](Mac) supported algorithm.
     DesCbcMacPkcs5 = 0x30000511,
     /// [Cipher](Cipher) supported algorithm.
+    Sm4EcbNopad = 0x10000014,
+    /// [Cipher](Cipher) supported algorithm.
+    Sm4CbcNopad = 0x10000114,
+    /// [Cipher](Cipher) supported algorithm.
+    Sm4Ctr = 0x10000214,
+    /// [Cipher](Cipher) supported algorithm.
     Des3EcbNopad = 0x10000013,
     /// [Cipher](Cipher) supported algorithm.
     Des3CbcNopad = 0x10000113,
@@ -1800,6 +2139,14 @@ pub enum AlgorithmId {
     Ed25519 = 0x70006043,
     /// [DeriveKey](DeriveKey) supported algorithm.
     X25519 = 0x80000044,
+    /// [Asymmetric](Asymmetric) supported algorithm, can be applied with
+    /// [Sign](OperationMode::Sign) or [Verify](OperationMode::Verify) mode.
+    Sm2DsaSm3 = 0x70006045,
+    /// [Asymmetric](Asymmetric) supported algorithm, can be applied with
+    /// [Encrypt](OperationMode::Encrypt) or [Decrypt](OperationMode::Decrypt) mode.
+    Sm2Pke = 0x80000045,
+    /// [DeriveKey](DeriveKey) supported algorithm.
+    Sm2Kep = 0x60000045,
     /// [Digest](Digest) supported algorithm.
     Md5 = 0x50000001,
     /// [Digest](Digest) supported algorithm.
@@ -1812,6 +2159,8 @@ pub enum AlgorithmId {
     Sha384 = 0x50000005,
     /// [Digest](Digest) supported algorithm.
     Sha512 = 0x50000006,
+    /// [Digest](Digest) supported algorithm.
+    Sm3 = 0x50000007,
     /// [Mac](Mac) supported algorithm.
     Md5Sha1 = 0x5000000F,
     /// [Mac](Mac) supported algorithm.
@@ -1826,10 +2175,104 @@ pub enum AlgorithmId {
     HmacSha384 = 0x30000005,
     /// [Mac](Mac) supported algorithm.
     HmacSha512 = 0x30000006,
+    /// [Mac](Mac) supported algorithm.
+    HmacSm3 = 0x30000007,
+    /// [DeriveKey](DeriveKey) supported algorithm, OP-TEE extension, see [Kdf](crate::Kdf).
+    HkdfMd5DeriveKey = 0x800010C0,
+    /// [DeriveKey](DeriveKey) supported algorithm, OP-TEE extension, see [Kdf](crate::Kdf).
+    HkdfSha1DeriveKey = 0x800020C0,
+    /// [DeriveKey](DeriveKey) supported algorithm, OP-TEE extension, see [Kdf](crate::Kdf).
+    HkdfSha224DeriveKey = 0x800030C0,
+    /// [DeriveKey](DeriveKey) supported algorithm, OP-TEE extension, see [Kdf](crate::Kdf).
+    HkdfSha256DeriveKey = 0x800040C0,
+    /// [DeriveKey](DeriveKey) supported algorithm, OP-TEE extension, see [Kdf](crate::Kdf).
+    HkdfSha384DeriveKey = 0x800050C0,
+    /// [DeriveKey](DeriveKey) supported algorithm, OP-TEE extension, see [Kdf](crate::Kdf).
+    HkdfSha512DeriveKey = 0x800060C0,
+    /// [DeriveKey](DeriveKey) supported algorithm, OP-TEE extension, see [Kdf](crate::Kdf).
+    ConcatKdfSha1DeriveKey = 0x800020C1,
+    /// [DeriveKey](DeriveKey) supported algorithm, OP-TEE extension, see [Kdf](crate::Kdf).
+    ConcatKdfSha224DeriveKey = 0x800030C1,
+    /// [DeriveKey](DeriveKey) supported algorithm, OP-TEE extension, see [Kdf](crate::Kdf).
+    ConcatKdfSha256DeriveKey = 0x800040C1,
+    /// [DeriveKey](DeriveKey) supported algorithm, OP-TEE extension, see [Kdf](crate::Kdf).
+    ConcatKdfSha384DeriveKey = 0x800050C1,
+    /// [DeriveKey](DeriveKey) supported algorithm, OP-TEE extension, see [Kdf](crate::Kdf).
+    ConcatKdfSha512DeriveKey = 0x800060C1,
+    /// [DeriveKey](DeriveKey) supported algorithm, OP-TEE extension, see [Kdf](crate::Kdf).
+    Pbkdf2HmacSha1DeriveKey = 0x800020C2,
     /// Reserved for GlobalPlatform compliance test applications.
     IllegalValue = 0xefffffff,
 }
 
+impl AlgorithmId {
+    // Algorithms supported by `Asymmetric::sign_message`.
+    const MESSAGE_SIGNATURE_ALGORITHMS: [AlgorithmId; 22] = [
+        AlgorithmId::RsassaPkcs1V15MD5,
+        AlgorithmId::RsassaPkcs1V15Sha1,
+        AlgorithmId::RsassaPkcs1V15Sha224,
+        AlgorithmId::RsassaPkcs1V15Sha256,
+        AlgorithmId::RsassaPkcs1V15Sha384,
+        AlgorithmId::RsassaPkcs1V15Sha512,
+        AlgorithmId::RsassaPkcs1V15MD5Sha1,
+        AlgorithmId::RsassaPkcs1PssMgf1MD5,
+        AlgorithmId::RsassaPkcs1PssMgf1Sha1,
+        AlgorithmId::RsassaPkcs1PssMgf1Sha224,
+        AlgorithmId::RsassaPkcs1PssMgf1Sha256,
+        AlgorithmId::RsassaPkcs1PssMgf1Sha384,
+        AlgorithmId::RsassaPkcs1PssMgf1Sha512,
+        AlgorithmId::DSASha1,
+        AlgorithmId::DSASha224,
+        AlgorithmId::DSASha256,
+        AlgorithmId::EcDsaSha1,
+        AlgorithmId::EcDsaSha224,
+        AlgorithmId::EcDsaSha256,
+        AlgorithmId::EcDsaSha384,
+        AlgorithmId::EcDsaSha512,
+        AlgorithmId::Ed25519,
+    ];
+
+    /// Return the signature algorithm identified by the raw value `algorithm`
+    /// if it is supported by [Asymmetric::sign_message](Asymmetric::sign_message).
+    pub(crate) fn from_signature_algorithm(algorithm: u32) -> Option<AlgorithmId> {
+        Self::MESSAGE_SIGNATURE_ALGORITHMS
+            .iter()
+            .copied()
+            .find(|a| *a as u32 == algorithm)
+    }
+
+    /// Return the [Digest](Digest) algorithm a signature algorithm expects its
+    /// input to be hashed with, or `None` if it does not take a digest of a
+    /// well-known hash algorithm.
+    pub(crate) fn digest_algorithm(&self) -> Option<AlgorithmId> {
+        match self {
+            AlgorithmId::RsassaPkcs1V15MD5 | AlgorithmId::RsassaPkcs1PssMgf1MD5 => {
+                Some(AlgorithmId::Md5)
+            }
+            AlgorithmId::RsassaPkcs1V15Sha1
+            | AlgorithmId::RsassaPkcs1PssMgf1Sha1
+            | AlgorithmId::DSASha1
+            | AlgorithmId::EcDsaSha1 => Some(AlgorithmId::Sha1),
+            AlgorithmId::RsassaPkcs1V15Sha224
+            | AlgorithmId::RsassaPkcs1PssMgf1Sha224
+            | AlgorithmId::DSASha224
+            | AlgorithmId::EcDsaSha224 => Some(AlgorithmId::Sha224),
+            AlgorithmId::RsassaPkcs1V15Sha256
+            | AlgorithmId::RsassaPkcs1PssMgf1Sha256
+            | AlgorithmId::DSASha256
+            | AlgorithmId::EcDsaSha256 => Some(AlgorithmId::Sha256),
+            AlgorithmId::RsassaPkcs1V15Sha384
+            | AlgorithmId::RsassaPkcs1PssMgf1Sha384
+            | AlgorithmId::EcDsaSha384 => Some(AlgorithmId::Sha384),
+            AlgorithmId::RsassaPkcs1V15Sha512
+            | AlgorithmId::RsassaPkcs1PssMgf1Sha512
+            | AlgorithmId::EcDsaSha512 => Some(AlgorithmId::Sha512),
+            AlgorithmId::RsassaPkcs1V15MD5Sha1 => Some(AlgorithmId::Md5Sha1),
+            _ => None,
+        }
+    }
+}
+
 /// This specification defines support for optional cryptographic elements.
 #[repr(u32)]
 pub enum ElementId {
@@ -1848,4 +2291,178 @@ pub enum ElementId {
     EccCurveNistP521 = 0x00000005,
     /// Source: `IETF`, Generic: `N`, Size: 256 bits
     EccCurve25519 = 0x00000300,
+    /// Source: `OSCCA`, Generic: `N`, Size: 256 bits
+    EccCurveSm2 = 0x00000400,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use optee_utee_mock::{
+        crypto::{set_global_crypto_mock, MockCryptoController},
+        object::SERIAL_TEST_LOCK,
+    };
+
+    #[test]
+    fn test_ecdsa_signature_conversion() {
+        // The high bit of `r` is set and `s` has a leading zero byte.
+        let mut raw = vec![0u8; 64];
+        raw[0] = 0x80;
+        raw[31] = 0x01;
+        raw[33] = 0x7f;
+        raw[63] = 0x02;
+        let der = Asymmetric::ecdsa_raw_to_der(&raw).unwrap();
+        assert_eq!(&der[..5], &[0x30, 0x44, 0x02, 0x21, 0x00]);
+        assert_eq!(&der[37..39], &[0x02, 0x1f]);
+        assert_eq!(Asymmetric::ecdsa_der_to_raw(&der, 256).unwrap(), raw);
+
+        // P-521 needs the long form length of the sequence.
+        let raw = vec![0xffu8; 132];
+        let der = Asymmetric::ecdsa_raw_to_der(&raw).unwrap();
+        assert_eq!(&der[..3], &[0x30, 0x81, 0x8a]);
+        assert_eq!(Asymmetric::ecdsa_der_to_raw(&der, 521).unwrap(), raw);
+    }
+
+    #[test]
+    fn test_ecdsa_invalid_der() {
+        let der = [0x30, 0x06, 0x02, 0x01, 0x01, 0x02, 0x01, 0x01];
+        assert!(Asymmetric::ecdsa_der_to_raw(&der, 256).is_ok());
+        // Trailing data, negative and non-minimal integers, too large values.
+        for der in [
+            &[0x30, 0x06, 0x02, 0x01, 0x01, 0x02, 0x01, 0x01, 0x00][..],
+            &[0x30, 0x06, 0x02, 0x01, 0x81, 0x02, 0x01, 0x01][..],
+            &[0x30, 0x07, 0x02, 0x02, 0x00, 0x01, 0x02, 0x01, 0x01][..],
+            &[0x30, 0x08, 0x02, 0x03, 0x01, 0x00, 0x00, 0x02, 0x01, 0x01][..],
+        ] {
+            let err = Asymmetric::ecdsa_der_to_raw(der, 16).err().unwrap();
+            assert_eq!(err.kind(), ErrorKind::BadFormat);
+        }
+        assert!(Asymmetric::ecdsa_raw_to_der(&[0u8; 3]).is_err());
+        assert!(Asymmetric::ecdsa_raw_to_der(&[0u8; 134]).is_err());
+    }
+
+    fn bytes<'a>(data: *const core::ffi::c_void, len: usize) -> &'a [u8] {
+        unsafe { core::slice::from_raw_parts(data as *const u8, len) }
+    }
+
+    // Mock a sign and a verify operation of `algorithm`, whose signature of
+    // `input` is `signature`.
+    fn mock_signature(
+        crypto_mock: &mut MockCryptoController,
+        algorithm: u32,
+        key_size: u32,
+        input: &'static [u8],
+        signature: &'static [u8],
+    ) {
+        crypto_mock.expect_TEE_AllocateOperation_success(
+            1,
+            algorithm,
+            raw::TEE_MODE_SIGN,
+            key_size,
+        );
+        crypto_mock.expect_TEE_AllocateOperation_success(
+            1,
+            algorithm,
+            raw::TEE_MODE_VERIFY,
+            key_size,
+        );
+        crypto_mock
+            .expect_TEE_GetOperationInfo()
+            .times(2)
+            .returning(move |_, info| unsafe {
+                (*info).algorithm = algorithm;
+                (*info).keySize = key_size;
+            });
+        crypto_mock
+            .expect_TEE_AsymmetricSignDigest()
+            .times(1)
+            .returning(move |_, _, _, digest, digest_len, dest, dest_len| {
+                assert_eq!(bytes(digest, digest_len), input);
+                let dest = unsafe {
+                    assert!(*dest_len >= signature.len());
+                    *dest_len = signature.len();
+                    core::slice::from_raw_parts_mut(dest as *mut u8, signature.len())
+                };
+                dest.copy_from_slice(signature);
+                raw::TEE_SUCCESS
+            });
+        crypto_mock
+            .expect_TEE_AsymmetricVerifyDigest()
+            .times(1)
+            .returning(move |_, _, _, digest, digest_len, sig, sig_len| {
+                assert_eq!(bytes(digest, digest_len), input);
+                if bytes(sig, sig_len) == signature {
+                    raw::TEE_SUCCESS
+                } else {
+                    raw::TEE_ERROR_SIGNATURE_INVALID
+                }
+            });
+    }
+
+    #[test]
+    fn test_sign_message_hashes() {
+        let _lock = SERIAL_TEST_LOCK.lock();
+        let algorithm = AlgorithmId::RsassaPkcs1V15Sha384;
+        let mut crypto_mock = MockCryptoController::new();
+        mock_signature(
+            &mut crypto_mock,
+            algorithm as u32,
+            2048,
+            &[0x38; 48],
+            &[0x5a; 256],
+        );
+        // the message is hashed with the digest algorithm of the signature
+        crypto_mock.expect_TEE_AllocateOperation_success(
+            2,
+            raw::TEE_ALG_SHA384,
+            raw::TEE_MODE_DIGEST,
+            0,
+        );
+        crypto_mock.expect_TEE_DigestDoFinal().times(2).returning(
+            |_, chunk, chunk_len, hash, hash_len| {
+                assert_eq!(bytes(chunk, chunk_len), b"message");
+                let hash = unsafe {
+                    assert_eq!(*hash_len, MAX_HASH_SIZE);
+                    *hash_len = 48;
+                    core::slice::from_raw_parts_mut(hash as *mut u8, 48)
+                };
+                hash.fill(0x38);
+                raw::TEE_SUCCESS
+            },
+        );
+        set_global_crypto_mock(crypto_mock);
+
+        let operation = Asymmetric::allocate(algorithm, OperationMode::Sign, 2048).unwrap();
+        let signature = operation.sign_message(&[], b"message").unwrap();
+        assert_eq!(signature, [0x5a; 256]);
+        let operation = Asymmetric::allocate(algorithm, OperationMode::Verify, 2048).unwrap();
+        operation
+            .verify_message(&[], b"message", &signature)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_sign_message_ed25519() {
+        let _lock = SERIAL_TEST_LOCK.lock();
+        let algorithm = AlgorithmId::Ed25519;
+        // no digest operation is expected: Ed25519 signs the message itself
+        let mut crypto_mock = MockCryptoController::new();
+        mock_signature(
+            &mut crypto_mock,
+            algorithm as u32,
+            256,
+            b"message",
+            &[0xed; 64],
+        );
+        set_global_crypto_mock(crypto_mock);
+
+        let operation = Asymmetric::allocate(algorithm, OperationMode::Sign, 256).unwrap();
+        let signature = operation.sign_message(&[], b"message").unwrap();
+        assert_eq!(signature, [0xed; 64]);
+        let operation = Asymmetric::allocate(algorithm, OperationMode::Verify, 256).unwrap();
+        let err = operation
+            .verify_message(&[], b"message", &signature[1..])
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::SignatureInvalid);
+    }
 }
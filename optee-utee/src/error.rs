@@ -15,8 +15,11 @@
 // specific language governing permissions and limitations
 // under the License.
 
+use alloc::borrow::Cow;
+use core::convert::TryFrom;
 #[cfg(not(target_os = "optee"))]
 use core::error;
+use core::ops::RangeInclusive;
 use core::{fmt, result};
 use optee_utee_sys as raw;
 #[cfg(target_os = "optee")]
@@ -39,9 +42,22 @@ pub type Result<T> = result::Result<T, Error>;
 #[derive(Clone)]
 pub struct Error {
     kind: ErrorKind,
+    code: u32,
     origin: Option<ErrorOrigin>,
+    context: Option<Cow<'static, str>>,
 }
 
+/// The range of error codes of the errors defined by Trusted Applications, see [`Error::app`].
+///
+/// An application error code `n` is returned by the TA as `0x8000_0000 + n`, and the client
+/// gets `n` back with `optee_teec::Error::app_code`. OP-TEE defines `TEE_ERROR_DEFER_DRIVER_INIT`
+/// (0x8000_0000) and `TEE_ERROR_NODE_DISABLED` (0x8000_0001) in `tee_api_defines.h` of
+/// optee_os, so `n` starts at 2 and an application error is never mistaken for these.
+pub const APP_ERROR_CODES: RangeInclusive<u32> = 0x8000_0002..=0x8000_FFFF;
+
+// The raw code of the application error code 0.
+const APP_ERROR_BASE: u32 = 0x8000_0000;
+
 /// A list specifying general categories of TEE error and its corresponding code
 /// in OP-TEE OS.
 #[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
@@ -200,7 +216,7 @@ impl From<u32> for ErrorKind {
 
 impl Error {
     pub fn new(kind: ErrorKind) -> Error {
-        Error { kind, origin: None }
+        Error::from(kind)
     }
 
     /// Creates a new instance of an `Error` from a particular TEE error code.
@@ -216,8 +232,92 @@ impl Error {
     pub fn from_raw_error(code: u32) -> Error {
         Error {
             kind: ErrorKind::from(code),
+            code,
             origin: None,
+            context: None,
+        }
+    }
+
+    /// Creates an error defined by the Trusted Application, whose raw code is
+    /// `0x8000_0000 + code` in [`APP_ERROR_CODES`], so the client can tell it apart from the
+    /// GlobalPlatform and OP-TEE errors.
+    ///
+    /// The errors of a TA are usually an enum of the proto crate shared with the client, which
+    /// converts into and from a `u16`, e.g. with the `num_enum` crate.
+    ///
+    /// # Examples
+    ///
+    /// ``` no_run
+    /// use optee_utee::{Error, ErrorKind};
+    ///
+    /// #[derive(Clone, Copy, Debug, PartialEq)]
+    /// #[repr(u16)]
+    /// enum WalletError {
+    ///     NotFound = 2,
+    ///     PinLocked = 3,
+    /// }
+    ///
+    /// impl From<WalletError> for u16 {
+    ///     fn from(error: WalletError) -> u16 {
+    ///         error as u16
+    ///     }
+    /// }
+    ///
+    /// let error = Error::app(WalletError::PinLocked);
+    /// assert_eq!(error.raw_code(), 0x8000_0003);
+    /// assert_eq!(error.app_code(), Some(3));
+    /// assert_eq!(error.kind(), ErrorKind::Unknown);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// If `code` is 0 or 1, which are reserved, see [`APP_ERROR_CODES`].
+    pub fn app(code: impl Into<u16>) -> Error {
+        let code = APP_ERROR_BASE + code.into() as u32;
+        assert!(
+            APP_ERROR_CODES.contains(&code),
+            "application error codes 0 and 1 are reserved"
+        );
+        Error::from_raw_error(code)
+    }
+
+    /// Creates an error from any other error, e.g. the error of a library, and prints `error`
+    /// and its chain of sources to the trace output.
+    ///
+    /// The error keeps the code of the first `Error` of the chain, or else has the kind `kind`,
+    /// and `error` as its context.
+    ///
+    /// # Examples
+    ///
+    /// ``` no_run
+    /// use optee_utee::{Error, ErrorKind};
+    ///
+    /// # fn parse() -> Result<u32, core::num::ParseIntError> { "x".parse() }
+    /// let error = parse()
+    ///     .map_err(|e| Error::from_error(ErrorKind::BadFormat, &e))
+    ///     .expect_err("not a number");
+    /// assert_eq!(error.kind(), ErrorKind::BadFormat);
+    /// ```
+    pub fn from_error(kind: ErrorKind, error: &(dyn error::Error + 'static)) -> Error {
+        crate::trace_println!("[-] {}", Chain(error));
+        match error.downcast_ref::<Error>() {
+            Some(tee_error) => tee_error.clone(),
+            None => Error::in_chain(error)
+                .unwrap_or_else(|| Error::new(kind))
+                .with_context(format!("{}", error)),
+        }
+    }
+
+    // The first Error of the chain of sources of `error`, `error` excluded.
+    fn in_chain(error: &(dyn error::Error + 'static)) -> Option<Error> {
+        let mut source = error.source();
+        while let Some(error) = source {
+            if let Some(tee_error) = error.downcast_ref::<Error>() {
+                return Some(tee_error.clone());
+            }
+            source = error.source();
         }
+        None
     }
 
     pub fn with_origin(mut self, origin: ErrorOrigin) -> Self {
@@ -225,6 +325,27 @@ impl Error {
         self
     }
 
+    /// Attaches a description of what failed to this error, which is printed before its
+    /// message, replacing the previous context. The context is not returned to the client.
+    ///
+    /// # Examples
+    ///
+    /// ``` no_run
+    /// use optee_utee::{Error, ErrorKind};
+    ///
+    /// let error = Error::new(ErrorKind::ItemNotFound).with_context("loading the wallet");
+    /// assert_eq!(error.context(), Some("loading the wallet"));
+    /// ```
+    pub fn with_context(mut self, context: impl Into<Cow<'static, str>>) -> Self {
+        self.context = Some(context.into());
+        self
+    }
+
+    /// Returns the context of this error, see [`Error::with_context`].
+    pub fn context(&self) -> Option<&str> {
+        self.context.as_deref()
+    }
+
     /// Returns the corresponding `ErrorKind` for this error.
     ///
     /// # Examples
@@ -245,17 +366,39 @@ impl Error {
 
     /// Returns raw code of this error.
     pub fn raw_code(&self) -> u32 {
-        self.kind.into()
+        self.code
     }
 
     /// Returns corresponding error message of this error.
     pub fn message(&self) -> &str {
-        self.kind().as_str()
+        match self.app_code() {
+            Some(_) => "Error defined by the Trusted Application.",
+            None => self.kind().as_str(),
+        }
+    }
+
+    /// Returns the code of the error defined by the Trusted Application, if this is one, see
+    /// [`Error::app`]. The [`ErrorKind`] of such an error is `Unknown`.
+    pub fn app_code(&self) -> Option<u16> {
+        if APP_ERROR_CODES.contains(&self.code) {
+            Some((self.code - APP_ERROR_BASE) as u16)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the error defined by the Trusted Application, if this is one and its code is a
+    /// valid `E`, see [`Error::app`].
+    pub fn app_error<E: TryFrom<u16>>(&self) -> Option<E> {
+        self.app_code().and_then(|code| E::try_from(code).ok())
     }
 }
 
 impl fmt::Debug for Error {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(context) = self.context() {
+            write!(fmt, "{}: ", context)?;
+        }
         write!(
             fmt,
             "{} (error code 0x{:x}, origin 0x{:x})",
@@ -278,10 +421,61 @@ impl error::Error for Error {
     }
 }
 
+// Displays an error and its sources, separated by colons.
+struct Chain<'a>(&'a (dyn error::Error + 'static));
+
+impl fmt::Display for Chain<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)?;
+        let mut source = self.0.source();
+        while let Some(error) = source {
+            write!(f, ": {}", error)?;
+            source = error.source();
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "anyhow")]
+impl From<anyhow::Error> for Error {
+    /// Prints the error and its chain of sources to the trace output, and keeps the code of the
+    /// first `Error` of the chain, if any, or else has the kind `Generic`, as
+    /// [`Error::from_error`] does.
+    fn from(error: anyhow::Error) -> Error {
+        crate::trace_println!("[-] {:#}", error);
+        match error.downcast_ref::<Error>() {
+            Some(tee_error) => tee_error.clone(),
+            None => in_anyhow_chain(&error)
+                .unwrap_or_else(|| Error::new(ErrorKind::Generic))
+                .with_context(format!("{}", error)),
+        }
+    }
+}
+
+// The first Error of the chain of sources of `error`. anyhow only keeps the
+// sources with std, as in std TAs.
+#[cfg(all(feature = "anyhow", any(target_os = "optee", test)))]
+fn in_anyhow_chain(error: &anyhow::Error) -> Option<Error> {
+    error
+        .chain()
+        .find_map(|error| error.downcast_ref::<Error>())
+        .cloned()
+}
+
+#[cfg(all(feature = "anyhow", not(any(target_os = "optee", test))))]
+fn in_anyhow_chain(_error: &anyhow::Error) -> Option<Error> {
+    None
+}
+
 impl From<ErrorKind> for Error {
     #[inline]
     fn from(kind: ErrorKind) -> Error {
-        Error { kind, origin: None }
+        Error {
+            kind,
+            code: kind.into(),
+            origin: None,
+            context: None,
+        }
     }
 }
 
@@ -313,3 +507,97 @@ impl From<u32> for ErrorOrigin {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_app_error() {
+        let error = Error::app(2u16);
+        assert_eq!(error.raw_code(), 0x8000_0002);
+        assert_eq!(error.kind(), ErrorKind::Unknown);
+        assert_eq!(error.app_code(), Some(2));
+        assert_eq!(error.app_error::<u8>(), Some(2));
+        assert_eq!(Error::app(u16::MAX).raw_code(), *APP_ERROR_CODES.end());
+
+        // The codes of OP-TEE below the range are not application errors.
+        for code in [0x8000_0000, 0x8000_0001] {
+            assert_eq!(Error::from_raw_error(code).app_code(), None);
+        }
+
+        let error = Error::new(ErrorKind::Security);
+        assert_eq!(error.raw_code(), raw::TEE_ERROR_SECURITY);
+        assert_eq!(error.app_code(), None);
+
+        // Codes which are neither GlobalPlatform nor application codes are kept.
+        let error = Error::from_raw_error(0x1234);
+        assert_eq!(error.kind(), ErrorKind::Unknown);
+        assert_eq!(error.raw_code(), 0x1234);
+        assert_eq!(error.app_code(), None);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_reserved_app_error() {
+        Error::app(1u16);
+    }
+
+    #[derive(Debug)]
+    struct Wrapper(&'static str, Option<Error>);
+
+    impl fmt::Display for Wrapper {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str(self.0)
+        }
+    }
+
+    impl error::Error for Wrapper {
+        fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+            self.1.as_ref().map(|e| e as _)
+        }
+    }
+
+    #[test]
+    fn test_chain() {
+        let error = Wrapper("opening the wallet", Some(ErrorKind::ItemNotFound.into()));
+        assert_eq!(
+            format!("{}", Chain(&error)),
+            format!(
+                "opening the wallet: {}",
+                Error::new(ErrorKind::ItemNotFound)
+            )
+        );
+        let found = Error::in_chain(&error).expect("it should be found");
+        assert_eq!(found.kind(), ErrorKind::ItemNotFound);
+
+        assert!(Error::in_chain(&Wrapper("no source", None)).is_none());
+    }
+
+    #[test]
+    fn test_context() {
+        let error = Error::new(ErrorKind::ItemNotFound).with_context("loading the wallet");
+        assert_eq!(error.context(), Some("loading the wallet"));
+        assert_eq!(error.raw_code(), raw::TEE_ERROR_ITEM_NOT_FOUND);
+        assert!(format!("{}", error).starts_with("loading the wallet: "));
+        assert_eq!(Error::new(ErrorKind::Generic).context(), None);
+    }
+
+    #[cfg(feature = "anyhow")]
+    #[test]
+    fn test_from_anyhow() {
+        let error = anyhow::Error::msg(Error::new(ErrorKind::ItemNotFound)).context("loading");
+        let error: Error = error.into();
+        assert_eq!(error.raw_code(), raw::TEE_ERROR_ITEM_NOT_FOUND);
+
+        let error: Error = anyhow::anyhow!("invalid wallet").into();
+        assert_eq!(error.kind(), ErrorKind::Generic);
+        assert_eq!(error.context(), Some("invalid wallet"));
+
+        // an Error which is the source of a library error
+        let error = anyhow::Error::new(Wrapper("reading", Some(ErrorKind::AccessDenied.into())));
+        let error: Error = error.context("opening").into();
+        assert_eq!(error.kind(), ErrorKind::AccessDenied);
+        assert_eq!(error.context(), Some("opening"));
+    }
+}
@@ -0,0 +1,485 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use alloc::vec::Vec;
+
+use crate::{
+    AlgorithmId, Attribute, AttributeId, AttributeMemref, AttributeValue, DeriveKey, Error,
+    ErrorKind, GenericObject, Result, TransientObject, TransientObjectType,
+};
+
+/// Parameters of one of the key derivation functions supported by [Kdf](Kdf).
+///
+/// Implemented by [HkdfParams](HkdfParams), [ConcatKdfParams](ConcatKdfParams) and
+/// [Pbkdf2Params](Pbkdf2Params).
+pub trait KdfParams {
+    /// Type of the transient object holding the input secret.
+    fn secret_type(&self) -> TransientObjectType;
+
+    /// Attribute used to populate the input secret.
+    fn secret_attribute_id(&self) -> AttributeId;
+
+    /// Length in bytes of the derived keying material.
+    fn output_len(&self) -> usize;
+
+    /// Operation parameters passed to [DeriveKey::derive](DeriveKey::derive).
+    fn attributes(&self) -> Vec<Attribute>;
+
+    /// Return `true` if `algo` belongs to this key derivation function.
+    fn accepts(&self, algo: &AlgorithmId) -> bool;
+}
+
+/// Parameters of the HKDF (RFC 5869) extension algorithms.
+pub struct HkdfParams<'a> {
+    salt: Option<&'a [u8]>,
+    info: Option<&'a [u8]>,
+    okm_len: usize,
+}
+
+impl<'a> HkdfParams<'a> {
+    /// Create the parameters for deriving `okm_len` bytes of output keying material.
+    pub fn new(okm_len: usize) -> Self {
+        Self {
+            salt: None,
+            info: None,
+            okm_len,
+        }
+    }
+
+    /// Set the optional salt.
+    pub fn with_salt(mut self, salt: &'a [u8]) -> Self {
+        self.salt = Some(salt);
+        self
+    }
+
+    /// Set the optional context and application specific information.
+    pub fn with_info(mut self, info: &'a [u8]) -> Self {
+        self.info = Some(info);
+        self
+    }
+}
+
+impl<'a> KdfParams for HkdfParams<'a> {
+    fn secret_type(&self) -> TransientObjectType {
+        TransientObjectType::HkdfIkm
+    }
+
+    fn secret_attribute_id(&self) -> AttributeId {
+        AttributeId::HkdfIkm
+    }
+
+    fn output_len(&self) -> usize {
+        self.okm_len
+    }
+
+    fn attributes(&self) -> Vec<Attribute> {
+        let mut attrs: Vec<Attribute> =
+            vec![
+                AttributeValue::from_value(AttributeId::HkdfOkmLength, self.okm_len as u32, 0)
+                    .into(),
+            ];
+        if let Some(salt) = self.salt {
+            attrs.push(AttributeMemref::from_ref(AttributeId::HkdfSalt, salt).into());
+        }
+        if let Some(info) = self.info {
+            attrs.push(AttributeMemref::from_ref(AttributeId::HkdfInfo, info).into());
+        }
+        attrs
+    }
+
+    fn accepts(&self, algo: &AlgorithmId) -> bool {
+        matches!(
+            algo,
+            AlgorithmId::HkdfMd5DeriveKey
+                | AlgorithmId::HkdfSha1DeriveKey
+                | AlgorithmId::HkdfSha224DeriveKey
+                | AlgorithmId::HkdfSha256DeriveKey
+                | AlgorithmId::HkdfSha384DeriveKey
+                | AlgorithmId::HkdfSha512DeriveKey
+        )
+    }
+}
+
+/// Parameters of the Concat KDF (NIST SP 800-56A section 5.8.1) extension
+/// algorithms.
+pub struct ConcatKdfParams<'a> {
+    other_info: Option<&'a [u8]>,
+    dkm_len: usize,
+}
+
+impl<'a> ConcatKdfParams<'a> {
+    /// Create the parameters for deriving `dkm_len` bytes of keying material.
+    pub fn new(dkm_len: usize) -> Self {
+        Self {
+            other_info: None,
+            dkm_len,
+        }
+    }
+
+    /// Set the optional `OtherInfo` field.
+    pub fn with_other_info(mut self, other_info: &'a [u8]) -> Self {
+        self.other_info = Some(other_info);
+        self
+    }
+}
+
+impl<'a> KdfParams for ConcatKdfParams<'a> {
+    fn secret_type(&self) -> TransientObjectType {
+        TransientObjectType::ConcatKdfZ
+    }
+
+    fn secret_attribute_id(&self) -> AttributeId {
+        AttributeId::ConcatKdfZ
+    }
+
+    fn output_len(&self) -> usize {
+        self.dkm_len
+    }
+
+    fn attributes(&self) -> Vec<Attribute> {
+        let mut attrs: Vec<Attribute> = vec![AttributeValue::from_value(
+            AttributeId::ConcatKdfDkmLength,
+            self.dkm_len as u32,
+            0,
+        )
+        .into()];
+        if let Some(other_info) = self.other_info {
+            attrs.push(
+                AttributeMemref::from_ref(AttributeId::ConcatKdfOtherInfo, other_info).into(),
+            );
+        }
+        attrs
+    }
+
+    fn accepts(&self, algo: &AlgorithmId) -> bool {
+        matches!(
+            algo,
+            AlgorithmId::ConcatKdfSha1DeriveKey
+                | AlgorithmId::ConcatKdfSha224DeriveKey
+                | AlgorithmId::ConcatKdfSha256DeriveKey
+                | AlgorithmId::ConcatKdfSha384DeriveKey
+                | AlgorithmId::ConcatKdfSha512DeriveKey
+        )
+    }
+}
+
+/// Parameters of the PBKDF2 (RFC 2898) extension algorithm.
+pub struct Pbkdf2Params<'a> {
+    salt: &'a [u8],
+    iteration_count: u32,
+    dkm_len: usize,
+}
+
+impl<'a> Pbkdf2Params<'a> {
+    /// Create the parameters for deriving `dkm_len` bytes of keying material
+    /// from `salt` with `iteration_count` rounds.
+    pub fn new(salt: &'a [u8], iteration_count: u32, dkm_len: usize) -> Self {
+        Self {
+            salt,
+            iteration_count,
+            dkm_len,
+        }
+    }
+}
+
+impl<'a> KdfParams for Pbkdf2Params<'a> {
+    fn secret_type(&self) -> TransientObjectType {
+        TransientObjectType::Pbkdf2Password
+    }
+
+    fn secret_attribute_id(&self) -> AttributeId {
+        AttributeId::Pbkdf2Password
+    }
+
+    fn output_len(&self) -> usize {
+        self.dkm_len
+    }
+
+    fn attributes(&self) -> Vec<Attribute> {
+        vec![
+            AttributeMemref::from_ref(AttributeId::Pbkdf2Salt, self.salt).into(),
+            AttributeValue::from_value(AttributeId::Pbkdf2IterationCount, self.iteration_count, 0)
+                .into(),
+            AttributeValue::from_value(AttributeId::Pbkdf2DkmLength, self.dkm_len as u32, 0).into(),
+        ]
+    }
+
+    fn accepts(&self, algo: &AlgorithmId) -> bool {
+        matches!(algo, AlgorithmId::Pbkdf2HmacSha1DeriveKey)
+    }
+}
+
+/// Key derivation with the HKDF, Concat KDF and PBKDF2 algorithms that OP-TEE
+/// provides as extensions, built on top of [DeriveKey](DeriveKey).
+pub struct Kdf;
+
+impl Kdf {
+    /// Derive keying material from `secret` and return it as a
+    /// [GenericSecret](TransientObjectType::GenericSecret) object, so that the
+    /// derived key can be used directly with other operations.
+    ///
+    /// # Parameters
+    ///
+    /// 1) `algo`: One of the `Hkdf*DeriveKey`, `ConcatKdf*DeriveKey` or
+    ///    `Pbkdf2*DeriveKey` algorithms of [AlgorithmId](AlgorithmId).
+    /// 2) `secret`: The input keying material, shared secret `Z` or password,
+    ///    at most 512 bytes.
+    /// 3) `params`: [HkdfParams](HkdfParams), [ConcatKdfParams](ConcatKdfParams)
+    ///    or [Pbkdf2Params](Pbkdf2Params) matching `algo`.
+    ///
+    /// # Errors
+    ///
+    /// 1) `BadParameters`: If `params` does not match `algo`, or if `secret` or
+    ///    the requested output is empty.
+    /// 2) `OutOfMemory`: If not enough resources are available.
+    /// 3) `NotSupported`: If the algorithm or the sizes are not supported.
+    ///
+    /// # Panics
+    ///
+    /// 1) Hardware or cryptographic algorithm failure.
+    /// 2) If the Implementation detects any other error.
+    pub fn derive_object<P: KdfParams>(
+        algo: AlgorithmId,
+        secret: &[u8],
+        params: &P,
+    ) -> Result<TransientObject> {
+        if !params.accepts(&algo) || secret.is_empty() || params.output_len() == 0 {
+            return Err(Error::new(ErrorKind::BadParameters));
+        }
+        let secret_size = secret.len() * 8;
+        let mut secret_object = TransientObject::allocate(params.secret_type(), secret_size)?;
+        secret_object.populate(&[AttributeMemref::from_ref(
+            params.secret_attribute_id(),
+            secret,
+        )
+        .into()])?;
+        let operation = DeriveKey::allocate(algo, secret_size)?;
+        operation.set_key(&secret_object)?;
+        let mut derived =
+            TransientObject::allocate(TransientObjectType::GenericSecret, params.output_len() * 8)?;
+        operation.derive(&params.attributes(), &mut derived);
+        Ok(derived)
+    }
+
+    /// Derive keying material from `secret` into `output`, which must hold
+    /// [output_len](KdfParams::output_len) bytes, and return its length. The
+    /// keying material is not copied anywhere else, so that the caller can
+    /// zeroize `output` once done with it.
+    ///
+    /// Parameters and errors are the same as [derive_object](Kdf::derive_object),
+    /// and `ShortBuffer` if `output` is too small.
+    ///
+    /// # Example
+    ///
+    /// ``` rust,no_run
+    /// # use optee_utee::{AlgorithmId, HkdfParams, Kdf, Pbkdf2Params};
+    /// # fn main() -> optee_utee::Result<()> {
+    /// let ikm = [0x0bu8; 22];
+    /// let params = HkdfParams::new(42).with_salt(&[0u8; 13]).with_info(b"session");
+    /// let mut session_key = [0u8; 42];
+    /// Kdf::derive(AlgorithmId::HkdfSha256DeriveKey, &ikm, &params, &mut session_key)?;
+    ///
+    /// let params = Pbkdf2Params::new(b"salt", 4096, 20);
+    /// let mut storage_key = [0u8; 20];
+    /// Kdf::derive(AlgorithmId::Pbkdf2HmacSha1DeriveKey, b"password", &params, &mut storage_key)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn derive<P: KdfParams>(
+        algo: AlgorithmId,
+        secret: &[u8],
+        params: &P,
+        output: &mut [u8],
+    ) -> Result<usize> {
+        if output.len() < params.output_len() {
+            return Err(Error::new(ErrorKind::ShortBuffer));
+        }
+        let derived = Self::derive_object(algo, secret, params)?;
+        derived.ref_attribute(AttributeId::SecretValue, output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::ptr::NonNull;
+    use optee_utee_mock::{
+        crypto::{set_global_crypto_mock, MockCryptoController},
+        object::{set_global_object_mock, MockObjectController, SERIAL_TEST_LOCK},
+        raw,
+    };
+
+    fn memref(attr: &Attribute) -> (u32, &[u8]) {
+        let raw = attr.raw();
+        let memref = unsafe { raw.content.memref };
+        let data = unsafe { core::slice::from_raw_parts(memref.buffer as *const u8, memref.size) };
+        (raw.attributeID, data)
+    }
+
+    fn value(attr: &Attribute) -> (u32, u32, u32) {
+        let raw = attr.raw();
+        let value = unsafe { raw.content.value };
+        (raw.attributeID, value.a, value.b)
+    }
+
+    #[test]
+    fn test_hkdf_attributes() {
+        let params = HkdfParams::new(42);
+        assert_eq!(params.secret_attribute_id() as u32, raw::TEE_ATTR_HKDF_IKM);
+        let attrs = params.attributes();
+        assert_eq!(attrs.len(), 1);
+        assert_eq!(value(&attrs[0]), (raw::TEE_ATTR_HKDF_OKM_LENGTH, 42, 0));
+
+        let params = HkdfParams::new(42).with_salt(b"salt").with_info(b"info");
+        let attrs = params.attributes();
+        assert_eq!(attrs.len(), 3);
+        assert_eq!(value(&attrs[0]), (raw::TEE_ATTR_HKDF_OKM_LENGTH, 42, 0));
+        assert_eq!(memref(&attrs[1]), (raw::TEE_ATTR_HKDF_SALT, &b"salt"[..]));
+        assert_eq!(memref(&attrs[2]), (raw::TEE_ATTR_HKDF_INFO, &b"info"[..]));
+    }
+
+    #[test]
+    fn test_concat_kdf_attributes() {
+        let params = ConcatKdfParams::new(32).with_other_info(b"other info");
+        assert_eq!(
+            params.secret_attribute_id() as u32,
+            raw::TEE_ATTR_CONCAT_KDF_Z
+        );
+        let attrs = params.attributes();
+        assert_eq!(attrs.len(), 2);
+        assert_eq!(
+            value(&attrs[0]),
+            (raw::TEE_ATTR_CONCAT_KDF_DKM_LENGTH, 32, 0)
+        );
+        assert_eq!(
+            memref(&attrs[1]),
+            (raw::TEE_ATTR_CONCAT_KDF_OTHER_INFO, &b"other info"[..])
+        );
+        assert_eq!(ConcatKdfParams::new(32).attributes().len(), 1);
+    }
+
+    #[test]
+    fn test_pbkdf2_attributes() {
+        let params = Pbkdf2Params::new(b"salt", 4096, 20);
+        assert_eq!(
+            params.secret_attribute_id() as u32,
+            raw::TEE_ATTR_PBKDF2_PASSWORD
+        );
+        let attrs = params.attributes();
+        assert_eq!(attrs.len(), 3);
+        assert_eq!(memref(&attrs[0]), (raw::TEE_ATTR_PBKDF2_SALT, &b"salt"[..]));
+        assert_eq!(
+            value(&attrs[1]),
+            (raw::TEE_ATTR_PBKDF2_ITERATION_COUNT, 4096, 0)
+        );
+        assert_eq!(value(&attrs[2]), (raw::TEE_ATTR_PBKDF2_DKM_LENGTH, 20, 0));
+    }
+
+    #[test]
+    fn test_accepts() {
+        assert!(HkdfParams::new(42).accepts(&AlgorithmId::HkdfSha256DeriveKey));
+        assert!(!HkdfParams::new(42).accepts(&AlgorithmId::Pbkdf2HmacSha1DeriveKey));
+        assert!(ConcatKdfParams::new(32).accepts(&AlgorithmId::ConcatKdfSha256DeriveKey));
+        assert!(Pbkdf2Params::new(b"salt", 1, 20).accepts(&AlgorithmId::Pbkdf2HmacSha1DeriveKey));
+    }
+
+    #[test]
+    fn test_derive() {
+        let _lock = SERIAL_TEST_LOCK.lock();
+        let ikm = [0x0bu8; 22];
+        let mut object_mock = MockObjectController::new();
+        let mut sizes = vec![
+            (raw::TEE_TYPE_HKDF_IKM, 176),
+            (raw::TEE_TYPE_GENERIC_SECRET, 336),
+        ];
+        object_mock
+            .expect_TEE_AllocateTransientObject()
+            .times(2)
+            .returning(move |object_type, size, object| {
+                assert_eq!((object_type, size), sizes.remove(0));
+                unsafe { *object = NonNull::dangling().as_ptr() };
+                raw::TEE_SUCCESS
+            });
+        object_mock
+            .expect_TEE_PopulateTransientObject()
+            .times(1)
+            .returning(move |_, attrs, count| {
+                assert_eq!(count, 1);
+                let attr = unsafe { &*attrs };
+                assert_eq!(attr.attributeID, raw::TEE_ATTR_HKDF_IKM);
+                let memref = unsafe { attr.content.memref };
+                let secret =
+                    unsafe { core::slice::from_raw_parts(memref.buffer as *const u8, memref.size) };
+                assert_eq!(secret, &ikm);
+                raw::TEE_SUCCESS
+            });
+        // the derived key is written to the output, and only there
+        object_mock
+            .expect_TEE_GetObjectBufferAttribute()
+            .withf(|_, id, _, size| *id == raw::TEE_ATTR_SECRET_VALUE && unsafe { **size } == 64)
+            .times(1)
+            .returning(|_, _, buffer, size| {
+                unsafe {
+                    core::slice::from_raw_parts_mut(buffer as *mut u8, 42).fill(0xab);
+                    *size = 42;
+                }
+                raw::TEE_SUCCESS
+            });
+        object_mock
+            .expect_TEE_CloseObject()
+            .times(2)
+            .return_const(());
+        set_global_object_mock(object_mock);
+
+        let mut crypto_mock = MockCryptoController::new();
+        crypto_mock.expect_TEE_AllocateOperation_success(
+            1,
+            raw::TEE_ALG_HKDF_SHA256_DERIVE_KEY,
+            raw::TEE_MODE_DERIVE,
+            176,
+        );
+        crypto_mock
+            .expect_TEE_SetOperationKey()
+            .times(1)
+            .return_const(raw::TEE_SUCCESS);
+        crypto_mock
+            .expect_TEE_DeriveKey()
+            .times(1)
+            .returning(|_, params, count, _| {
+                assert_eq!(count, 2);
+                let okm_length = unsafe { &*params };
+                assert_eq!(okm_length.attributeID, raw::TEE_ATTR_HKDF_OKM_LENGTH);
+                assert_eq!(unsafe { okm_length.content.value.a }, 42);
+            });
+        set_global_crypto_mock(crypto_mock);
+
+        let params = HkdfParams::new(42).with_info(b"info");
+        let mut output = [0u8; 64];
+        let len =
+            Kdf::derive(AlgorithmId::HkdfSha256DeriveKey, &ikm, &params, &mut output).unwrap();
+        assert_eq!(len, 42);
+        assert_eq!(&output[..42], &[0xab; 42]);
+        assert_eq!(&output[42..], &[0; 22]);
+
+        // rejected before anything is allocated
+        let mut output = [0u8; 41];
+        let error = Kdf::derive(AlgorithmId::HkdfSha256DeriveKey, &ikm, &params, &mut output);
+        assert_eq!(error.unwrap_err().kind(), ErrorKind::ShortBuffer);
+        let error = Kdf::derive_object(AlgorithmId::Pbkdf2HmacSha1DeriveKey, &ikm, &params);
+        assert_eq!(error.err().unwrap().kind(), ErrorKind::BadParameters);
+    }
+}
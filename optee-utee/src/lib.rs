@@ -21,6 +21,8 @@
 // Requires `alloc`.
 #[macro_use]
 extern crate alloc;
+#[cfg(all(test, not(target_os = "optee")))]
+extern crate std;
 
 #[cfg(not(target_os = "optee"))]
 use libc_alloc::LibcAlloc;
@@ -29,25 +31,21 @@ use libc_alloc::LibcAlloc;
 #[global_allocator]
 static ALLOCATOR: LibcAlloc = LibcAlloc;
 
-#[cfg(not(target_os = "optee"))]
+#[cfg(all(not(target_os = "optee"), not(feature = "no_panic_handler")))]
 use core::panic::PanicInfo;
-#[cfg(not(target_os = "optee"))]
-use optee_utee_sys as raw;
 
 #[cfg(all(not(target_os = "optee"), not(feature = "no_panic_handler")))]
 #[panic_handler]
-fn panic(_info: &PanicInfo) -> ! {
-    unsafe {
-        raw::TEE_Panic(0);
-    }
-    loop {}
+fn panic(info: &PanicInfo) -> ! {
+    crate::panic::abort(info)
 }
 
 pub use self::arithmetical::*;
 pub use self::crypto_op::*;
-pub use self::error::{Error, ErrorKind, Result};
+pub use self::error::{Error, ErrorKind, Result, APP_ERROR_CODES};
 pub use self::extension::*;
 pub use self::identity::{Identity, LoginType};
+pub use self::kdf::{ConcatKdfParams, HkdfParams, Kdf, KdfParams, Pbkdf2Params};
 pub use self::object::*;
 pub use self::parameter::{ParamType, ParamTypes, Parameter, Parameters};
 pub use self::ta_session::{TaSession, TaSessionBuilder};
@@ -66,11 +64,27 @@ pub mod crypto_op;
 mod error;
 pub mod extension;
 pub mod identity;
+pub mod kdf;
+#[cfg(feature = "log")]
+pub mod logger;
 pub mod net;
 pub mod object;
+#[cfg(not(target_os = "optee"))]
+pub mod panic;
 mod parameter;
 pub mod property;
+#[cfg(feature = "rustcrypto")]
+pub mod rustcrypto;
 mod ta_session;
 mod tee_parameter;
 pub mod time;
+#[cfg(any(target_os = "optee", test))]
+pub mod unwind;
+// Without std a panic aborts the TA, so no session is ever poisoned, but the
+// close-session entry point forgets the poisoning of its session all the same.
+#[cfg(not(any(target_os = "optee", test)))]
+#[doc(hidden)]
+pub mod unwind {
+    pub fn clear(_sess_ctx: *mut core::ffi::c_void) {}
+}
 pub mod uuid;
@@ -0,0 +1,151 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::trace::Trace;
+use alloc::string::String;
+use core::fmt::Write;
+use log::{Level, LevelFilter, Log, Metadata, Record, SetLoggerError};
+
+// Trace levels of OP-TEE, see trace_levels.h.
+const TRACE_ERROR: i32 = 1;
+const TRACE_INFO: i32 = 2;
+const TRACE_DEBUG: i32 = 3;
+const TRACE_FLOW: i32 = 4;
+
+static LOGGER: TraceLogger = TraceLogger;
+
+/// A backend of the [log] facade which prints the records to the trace output, like
+/// [trace_println](crate::trace_println), so the logs of the libraries used by a Trusted
+/// Application are not lost.
+///
+/// The levels of the records map to the trace levels of OP-TEE:
+///
+/// | `log::Level`     | OP-TEE trace level |
+/// |------------------|--------------------|
+/// | `Error`          | `TRACE_ERROR` (1)  |
+/// | `Warn`, `Info`   | `TRACE_INFO` (2)   |
+/// | `Debug`          | `TRACE_DEBUG` (3)  |
+/// | `Trace`          | `TRACE_FLOW` (4)   |
+///
+/// A record is only printed when its trace level is not above the trace level of the TA,
+/// which is set with `TaConfig::trace_level` in `build.rs`, and can be changed at runtime with
+/// [Trace::set_level]. Each record is printed on its own line, prefixed with its level, its
+/// module and its line, e.g. `W/TA: my_ta::storage:42 message`.
+///
+/// # Example
+///
+/// ``` rust,no_run
+/// # use optee_utee::logger::TraceLogger;
+/// TraceLogger::init().expect("the logger should be installed once");
+/// log::info!("logged to the trace output");
+/// ```
+pub struct TraceLogger;
+
+impl TraceLogger {
+    /// Install the logger as the global logger of the [log] facade.
+    ///
+    /// The maximum level of the facade is set to `Trace`, and records are filtered by the trace
+    /// level of the TA when they are logged, so that changes of the trace level apply
+    /// immediately.
+    ///
+    /// # Errors
+    ///
+    /// 1) `SetLoggerError`: If a global logger has already been installed.
+    pub fn init() -> Result<(), SetLoggerError> {
+        log::set_logger(&LOGGER)?;
+        log::set_max_level(LevelFilter::Trace);
+        Ok(())
+    }
+}
+
+impl Log for TraceLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        trace_level(metadata.level()) <= Trace::get_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            Trace::_print(format_args!("{}", format_record(record)));
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+fn trace_level(level: Level) -> i32 {
+    match level {
+        Level::Error => TRACE_ERROR,
+        Level::Warn | Level::Info => TRACE_INFO,
+        Level::Debug => TRACE_DEBUG,
+        Level::Trace => TRACE_FLOW,
+    }
+}
+
+// The record is formatted before printing, so that it is printed with a
+// single call and lines of concurrent sessions are not interleaved.
+fn format_record(record: &Record) -> String {
+    let level = match record.level() {
+        Level::Error => 'E',
+        Level::Warn => 'W',
+        Level::Info => 'I',
+        Level::Debug => 'D',
+        Level::Trace => 'F',
+    };
+    let mut line = String::new();
+    let _ = write!(line, "{}/TA: {}", level, record.target());
+    if let Some(number) = record.line() {
+        let _ = write!(line, ":{}", number);
+    }
+    let _ = writeln!(line, " {}", record.args());
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trace_level() {
+        assert_eq!(trace_level(Level::Error), TRACE_ERROR);
+        assert_eq!(trace_level(Level::Warn), TRACE_INFO);
+        assert_eq!(trace_level(Level::Info), TRACE_INFO);
+        assert_eq!(trace_level(Level::Debug), TRACE_DEBUG);
+        assert_eq!(trace_level(Level::Trace), TRACE_FLOW);
+    }
+
+    #[test]
+    fn test_format_record() {
+        let line = format_record(
+            &Record::builder()
+                .level(Level::Warn)
+                .target("my_ta::storage")
+                .line(Some(42))
+                .args(format_args!("{} left", 3))
+                .build(),
+        );
+        assert_eq!(line, "W/TA: my_ta::storage:42 3 left\n");
+
+        let line = format_record(
+            &Record::builder()
+                .level(Level::Trace)
+                .target("my_ta")
+                .args(format_args!("enter"))
+                .build(),
+        );
+        assert_eq!(line, "F/TA: my_ta enter\n");
+    }
+}
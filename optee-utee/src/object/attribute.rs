@@ -103,6 +103,36 @@ impl<'attrref> AttributeMemref<'attrref> {
         }
         res
     }
+
+    /// Populate a single attribute with a reference to a buffer that the
+    /// implementation writes into, such as
+    /// [Sm2KepConfirmationOut](crate::AttributeId::Sm2KepConfirmationOut).
+    ///
+    /// # Parameters
+    ///
+    /// 1) `id`: The [AttributeId](crate::AttributeId) is an identifier of the
+    ///    attribute to populate.
+    /// 2) `buffer`: Output buffer that receives the content of the attribute.
+    ///
+    /// # Example
+    ///
+    /// ``` rust,no_run
+    /// # use optee_utee::{AttributeMemref, AttributeId};
+    /// let mut conf_out = [0u8; 32];
+    /// let attr = AttributeMemref::from_mut_ref(AttributeId::Sm2KepConfirmationOut, &mut conf_out);
+    /// ```
+    pub fn from_mut_ref(id: AttributeId, buffer: &'attrref mut [u8]) -> Self {
+        let mut res = AttributeMemref::new_ref();
+        unsafe {
+            raw::TEE_InitRefAttribute(
+                &mut res.raw,
+                id as u32,
+                buffer.as_mut_ptr() as _,
+                buffer.len(),
+            );
+        }
+        res
+    }
 }
 
 /// A value attribute.
@@ -149,6 +179,7 @@ impl AttributeValue {
     }
 }
 
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 #[repr(u32)]
 pub enum AttributeId {
     /// Used for all secret keys for symmetric ciphers, MACs, and HMACs
@@ -204,12 +235,53 @@ pub enum AttributeId {
     Ed25519PublicValue = 0xD0000743,
     /// Ed25519 private value
     Ed25519PrivateValue = 0xC0000843,
+    /// EdDSA context, selects Ed25519ctx, or Ed25519ph together with
+    /// [EddsaPrehash](AttributeId::EddsaPrehash). At most 255 bytes
+    EddsaCtx = 0xD0000643,
+    /// EdDSA pre-hash flag, a value attribute: `a` set to 1 selects Ed25519ph
+    EddsaPrehash = 0xF0000543,
     /// X25519 public value
     X25519PublicValue = 0xD0000944,
     /// X25519 private value
     X25519PrivateValue = 0xC0000A44,
     /// ECC Curve algorithm
     EccCurve = 0xF0000441,
+    /// SM2 identifier of the initiator: `IDA`
+    Sm2IdInitiator = 0xD0000446,
+    /// SM2 identifier of the responder: `IDB`
+    Sm2IdResponder = 0xD0000546,
+    /// SM2 key exchange role: 0 for initiator, 1 for responder
+    Sm2KepUser = 0xF0000646,
+    /// SM2 key exchange confirmation value received from the peer
+    Sm2KepConfirmationIn = 0xD0000746,
+    /// SM2 key exchange confirmation value to be sent to the peer
+    Sm2KepConfirmationOut = 0xD0000846,
+    /// ECC ephemeral public value: `x`
+    EccEphemeralPublicValueX = 0xD0000946,
+    /// ECC ephemeral public value: `y`
+    EccEphemeralPublicValueY = 0xD0000A46,
+    /// HKDF input keying material: `IKM`
+    HkdfIkm = 0xC00001C0,
+    /// HKDF salt, optional
+    HkdfSalt = 0xD00002C0,
+    /// HKDF context and application specific information, optional
+    HkdfInfo = 0xD00003C0,
+    /// HKDF output keying material length in bytes
+    HkdfOkmLength = 0xF00004C0,
+    /// Concat KDF shared secret: `Z`
+    ConcatKdfZ = 0xC00001C1,
+    /// Concat KDF other information, optional
+    ConcatKdfOtherInfo = 0xD00002C1,
+    /// Concat KDF derived keying material length in bytes
+    ConcatKdfDkmLength = 0xF00003C1,
+    /// PBKDF2 password
+    Pbkdf2Password = 0xC00001C2,
+    /// PBKDF2 salt
+    Pbkdf2Salt = 0xD00002C2,
+    /// PBKDF2 iteration count
+    Pbkdf2IterationCount = 0xF00003C2,
+    /// PBKDF2 derived keying material length in bytes
+    Pbkdf2DkmLength = 0xF00004C2,
     BitProtected = (1 << 28),
     BitValue = (1 << 29),
 }
@@ -0,0 +1,200 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Stream traits for the data stream of a [PersistentObject].
+//!
+//! With `std` (`target_os = "optee"`), [PersistentObject] implements
+//! `std::io::{Read, Write, Seek}`; with the `embedded_io` feature it
+//! implements the `embedded_io` equivalents, which also work in `no_std`
+//! TAs.
+//!
+//! Both follow the semantics of `TEE_ReadObjectData`: a read at or beyond
+//! the end of the data stream returns `Ok(0)`. A write either stores the
+//! whole buffer or fails, and seeking goes through
+//! [PersistentObject::seek_to], so objects larger than 2 GiB are reachable.
+//!
+//! The inherent `read`, `write` and `seek` methods of [PersistentObject] take
+//! precedence over the trait methods, so call the traits explicitly, e.g.
+//! `std::io::Read::read(&mut object, &mut buf)`, or through generic code.
+
+use core::convert::TryFrom;
+
+use super::{PersistentObject, Whence};
+use crate::{Error, ErrorKind};
+
+#[cfg(target_os = "optee")]
+mod std_io {
+    use std::io;
+
+    use super::*;
+
+    fn io_error(err: Error) -> io::Error {
+        let kind = match err.kind() {
+            ErrorKind::AccessDenied | ErrorKind::AccessConflict => io::ErrorKind::PermissionDenied,
+            ErrorKind::ItemNotFound => io::ErrorKind::NotFound,
+            ErrorKind::BadParameters | ErrorKind::Overflow => io::ErrorKind::InvalidInput,
+            ErrorKind::CorruptObject | ErrorKind::BadFormat => io::ErrorKind::InvalidData,
+            ErrorKind::NotSupported | ErrorKind::NotImplemented => io::ErrorKind::Unsupported,
+            ErrorKind::OutOfMemory => io::ErrorKind::OutOfMemory,
+            _ => io::ErrorKind::Other,
+        };
+        io::Error::new(kind, err)
+    }
+
+    impl io::Read for PersistentObject {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            PersistentObject::read(self, buf)
+                .map(|count| count as usize)
+                .map_err(io_error)
+        }
+    }
+
+    impl io::Write for PersistentObject {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            PersistentObject::write(self, buf)
+                .map(|_| buf.len())
+                .map_err(io_error)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl io::Seek for PersistentObject {
+        fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
+            let (offset, whence) = match pos {
+                io::SeekFrom::Start(offset) => (
+                    i64::try_from(offset).map_err(|_| io_error(Error::new(ErrorKind::Overflow)))?,
+                    Whence::DataSeekSet,
+                ),
+                io::SeekFrom::Current(offset) => (offset, Whence::DataSeekCur),
+                io::SeekFrom::End(offset) => (offset, Whence::DataSeekEnd),
+            };
+            self.seek_to(offset, whence).map_err(io_error)
+        }
+    }
+}
+
+#[cfg(feature = "embedded_io")]
+mod embedded {
+    use super::*;
+
+    impl embedded_io::Error for Error {
+        fn kind(&self) -> embedded_io::ErrorKind {
+            match Error::kind(self) {
+                ErrorKind::AccessDenied | ErrorKind::AccessConflict => {
+                    embedded_io::ErrorKind::PermissionDenied
+                }
+                ErrorKind::ItemNotFound => embedded_io::ErrorKind::NotFound,
+                ErrorKind::BadParameters | ErrorKind::Overflow => {
+                    embedded_io::ErrorKind::InvalidInput
+                }
+                ErrorKind::CorruptObject | ErrorKind::BadFormat => {
+                    embedded_io::ErrorKind::InvalidData
+                }
+                ErrorKind::NotSupported | ErrorKind::NotImplemented => {
+                    embedded_io::ErrorKind::Unsupported
+                }
+                ErrorKind::OutOfMemory => embedded_io::ErrorKind::OutOfMemory,
+                _ => embedded_io::ErrorKind::Other,
+            }
+        }
+    }
+
+    impl embedded_io::ErrorType for PersistentObject {
+        type Error = Error;
+    }
+
+    impl embedded_io::Read for PersistentObject {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+            PersistentObject::read(self, buf).map(|count| count as usize)
+        }
+    }
+
+    impl embedded_io::Write for PersistentObject {
+        fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+            PersistentObject::write(self, buf).map(|_| buf.len())
+        }
+
+        fn flush(&mut self) -> Result<(), Error> {
+            Ok(())
+        }
+    }
+
+    impl embedded_io::Seek for PersistentObject {
+        fn seek(&mut self, pos: embedded_io::SeekFrom) -> Result<u64, Error> {
+            let (offset, whence) = match pos {
+                embedded_io::SeekFrom::Start(offset) => (
+                    i64::try_from(offset).map_err(|_| Error::new(ErrorKind::Overflow))?,
+                    Whence::DataSeekSet,
+                ),
+                embedded_io::SeekFrom::Current(offset) => (offset, Whence::DataSeekCur),
+                embedded_io::SeekFrom::End(offset) => (offset, Whence::DataSeekEnd),
+            };
+            self.seek_to(offset, whence)
+        }
+    }
+}
+
+#[cfg(all(test, feature = "embedded_io"))]
+mod tests {
+    use embedded_io::{Read, Seek, SeekFrom};
+    use optee_utee_mock::{
+        object::{set_global_object_mock, MockObjectController, SERIAL_TEST_LOCK},
+        raw,
+    };
+
+    use super::*;
+    use crate::{DataFlag, ObjectStorageConstants};
+
+    #[test]
+    // A read at the end of the data stream returns `Ok(0)`, and a start
+    // offset that does not fit the TEE API is reported as invalid input.
+    fn test_embedded_io_eof_and_seek_overflow() {
+        let _lock = SERIAL_TEST_LOCK.lock();
+
+        let mut mock = MockObjectController::new();
+        let mut handle_struct = MockObjectController::new_valid_test_handle_struct();
+        let handle = MockObjectController::new_valid_test_handle(&mut handle_struct);
+
+        mock.expect_TEE_OpenPersistentObject_success_once(handle.clone());
+        mock.expect_TEE_ReadObjectData()
+            .return_once_st(|_, _, _, count| {
+                unsafe {
+                    *count = 0;
+                }
+                raw::TEE_SUCCESS
+            });
+        mock.expect_TEE_CloseObject_once(handle);
+
+        set_global_object_mock(mock);
+
+        let mut obj =
+            PersistentObject::open(ObjectStorageConstants::Private, &[], DataFlag::ACCESS_READ)
+                .expect("it should be ok");
+
+        let mut buf = [0u8; 16];
+        assert_eq!(Read::read(&mut obj, &mut buf).expect("it should be ok"), 0);
+
+        let err = Seek::seek(&mut obj, SeekFrom::Start(u64::MAX)).expect_err("it should be err");
+        assert_eq!(
+            embedded_io::Error::kind(&err),
+            embedded_io::ErrorKind::InvalidInput
+        );
+    }
+}
@@ -15,12 +15,12 @@
 // specific language governing permissions and limitations
 // under the License.
 
-use alloc::boxed::Box;
+use alloc::{boxed::Box, vec::Vec};
 
 use optee_utee_sys as raw;
 
 use super::ObjectInfo;
-use crate::{Error, Result};
+use crate::{Error, ErrorKind, Result};
 
 // TODO: The examples and detailed function explanation will be added after we
 // test this struct and its functions.
@@ -103,3 +103,156 @@ impl Drop for ObjectEnumHandle {
         }
     }
 }
+
+/// An iterator over the [PersistentObject](crate::PersistentObject)s of a
+/// Trusted Storage, returned by
+/// [PersistentObject::list](crate::PersistentObject::list).
+///
+/// Each item is the object identifier together with its
+/// [ObjectInfo](crate::ObjectInfo). Objects are fetched one at a time from
+/// the enumerator as the iterator advances, and the iteration ends once the
+/// enumeration reaches the last object or fails.
+pub struct PersistentObjectList {
+    handle: ObjectEnumHandle,
+    prefix: Vec<u8>,
+    done: bool,
+}
+
+impl PersistentObjectList {
+    pub(crate) fn start(storage_id: u32) -> Result<Self> {
+        let mut handle = ObjectEnumHandle::allocate()?;
+        // An empty storage is reported as `ItemNotFound` by the enumerator.
+        let done = match handle.start(storage_id) {
+            Ok(()) => false,
+            Err(e) if e.kind() == ErrorKind::ItemNotFound => true,
+            Err(e) => return Err(e),
+        };
+        Ok(Self {
+            handle,
+            prefix: Vec::new(),
+            done,
+        })
+    }
+
+    /// Only yield the objects whose identifier starts with `prefix`.
+    ///
+    /// # Example
+    ///
+    /// ``` rust,no_run
+    /// # use optee_utee::{PersistentObject, ObjectStorageConstants};
+    /// # fn main() -> optee_utee::Result<()> {
+    /// for object in PersistentObject::list(ObjectStorageConstants::Private)?.with_prefix(b"session/") {
+    ///     let (id, info) = object?;
+    ///     // ...
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_prefix(mut self, prefix: &[u8]) -> Self {
+        self.prefix = prefix.to_vec();
+        self
+    }
+}
+
+impl Iterator for PersistentObjectList {
+    type Item = Result<(Vec<u8>, ObjectInfo)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.done {
+            let mut info = ObjectInfo::from_raw(unsafe { core::mem::zeroed() });
+            let mut id = [0u8; raw::TEE_OBJECT_ID_MAX_LEN as usize];
+            match self.handle.get_next::<()>(&mut info, &mut id) {
+                Ok(len) => {
+                    let id = &id[..len as usize];
+                    if id.starts_with(&self.prefix) {
+                        return Some(Ok((id.to_vec(), info)));
+                    }
+                }
+                Err(e) => {
+                    self.done = true;
+                    if e.kind() != ErrorKind::ItemNotFound {
+                        return Some(Err(e));
+                    }
+                }
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+    use core::sync::atomic::{AtomicUsize, Ordering};
+
+    use optee_utee_mock::{
+        object::{set_global_object_mock, MockObjectController, SERIAL_TEST_LOCK},
+        raw,
+    };
+
+    use super::*;
+
+    #[test]
+    // Objects are fetched until the enumerator reports `ItemNotFound`, and
+    // those outside the prefix are skipped.
+    fn test_list_with_prefix() {
+        let _lock = SERIAL_TEST_LOCK.lock();
+
+        let mut mock = MockObjectController::new();
+        mock.expect_TEE_AllocatePersistentObjectEnumerator()
+            .return_once_st(|_| raw::TEE_SUCCESS);
+        mock.expect_TEE_StartPersistentObjectEnumerator()
+            .withf(|_, storage_id| *storage_id == raw::TEE_STORAGE_PRIVATE)
+            .return_once_st(|_, _| raw::TEE_SUCCESS);
+        let ids: Vec<&'static [u8]> = vec![b"a/1", b"b/1", b"a/2"];
+        let next = AtomicUsize::new(0);
+        mock.expect_TEE_GetNextPersistentObject()
+            .times(4)
+            .returning(move |_, info, id, len| {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                match ids.get(index) {
+                    Some(value) => unsafe {
+                        (*info).dataSize = index;
+                        core::ptr::copy_nonoverlapping(value.as_ptr(), id as *mut u8, value.len());
+                        *len = value.len();
+                        raw::TEE_SUCCESS
+                    },
+                    None => raw::TEE_ERROR_ITEM_NOT_FOUND,
+                }
+            });
+        mock.expect_TEE_FreePersistentObjectEnumerator()
+            .return_once_st(|_| ());
+
+        set_global_object_mock(mock);
+
+        let objects = PersistentObjectList::start(raw::TEE_STORAGE_PRIVATE)
+            .expect("it should be ok")
+            .with_prefix(b"a/")
+            .map(|object| object.map(|(id, info)| (id, info.data_size())))
+            .collect::<Result<Vec<_>>>()
+            .expect("it should be ok");
+
+        assert_eq!(objects, vec![(b"a/1".to_vec(), 0), (b"a/2".to_vec(), 2)]);
+    }
+
+    #[test]
+    // An empty storage is not an error, it yields no objects.
+    fn test_list_empty_storage() {
+        let _lock = SERIAL_TEST_LOCK.lock();
+
+        let mut mock = MockObjectController::new();
+        mock.expect_TEE_AllocatePersistentObjectEnumerator()
+            .return_once_st(|_| raw::TEE_SUCCESS);
+        mock.expect_TEE_StartPersistentObjectEnumerator()
+            .return_once_st(|_, _| raw::TEE_ERROR_ITEM_NOT_FOUND);
+        mock.expect_TEE_GetNextPersistentObject().never();
+        mock.expect_TEE_FreePersistentObjectEnumerator()
+            .return_once_st(|_| ());
+
+        set_global_object_mock(mock);
+
+        let mut objects =
+            PersistentObjectList::start(raw::TEE_STORAGE_PRIVATE).expect("it should be ok");
+        assert!(objects.next().is_none());
+    }
+}
@@ -15,8 +15,12 @@
 // specific language governing permissions and limitations
 // under the License.
 
+#[cfg(feature = "key_encoding")]
+use alloc::vec::Vec;
 use core::mem;
 
+#[cfg(feature = "key_encoding")]
+use super::KeyEncoding;
 use super::{AttributeId, ObjectInfo, UsageFlag};
 use crate::{Error, Result};
 
@@ -171,4 +175,54 @@ pub trait GenericObject {
             code => Err(Error::from_raw_error(code)),
         }
     }
+
+    /// Export the public key of an RSA, ECDSA, ECDH, Ed25519 or X25519 object
+    /// in `encoding`, one of the `Pkcs1`, `Spki` or `Jwk`
+    /// [KeyEncoding](crate::KeyEncoding).
+    ///
+    /// # Example
+    ///
+    /// ``` rust,no_run
+    /// # use optee_utee::{GenericObject, KeyEncoding, TransientObject, TransientObjectType};
+    /// # fn main() -> optee_utee::Result<()> {
+    /// let key = TransientObject::allocate(TransientObjectType::EcdsaKeypair, 256)?;
+    /// // ... generate or import the key
+    /// let pem = key.export_public_key(KeyEncoding::SpkiPem)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * `BadParameters`: If `encoding` can not hold a public key, or can not
+    ///   hold a key of this type.
+    /// * `NotSupported`: If the object type or its curve is not supported.
+    /// * Any error returned by [info](Self::info) or
+    ///   [ref_attribute](Self::ref_attribute).
+    #[cfg(feature = "key_encoding")]
+    fn export_public_key(&self, encoding: KeyEncoding) -> Result<Vec<u8>> {
+        super::key_encoding::export_key(self, encoding, false)
+    }
+
+    /// Export the private key of an RSA, ECDSA, ECDH, Ed25519 or X25519
+    /// keypair in `encoding`, one of the `Pkcs1`, `Pkcs8`, `Sec1` or `Jwk`
+    /// [KeyEncoding](crate::KeyEncoding).
+    ///
+    /// The object usage SHALL contain
+    /// [UsageFlag::EXTRACTABLE](crate::UsageFlag::EXTRACTABLE).
+    ///
+    /// # Errors
+    ///
+    /// * `AccessDenied`: If the object is not extractable.
+    /// * `BadParameters`: If the object is not a keypair, if `encoding` can
+    ///   not hold a private key, or can not hold a key of this type.
+    /// * `NotSupported`: If the object type or its curve is not supported, or
+    ///   if an RSA keypair without CRT components is exported as PKCS#1 or
+    ///   PKCS#8.
+    /// * Any error returned by [info](Self::info) or
+    ///   [ref_attribute](Self::ref_attribute).
+    #[cfg(feature = "key_encoding")]
+    fn export_private_key(&self, encoding: KeyEncoding) -> Result<Vec<u8>> {
+        super::key_encoding::export_key(self, encoding, true)
+    }
 }
@@ -16,8 +16,12 @@
 // under the License.
 
 mod attribute;
+#[cfg(any(target_os = "optee", feature = "embedded_io"))]
+mod data_stream;
 mod enum_handle;
 mod generic_object;
+#[cfg(feature = "key_encoding")]
+mod key_encoding;
 mod object_define;
 mod object_handle;
 mod object_info;
@@ -25,8 +29,10 @@ mod persistent_object;
 mod transient_object;
 
 pub use attribute::*;
-pub use enum_handle::ObjectEnumHandle;
+pub use enum_handle::{ObjectEnumHandle, PersistentObjectList};
 pub use generic_object::GenericObject;
+#[cfg(feature = "key_encoding")]
+pub use key_encoding::KeyEncoding;
 pub use object_define::*;
 pub use object_handle::ObjectHandle;
 pub use object_info::ObjectInfo;
@@ -39,9 +39,19 @@ impl Into<raw::TEE_Whence> for Whence {
     }
 }
 
+/// The storages of the persistent objects.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(u32)]
 pub enum ObjectStorageConstants {
+    /// The default private storage of the TA, one of the storages below as
+    /// configured when OP-TEE is built.
     Private = 0x00000001,
+    /// The private storage in the REE file system, encrypted and
+    /// authenticated by OP-TEE (OP-TEE extension).
+    PrivateReeFs = 0x80000000,
+    /// The private storage in the RPMB partition of the eMMC, which is also
+    /// protected against rollback (OP-TEE extension).
+    PrivateRpmb = 0x80000100,
     IllegalValue = 0x7FFFFFFF,
 }
 
@@ -122,7 +132,7 @@ bitflags! {
         /// `TEE_SetOperationKey2`.
         /// This happens only if algorithm is set to
         /// [AesXts](crate::AlgorithmId::AesXts)
-        /// or `TEE_ALG_SM2_KEP`(not supported now).
+        /// or [Sm2Kep](crate::AlgorithmId::Sm2Kep).
         const EXPECT_TWO_KEYS = 0x00080000;
     }
 }
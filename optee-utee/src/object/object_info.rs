@@ -17,6 +17,8 @@
 
 use optee_utee_sys as raw;
 
+use super::UsageFlag;
+
 /// Represent the characteristics of an object.
 /// This info can be returned by [GenericObject](crate::GenericObject) function
 /// [info](crate::GenericObject::info)
@@ -66,6 +68,11 @@ impl ObjectInfo {
         self.raw.dataSize as usize
     }
 
+    /// Return the `dataPosition` field of the raw structure `TEE_ObjectInfo`.
+    pub fn data_position(&self) -> usize {
+        self.raw.dataPosition
+    }
+
     /// Return the `objectSize` field of the raw structure `TEE_ObjectInfo`.
     pub fn object_size(&self) -> usize {
         self.raw.objectSize as usize
@@ -75,4 +82,9 @@ impl ObjectInfo {
     pub fn object_type(&self) -> u32 {
         self.raw.objectType
     }
+
+    /// Return the `objectUsage` field of the raw structure `TEE_ObjectInfo`.
+    pub fn object_usage(&self) -> UsageFlag {
+        UsageFlag::from_bits_truncate(self.raw.objectUsage)
+    }
 }
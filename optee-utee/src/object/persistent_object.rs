@@ -17,8 +17,10 @@
 
 use optee_utee_sys as raw;
 
-use super::{DataFlag, GenericObject, ObjectHandle, ObjectStorageConstants, Whence};
-use crate::{Error, Result};
+use super::{
+    DataFlag, GenericObject, ObjectHandle, ObjectStorageConstants, PersistentObjectList, Whence,
+};
+use crate::{Error, ErrorKind, Result};
 
 /// An object identified by an Object Identifier and including a Data Stream.
 ///
@@ -324,6 +326,47 @@ impl PersistentObject {
         }
     }
 
+    /// List the persistent objects of a Trusted Storage.
+    ///
+    /// The returned [PersistentObjectList](crate::PersistentObjectList) yields
+    /// the identifier and [ObjectInfo](crate::ObjectInfo) of each object,
+    /// fetching them lazily from a persistent object enumerator. Use
+    /// [with_prefix](crate::PersistentObjectList::with_prefix) to only visit
+    /// the objects whose identifier starts with a given prefix.
+    ///
+    /// # Parameters
+    ///
+    /// 1) `storage_id`: The storage to enumerate which is defined in
+    ///    [ObjectStorageConstants](crate::ObjectStorageConstants).
+    ///
+    /// # Example
+    ///
+    /// ``` rust,no_run
+    /// # use optee_utee::{PersistentObject, ObjectStorageConstants};
+    /// # fn main() -> optee_utee::Result<()> {
+    /// for object in PersistentObject::list(ObjectStorageConstants::Private)? {
+    ///     let (id, info) = object?;
+    ///     // ...
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// 1) `OutOfMemory`: If there is not enough memory to allocate the
+    ///    enumerator.
+    /// 2) `CorruptObject`: If the storage or an object in it is corrupt.
+    /// 3) `StorageNotAvailable`: If the persistent object is stored in a
+    ///    storage area which is currently inaccessible.
+    ///
+    /// An empty storage yields an empty iterator. Errors raised while
+    /// advancing the enumeration are returned as the last item of the
+    /// iterator.
+    pub fn list(storage_id: ObjectStorageConstants) -> Result<PersistentObjectList> {
+        PersistentObjectList::start(storage_id as u32)
+    }
+
     /// Read requested size from the data stream associate with the object into
     /// the buffer.
     ///
@@ -524,6 +567,77 @@ impl PersistentObject {
             code => Err(Error::from_raw_error(code)),
         }
     }
+
+    /// Set the data position indicator associate with the object using a
+    /// 64-bit offset, and return the new position.
+    ///
+    /// Unlike [seek](PersistentObject::seek), the offset is not limited to
+    /// `i32`, so every position up to
+    /// [MiscellaneousConstants::TeeDataMaxPosition](crate::MiscellaneousConstants::TeeDataMaxPosition)
+    /// can be reached, including those of objects larger than 2 GiB. The
+    /// target position is computed before calling `TEE_SeekObjectData`, so a
+    /// position before the start of the data stream is rejected instead of
+    /// being clamped to zero.
+    ///
+    /// # Parameters
+    /// 1) `offset`: The bytes shifted based on `whence`.
+    /// 2) `whence`: Defined in [Whence](crate::Whence).
+    ///
+    /// # Example
+    ///
+    /// ``` rust,no_run
+    /// # use optee_utee::{PersistentObject, ObjectStorageConstants, DataFlag, Whence};
+    /// # fn main() -> optee_utee::Result<()> {
+    /// let obj_id = [1u8;1];
+    /// let object = PersistentObject::open(
+    ///     ObjectStorageConstants::Private,
+    ///     &obj_id,
+    ///     DataFlag::ACCESS_READ)?;
+    /// let size = object.seek_to(0, Whence::DataSeekEnd)?;
+    /// object.seek_to(-(size as i64) / 2, Whence::DataSeekCur)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// 1) `BadParameters`: If the resulting position is negative.
+    /// 2) `Overflow`: If the resulting position is greater than
+    ///    [MiscellaneousConstants::TeeDataMaxPosition](crate::MiscellaneousConstants::TeeDataMaxPosition).
+    /// 3) `CorruptObject`: If the object is corrupt. The object handle SHALL
+    ///    behave based on the `gpd.ta.doesNotCloseHandleOnCorruptObject`
+    ///    property.
+    /// 4) `StorageNotAvailable`: If the object is stored in a storage area
+    ///    which is currently inaccessible.
+    ///
+    /// # Panics
+    ///
+    /// 1) If object is not a valid handle on a persistent object.
+    /// 2) If the Implementation detects any other error associated with this
+    ///    function which is not explicitly associated with a defined return
+    ///    code for this function.
+    pub fn seek_to(&self, offset: i64, whence: Whence) -> Result<u64> {
+        let base = match whence {
+            Whence::DataSeekSet => 0,
+            Whence::DataSeekCur => self.info()?.data_position(),
+            Whence::DataSeekEnd => self.info()?.data_size(),
+        };
+        let position = (base as i64)
+            .checked_add(offset)
+            .ok_or(Error::new(ErrorKind::Overflow))?;
+        if position < 0 {
+            return Err(Error::new(ErrorKind::BadParameters));
+        }
+        if position > raw::TEE_DATA_MAX_POSITION as i64 {
+            return Err(Error::new(ErrorKind::Overflow));
+        }
+        match unsafe {
+            raw::TEE_SeekObjectData(self.handle(), position, raw::TEE_Whence::TEE_DATA_SEEK_SET)
+        } {
+            raw::TEE_SUCCESS => Ok(position as u64),
+            code => Err(Error::from_raw_error(code)),
+        }
+    }
 }
 
 impl GenericObject for PersistentObject {
@@ -642,4 +756,71 @@ mod tests {
 
         obj.close_and_delete().expect_err("it should be err");
     }
+
+    #[test]
+    // A position before the start of the data stream is rejected without
+    // calling `TEE_SeekObjectData`, which would clamp it to zero.
+    fn test_seek_to_before_start() {
+        let _lock = SERIAL_TEST_LOCK.lock();
+
+        let mut mock = MockObjectController::new();
+        let mut handle_struct = MockObjectController::new_valid_test_handle_struct();
+        let handle = MockObjectController::new_valid_test_handle(&mut handle_struct);
+
+        let mut info: raw::TEE_ObjectInfo = unsafe { core::mem::zeroed() };
+        info.dataSize = 8;
+        info.dataPosition = 4;
+        mock.expect_TEE_OpenPersistentObject_success_once(handle.clone());
+        mock.expect_TEE_GetObjectInfo1_success_once(info);
+        mock.expect_TEE_CloseObject_once(handle);
+
+        set_global_object_mock(mock);
+
+        let obj =
+            PersistentObject::open(ObjectStorageConstants::Private, &[], DataFlag::ACCESS_READ)
+                .expect("it should be ok");
+
+        let err = obj
+            .seek_to(-5, Whence::DataSeekCur)
+            .expect_err("it should be err");
+        assert_eq!(err.kind(), ErrorKind::BadParameters);
+    }
+
+    #[test]
+    // Positions above `i32::MAX` are reachable up to `TEE_DATA_MAX_POSITION`.
+    fn test_seek_to_large_position() {
+        let _lock = SERIAL_TEST_LOCK.lock();
+
+        let mut mock = MockObjectController::new();
+        let mut handle_struct = MockObjectController::new_valid_test_handle_struct();
+        let handle = MockObjectController::new_valid_test_handle(&mut handle_struct);
+
+        let mut info: raw::TEE_ObjectInfo = unsafe { core::mem::zeroed() };
+        info.dataSize = raw::TEE_DATA_MAX_POSITION as usize;
+        mock.expect_TEE_OpenPersistentObject_success_once(handle.clone());
+        mock.expect_TEE_SeekObjectData()
+            .withf(|_, offset, whence| {
+                *offset == raw::TEE_DATA_MAX_POSITION as i64
+                    && matches!(whence, raw::TEE_Whence::TEE_DATA_SEEK_SET)
+            })
+            .return_once_st(|_, _, _| raw::TEE_SUCCESS);
+        mock.expect_TEE_GetObjectInfo1_success_once(info);
+        mock.expect_TEE_CloseObject_once(handle);
+
+        set_global_object_mock(mock);
+
+        let obj =
+            PersistentObject::open(ObjectStorageConstants::Private, &[], DataFlag::ACCESS_READ)
+                .expect("it should be ok");
+
+        let position = obj
+            .seek_to(raw::TEE_DATA_MAX_POSITION as i64, Whence::DataSeekSet)
+            .expect("it should be ok");
+        assert_eq!(position, raw::TEE_DATA_MAX_POSITION as u64);
+
+        let err = obj
+            .seek_to(1, Whence::DataSeekEnd)
+            .expect_err("it should be err");
+        assert_eq!(err.kind(), ErrorKind::Overflow);
+    }
 }
@@ -19,11 +19,14 @@ use alloc::vec::Vec;
 
 use optee_utee_sys as raw;
 
+#[cfg(feature = "key_encoding")]
+use super::KeyEncoding;
 use super::{Attribute, GenericObject, ObjectHandle};
 use crate::{Error, Result};
 
 /// Define types of [TransientObject](crate::TransientObject) with
 /// predefined maximum sizes.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 #[repr(u32)]
 pub enum TransientObjectType {
     /// 128, 192, or 256 bits
@@ -34,6 +37,8 @@ pub enum TransientObjectType {
     /// 128 or 192 bits including the parity bits. This gives effective key
     /// sizes of 112 or 168 bits
     Des3 = 0xA0000013,
+    /// 128 bits
+    Sm4 = 0xA0000014,
     /// Between 64 and 512 bits, multiple of 8 bits
     HmacMd5 = 0xA0000001,
     /// Between 80 and 512 bits, multiple of 8 bits
@@ -46,6 +51,8 @@ pub enum TransientObjectType {
     HmacSha384 = 0xA0000005,
     /// Between 256 and 1024 bits, multiple of 8 bits
     HmacSha512 = 0xA0000006,
+    /// Between 80 and 1024 bits, multiple of 8 bits
+    HmacSm3 = 0xA0000007,
     /// The number of bits in the modulus. 256, 512, 768, 1024, 1536 and
     /// 2048-bit keys SHALL be supported.
     /// Support for other key sizes including bigger key sizes is
@@ -93,6 +100,33 @@ pub enum TransientObjectType {
     /// 256 bits. Conditional: Available only if TEE_ECC_CURVE_25519
     /// defined in Table 6-14 is supported.
     X25519Keypair = 0xA1000044,
+    /// 256 bits. Conditional: Available only if TEE_ECC_CURVE_SM2 defined in
+    /// Table 6-14 is supported.
+    Sm2DsaPublicKey = 0xA0000045,
+    /// 256 bits. Conditional: Available only if TEE_ECC_CURVE_SM2 defined in
+    /// Table 6-14 is supported.
+    Sm2DsaKeypair = 0xA1000045,
+    /// 256 bits. Conditional: Available only if TEE_ECC_CURVE_SM2 defined in
+    /// Table 6-14 is supported.
+    Sm2KepPublicKey = 0xA0000046,
+    /// 256 bits. Conditional: Available only if TEE_ECC_CURVE_SM2 defined in
+    /// Table 6-14 is supported.
+    Sm2KepKeypair = 0xA1000046,
+    /// 256 bits. Conditional: Available only if TEE_ECC_CURVE_SM2 defined in
+    /// Table 6-14 is supported.
+    Sm2PkePublicKey = 0xA0000047,
+    /// 256 bits. Conditional: Available only if TEE_ECC_CURVE_SM2 defined in
+    /// Table 6-14 is supported.
+    Sm2PkeKeypair = 0xA1000047,
+    /// Between 8 and 4096 bits, multiple of 8 bits. Input keying material of
+    /// the HKDF extension algorithms.
+    HkdfIkm = 0xA10000C0,
+    /// Between 8 and 4096 bits, multiple of 8 bits. Shared secret `Z` of the
+    /// Concat KDF extension algorithms.
+    ConcatKdfZ = 0xA10000C1,
+    /// Between 8 and 4096 bits, multiple of 8 bits. Password of the PBKDF2
+    /// extension algorithm.
+    Pbkdf2Password = 0xA10000C2,
     /// Multiple of 8 bits, up to 4096 bits. This type is intended for secret
     /// data that has been derived from a key derivation scheme.
     GenericSecret = 0xA0000000,
@@ -372,6 +406,55 @@ impl TransientObject {
             }
         }
     }
+
+    /// Allocate an object of `object_type` and populate it with a key decoded
+    /// from `data`.
+    ///
+    /// The key size is taken from the key itself. A keypair type requires
+    /// private key material, a public key type accepts both public and private
+    /// key material and only keeps the public part. See
+    /// [KeyEncoding](crate::KeyEncoding) for the supported combinations.
+    ///
+    /// # Parameters
+    ///
+    /// 1) `object_type`: One of the RSA, ECDSA, ECDH, Ed25519 or X25519
+    ///    [TransientObjectType](crate::TransientObjectType).
+    /// 2) `encoding`: The [KeyEncoding](crate::KeyEncoding) of `data`.
+    /// 3) `data`: The encoded key.
+    ///
+    /// # Example
+    ///
+    /// ``` rust,no_run
+    /// # use optee_utee::{KeyEncoding, TransientObject, TransientObjectType};
+    /// # fn main() -> optee_utee::Result<()> {
+    /// let pem = b"-----BEGIN PUBLIC KEY-----\n...\n-----END PUBLIC KEY-----\n";
+    /// let key = TransientObject::import_key(
+    ///     TransientObjectType::RsaPublicKey,
+    ///     KeyEncoding::SpkiPem,
+    ///     pem,
+    /// )?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// 1) `BadFormat`: If `data` is not a valid key in `encoding`.
+    /// 2) `BadParameters`: If the key does not match `object_type`, or if
+    ///    `object_type` is a keypair and `data` holds a public key only.
+    /// 3) `NotSupported`: If `object_type`, the algorithm or the curve of the
+    ///    key is not supported, or if the public key of an ECC, Ed25519 or
+    ///    X25519 keypair is not included in `data`.
+    /// 4) Any error returned by [allocate](Self::allocate) or
+    ///    [populate](Self::populate).
+    #[cfg(feature = "key_encoding")]
+    pub fn import_key(
+        object_type: TransientObjectType,
+        encoding: KeyEncoding,
+        data: &[u8],
+    ) -> Result<Self> {
+        super::key_encoding::import_key(object_type, encoding, data)
+    }
 }
 
 impl GenericObject for TransientObject {
@@ -0,0 +1,125 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Panic handling of no_std Trusted Applications.
+//!
+//! Unless the `no_panic_handler` feature is enabled, optee-utee provides the panic handler of
+//! the TA, which:
+//!
+//! 1) prints the panic message and its location to the trace output,
+//! 2) calls the hook set with [set_hook], e.g. to zeroize the secrets of the TA,
+//! 3) and panics the TA with `TEE_Panic`, passing the code set with [set_code].
+
+use crate::trace::Trace;
+use core::panic::PanicInfo;
+use core::ptr;
+use core::sync::atomic::{AtomicBool, AtomicPtr, AtomicU32, Ordering};
+use optee_utee_sys as raw;
+
+static CODE: AtomicU32 = AtomicU32::new(0);
+static HOOK: AtomicPtr<()> = AtomicPtr::new(ptr::null_mut());
+static PANICKING: AtomicBool = AtomicBool::new(false);
+
+/// Set the panic code passed to `TEE_Panic` when the TA panics, 0 by default.
+///
+/// # Example
+///
+/// ``` rust,no_run
+/// # use optee_utee::panic;
+/// panic::set_code(0xDEAD);
+/// ```
+pub fn set_code(code: u32) {
+    CODE.store(code, Ordering::SeqCst);
+}
+
+/// Returns the panic code passed to `TEE_Panic` when the TA panics.
+pub fn code() -> u32 {
+    CODE.load(Ordering::SeqCst)
+}
+
+/// Set the hook called when the TA panics, after the panic has been printed and before the
+/// TA is panicked, replacing the previous hook.
+///
+/// The hook should not allocate, since the panic may come from an allocation failure. If the
+/// hook panics, the TA is panicked without calling it again.
+///
+/// # Example
+///
+/// ``` rust,no_run
+/// # use optee_utee::panic;
+/// # use core::panic::PanicInfo;
+/// fn zeroize_secrets(_info: &PanicInfo) {
+///     // Zeroize the keys kept in memory.
+/// }
+///
+/// panic::set_hook(zeroize_secrets);
+/// ```
+pub fn set_hook(hook: fn(&PanicInfo)) {
+    HOOK.store(hook as *mut (), Ordering::SeqCst);
+}
+
+/// Remove the hook called when the TA panics, and return it.
+pub fn take_hook() -> Option<fn(&PanicInfo)> {
+    let hook = HOOK.swap(ptr::null_mut(), Ordering::SeqCst);
+    if hook.is_null() {
+        None
+    } else {
+        // Only function pointers of this type are stored in HOOK.
+        Some(unsafe { core::mem::transmute::<*mut (), fn(&PanicInfo)>(hook) })
+    }
+}
+
+/// Handle a panic as the panic handler of optee-utee does, see the [module](self)
+/// documentation. TAs which enable the `no_panic_handler` feature can call it from their own
+/// panic handler.
+pub fn abort(info: &PanicInfo) -> ! {
+    if !PANICKING.swap(true, Ordering::SeqCst) {
+        Trace::_print(format_args!("TA {}\n", info));
+        if let Some(hook) = take_hook() {
+            hook(info);
+        }
+    }
+    unsafe {
+        raw::TEE_Panic(code());
+    }
+    // TEE_Panic does not return, but is not declared as diverging.
+    loop {
+        core::hint::spin_loop()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hook(_info: &PanicInfo) {}
+
+    #[test]
+    fn test_code() {
+        assert_eq!(code(), 0);
+        set_code(0xDEAD);
+        assert_eq!(code(), 0xDEAD);
+    }
+
+    #[test]
+    fn test_hook() {
+        assert!(take_hook().is_none());
+        set_hook(hook);
+        assert_eq!(take_hook().map(|hook| hook as usize), Some(hook as usize));
+        assert!(take_hook().is_none());
+    }
+}
@@ -0,0 +1,296 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use core::marker::PhantomData;
+
+use aead::consts::{U0, U12, U16, U24, U32};
+use aead::generic_array::ArrayLength;
+use aead::{AeadCore, AeadInPlace, Nonce, Tag};
+use crypto_common::{InvalidLength, Key, KeyInit, KeySizeUser};
+use zeroize::Zeroizing;
+
+use crate::{
+    AlgorithmId, AttributeId, AttributeMemref, OperationMode, TransientObject, TransientObjectType,
+    AE,
+};
+
+/// AES-GCM with a 96-bit nonce and a 128-bit tag backed by [AE](crate::AE).
+///
+/// The key is held in a [TransientObject](crate::TransientObject), a new
+/// operation is allocated for every message.
+pub struct AesGcm<KeySize> {
+    key: TransientObject,
+    _key_size: PhantomData<KeySize>,
+}
+
+/// AES-128-GCM backed by [AE](crate::AE).
+pub type Aes128Gcm = AesGcm<U16>;
+/// AES-192-GCM backed by [AE](crate::AE).
+pub type Aes192Gcm = AesGcm<U24>;
+/// AES-256-GCM backed by [AE](crate::AE).
+pub type Aes256Gcm = AesGcm<U32>;
+
+const TAG_BITS: usize = 128;
+
+impl<KeySize: ArrayLength<u8>> AesGcm<KeySize> {
+    /// Import `key` into a transient AES key object.
+    pub fn try_new(key: &[u8]) -> crate::Result<Self> {
+        let mut object = TransientObject::allocate(TransientObjectType::Aes, key.len() * 8)?;
+        object.populate(&[AttributeMemref::from_ref(AttributeId::SecretValue, key).into()])?;
+        Ok(Self {
+            key: object,
+            _key_size: PhantomData,
+        })
+    }
+
+    fn operation(
+        &self,
+        mode: OperationMode,
+        nonce: &[u8],
+        aad: &[u8],
+        payload_len: usize,
+    ) -> crate::Result<AE> {
+        let operation = AE::allocate(AlgorithmId::AesGcm, mode, KeySize::USIZE * 8)?;
+        operation.set_key(&self.key)?;
+        operation.init(nonce, TAG_BITS, aad.len(), payload_len)?;
+        operation.update_aad(aad);
+        Ok(operation)
+    }
+}
+
+impl<KeySize: ArrayLength<u8>> KeySizeUser for AesGcm<KeySize> {
+    type KeySize = KeySize;
+}
+
+impl<KeySize: ArrayLength<u8>> KeyInit for AesGcm<KeySize> {
+    fn new(key: &Key<Self>) -> Self {
+        Self::try_new(key).expect("failed to import the AES key")
+    }
+
+    fn new_from_slice(key: &[u8]) -> Result<Self, InvalidLength> {
+        if key.len() != KeySize::USIZE {
+            return Err(InvalidLength);
+        }
+        Self::try_new(key).map_err(|_| InvalidLength)
+    }
+}
+
+impl<KeySize> AeadCore for AesGcm<KeySize> {
+    type NonceSize = U12;
+    type TagSize = U16;
+    type CiphertextOverhead = U0;
+}
+
+impl<KeySize: ArrayLength<u8>> AeadInPlace for AesGcm<KeySize> {
+    fn encrypt_in_place_detached(
+        &self,
+        nonce: &Nonce<Self>,
+        associated_data: &[u8],
+        buffer: &mut [u8],
+    ) -> aead::Result<Tag<Self>> {
+        let operation = self
+            .operation(OperationMode::Encrypt, nonce, associated_data, buffer.len())
+            .map_err(|_| aead::Error)?;
+        // The TEE API takes distinct source and destination buffers.
+        let plaintext = Zeroizing::new(buffer.to_vec());
+        let mut tag = Tag::<Self>::default();
+        match operation.encrypt_final(&plaintext, buffer, &mut tag) {
+            Ok((len, tag_len)) if len == buffer.len() && tag_len == tag.len() => Ok(tag),
+            _ => Err(aead::Error),
+        }
+    }
+
+    fn decrypt_in_place_detached(
+        &self,
+        nonce: &Nonce<Self>,
+        associated_data: &[u8],
+        buffer: &mut [u8],
+        tag: &Tag<Self>,
+    ) -> aead::Result<()> {
+        let operation = self
+            .operation(OperationMode::Decrypt, nonce, associated_data, buffer.len())
+            .map_err(|_| aead::Error)?;
+        let ciphertext = Zeroizing::new(buffer.to_vec());
+        match operation.decrypt_final(&ciphertext, buffer, tag) {
+            Ok(len) if len == buffer.len() => Ok(()),
+            _ => {
+                buffer.fill(0);
+                Err(aead::Error)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use std::vec::Vec;
+
+    use aead::{AeadInPlace, KeyInit};
+    use optee_utee_mock::{
+        crypto::{set_global_crypto_mock, MockCryptoController},
+        object::{set_global_object_mock, MockObjectController, SERIAL_TEST_LOCK},
+        raw,
+    };
+
+    use super::Aes128Gcm;
+
+    const KEY: [u8; 16] = [0x42; 16];
+    const NONCE: [u8; 12] = [0x24; 12];
+
+    fn bytes<'a>(data: *const core::ffi::c_void, len: usize) -> &'a [u8] {
+        unsafe { core::slice::from_raw_parts(data as *const u8, len) }
+    }
+
+    // The mocked cipher xors the payload with 0x5a, and its tag mixes the AAD
+    // and the ciphertext.
+    fn mocked_tag(aad: &[u8], ciphertext: &[u8]) -> [u8; 16] {
+        let mut tag = [0u8; 16];
+        for (i, byte) in aad.iter().chain(ciphertext).enumerate() {
+            tag[i % 16] ^= byte.wrapping_add(i as u8);
+        }
+        tag
+    }
+
+    fn mock_aes_gcm(encryptions: usize, decryptions: usize) {
+        let mut object_mock = MockObjectController::new();
+        object_mock
+            .expect_TEE_AllocateTransientObject()
+            .withf(|object_type, size, _| (*object_type, *size) == (raw::TEE_TYPE_AES, 128))
+            .times(1)
+            .returning(|_, _, object| {
+                unsafe { *object = core::ptr::NonNull::dangling().as_ptr() };
+                raw::TEE_SUCCESS
+            });
+        object_mock
+            .expect_TEE_PopulateTransientObject()
+            .times(1)
+            .returning(|_, attrs, count| {
+                assert_eq!(count, 1);
+                let attr = unsafe { &*attrs };
+                assert_eq!(attr.attributeID, raw::TEE_ATTR_SECRET_VALUE);
+                let memref = unsafe { attr.content.memref };
+                assert_eq!(bytes(memref.buffer, memref.size), &KEY);
+                raw::TEE_SUCCESS
+            });
+        object_mock
+            .expect_TEE_CloseObject()
+            .times(1)
+            .return_const(());
+        set_global_object_mock(object_mock);
+
+        let mut crypto_mock = MockCryptoController::new();
+        crypto_mock.expect_TEE_AllocateOperation_success(
+            encryptions,
+            raw::TEE_ALG_AES_GCM,
+            raw::TEE_MODE_ENCRYPT,
+            128,
+        );
+        crypto_mock.expect_TEE_AllocateOperation_success(
+            decryptions,
+            raw::TEE_ALG_AES_GCM,
+            raw::TEE_MODE_DECRYPT,
+            128,
+        );
+        crypto_mock
+            .expect_TEE_SetOperationKey()
+            .return_const(raw::TEE_SUCCESS);
+        let aad = Arc::new(Mutex::new(Vec::new()));
+        let initialized = aad.clone();
+        crypto_mock
+            .expect_TEE_AEInit()
+            .returning(move |_, nonce, nonce_len, tag_len, _, _| {
+                assert_eq!(bytes(nonce, nonce_len), &NONCE);
+                assert_eq!(tag_len, 128);
+                initialized.lock().unwrap().clear();
+                raw::TEE_SUCCESS
+            });
+        let updated = aad.clone();
+        crypto_mock
+            .expect_TEE_AEUpdateAAD()
+            .returning(move |_, data, len| {
+                updated.lock().unwrap().extend_from_slice(bytes(data, len))
+            });
+        let encrypted = aad.clone();
+        crypto_mock
+            .expect_TEE_AEEncryptFinal()
+            .times(encryptions)
+            .returning(move |_, src, src_len, dest, dest_len, tag, tag_len| {
+                let dest = unsafe {
+                    assert_eq!(*dest_len, src_len);
+                    core::slice::from_raw_parts_mut(dest as *mut u8, src_len)
+                };
+                for (out, byte) in dest.iter_mut().zip(bytes(src, src_len)) {
+                    *out = byte ^ 0x5a;
+                }
+                let tag = unsafe {
+                    assert_eq!(*tag_len, 16);
+                    core::slice::from_raw_parts_mut(tag as *mut u8, 16)
+                };
+                tag.copy_from_slice(&mocked_tag(&encrypted.lock().unwrap(), dest));
+                raw::TEE_SUCCESS
+            });
+        crypto_mock
+            .expect_TEE_AEDecryptFinal()
+            .times(decryptions)
+            .returning(move |_, src, src_len, dest, dest_len, tag, tag_len| {
+                let ciphertext = bytes(src, src_len);
+                let dest = unsafe {
+                    assert_eq!(*dest_len, src_len);
+                    core::slice::from_raw_parts_mut(dest as *mut u8, src_len)
+                };
+                // the plaintext is written out even when the tag is invalid
+                for (out, byte) in dest.iter_mut().zip(ciphertext) {
+                    *out = byte ^ 0x5a;
+                }
+                if bytes(tag, tag_len) == mocked_tag(&aad.lock().unwrap(), ciphertext) {
+                    raw::TEE_SUCCESS
+                } else {
+                    raw::TEE_ERROR_MAC_INVALID
+                }
+            });
+        set_global_crypto_mock(crypto_mock);
+    }
+
+    #[test]
+    fn test_aes_128_gcm() {
+        let _lock = SERIAL_TEST_LOCK.lock();
+        mock_aes_gcm(1, 2);
+
+        let cipher = Aes128Gcm::new_from_slice(&KEY).unwrap();
+        let mut buffer = *b"plaintext";
+        let tag = cipher
+            .encrypt_in_place_detached(&NONCE.into(), b"aad", &mut buffer)
+            .unwrap();
+        assert_ne!(&buffer, b"plaintext");
+
+        let ciphertext = buffer;
+        cipher
+            .decrypt_in_place_detached(&NONCE.into(), b"aad", &mut buffer, &tag)
+            .unwrap();
+        assert_eq!(&buffer, b"plaintext");
+
+        // a plaintext which fails authentication is not returned
+        let mut buffer = ciphertext;
+        assert!(cipher
+            .decrypt_in_place_detached(&NONCE.into(), b"AAD", &mut buffer, &tag)
+            .is_err());
+        assert_eq!(buffer, [0u8; 9]);
+
+        assert!(Aes128Gcm::new_from_slice(&KEY[..8]).is_err());
+    }
+}
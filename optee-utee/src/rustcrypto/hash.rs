@@ -0,0 +1,180 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use digest::consts::{U16, U20, U28, U32, U48, U64};
+use digest::{FixedOutput, FixedOutputReset, HashMarker, Output, OutputSizeUser, Reset, Update};
+
+use crate::{AlgorithmId, Digest};
+
+macro_rules! tee_hash {
+    ($(#[$doc:meta])* $name:ident, $algo:ident, $output_size:ty) => {
+        $(#[$doc])*
+        pub struct $name(Digest);
+
+        impl $name {
+            /// Allocate the underlying [Digest](crate::Digest) operation.
+            pub fn try_new() -> crate::Result<Self> {
+                Digest::allocate(AlgorithmId::$algo).map(Self)
+            }
+        }
+
+        impl Default for $name {
+            fn default() -> Self {
+                Self::try_new().expect("failed to allocate the digest operation")
+            }
+        }
+
+        impl Clone for $name {
+            fn clone(&self) -> Self {
+                let mut cloned = Self::default();
+                cloned.0.copy(&self.0);
+                cloned
+            }
+        }
+
+        impl HashMarker for $name {}
+
+        impl OutputSizeUser for $name {
+            type OutputSize = $output_size;
+        }
+
+        impl Update for $name {
+            fn update(&mut self, data: &[u8]) {
+                self.0.update(data);
+            }
+        }
+
+        impl FixedOutput for $name {
+            fn finalize_into(self, out: &mut Output<Self>) {
+                self.0
+                    .do_final(&[], out)
+                    .expect("failed to finalize the digest operation");
+            }
+        }
+
+        impl Reset for $name {
+            fn reset(&mut self) {
+                self.0.reset();
+            }
+        }
+
+        // The operation is back to initial state after `do_final`.
+        impl FixedOutputReset for $name {
+            fn finalize_into_reset(&mut self, out: &mut Output<Self>) {
+                self.0
+                    .do_final(&[], out)
+                    .expect("failed to finalize the digest operation");
+            }
+        }
+    };
+}
+
+tee_hash!(
+    /// MD5 backed by [Digest](crate::Digest).
+    Md5,
+    Md5,
+    U16
+);
+tee_hash!(
+    /// SHA-1 backed by [Digest](crate::Digest).
+    Sha1,
+    Sha1,
+    U20
+);
+tee_hash!(
+    /// SHA-224 backed by [Digest](crate::Digest).
+    Sha224,
+    Sha224,
+    U28
+);
+tee_hash!(
+    /// SHA-256 backed by [Digest](crate::Digest).
+    Sha256,
+    Sha256,
+    U32
+);
+tee_hash!(
+    /// SHA-384 backed by [Digest](crate::Digest).
+    Sha384,
+    Sha384,
+    U48
+);
+tee_hash!(
+    /// SHA-512 backed by [Digest](crate::Digest).
+    Sha512,
+    Sha512,
+    U64
+);
+tee_hash!(
+    /// SM3 backed by [Digest](crate::Digest).
+    Sm3,
+    Sm3,
+    U32
+);
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use std::vec::Vec;
+
+    use digest::Digest;
+    use optee_utee_mock::{
+        crypto::{set_global_crypto_mock, MockCryptoController},
+        object::SERIAL_TEST_LOCK,
+        raw,
+    };
+
+    use super::Sha256;
+
+    #[test]
+    fn test_sha256() {
+        let _lock = SERIAL_TEST_LOCK.lock();
+
+        let mut mock = MockCryptoController::new();
+        mock.expect_TEE_AllocateOperation_success(1, raw::TEE_ALG_SHA256, raw::TEE_MODE_DIGEST, 0);
+        let message = Arc::new(Mutex::new(Vec::new()));
+        let updated = message.clone();
+        mock.expect_TEE_DigestUpdate()
+            .returning(move |_, chunk, size| {
+                let chunk = unsafe { core::slice::from_raw_parts(chunk as *const u8, size) };
+                updated.lock().unwrap().extend_from_slice(chunk);
+            });
+        // the mocked hash is the message padded with zeros
+        mock.expect_TEE_DigestDoFinal().times(1).returning(
+            move |_, _, chunk_len, hash, hash_len| {
+                assert_eq!(chunk_len, 0);
+                let message = message.lock().unwrap();
+                let hash = unsafe {
+                    assert_eq!(*hash_len, 32);
+                    core::slice::from_raw_parts_mut(hash as *mut u8, 32)
+                };
+                hash.fill(0);
+                hash[..message.len()].copy_from_slice(&message);
+                raw::TEE_SUCCESS
+            },
+        );
+        set_global_crypto_mock(mock);
+
+        let hash = Sha256::new()
+            .chain_update(b"abc")
+            .chain_update(b"def")
+            .finalize();
+        let mut expected = [0u8; 32];
+        expected[..6].copy_from_slice(b"abcdef");
+        assert_eq!(hash.as_slice(), &expected);
+    }
+}
@@ -0,0 +1,281 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crypto_common::{InvalidLength, Key, KeyInit, KeySizeUser};
+use digest::consts::{U128, U16, U20, U28, U32, U48, U64};
+use digest::{FixedOutput, FixedOutputReset, MacMarker, Output, OutputSizeUser, Reset, Update};
+
+use crate::{AlgorithmId, AttributeId, AttributeMemref, Mac, TransientObject, TransientObjectType};
+
+fn allocate_mac(
+    algo: AlgorithmId,
+    object_type: TransientObjectType,
+    key: &[u8],
+) -> crate::Result<Mac> {
+    let key_size = key.len() * 8;
+    let mut key_object = TransientObject::allocate(object_type, key_size)?;
+    key_object.populate(&[AttributeMemref::from_ref(AttributeId::SecretValue, key).into()])?;
+    let mac = Mac::allocate(algo, key_size)?;
+    mac.set_key(&key_object)?;
+    mac.init(&[]);
+    Ok(mac)
+}
+
+macro_rules! tee_hmac {
+    ($(#[$doc:meta])* $name:ident, $algo:ident, $block_size:ty, $output_size:ty) => {
+        $(#[$doc])*
+        ///
+        /// The key size is variable, [new_from_slice](KeyInit::new_from_slice)
+        /// accepts any length supported by the corresponding
+        /// [TransientObjectType](crate::TransientObjectType).
+        pub struct $name(Mac);
+
+        impl $name {
+            /// Allocate the underlying [Mac](crate::Mac) operation keyed with
+            /// `key`.
+            pub fn try_new(key: &[u8]) -> crate::Result<Self> {
+                allocate_mac(AlgorithmId::$algo, TransientObjectType::$algo, key).map(Self)
+            }
+        }
+
+        impl KeySizeUser for $name {
+            type KeySize = $block_size;
+        }
+
+        impl KeyInit for $name {
+            fn new(key: &Key<Self>) -> Self {
+                Self::try_new(key).expect("failed to allocate the MAC operation")
+            }
+
+            fn new_from_slice(key: &[u8]) -> Result<Self, InvalidLength> {
+                Self::try_new(key).map_err(|_| InvalidLength)
+            }
+        }
+
+        impl MacMarker for $name {}
+
+        impl OutputSizeUser for $name {
+            type OutputSize = $output_size;
+        }
+
+        impl Update for $name {
+            fn update(&mut self, data: &[u8]) {
+                self.0.update(data);
+            }
+        }
+
+        impl FixedOutput for $name {
+            fn finalize_into(self, out: &mut Output<Self>) {
+                self.0
+                    .compute_final(&[], out)
+                    .expect("failed to finalize the MAC operation");
+            }
+        }
+
+        impl Reset for $name {
+            fn reset(&mut self) {
+                self.0.reset();
+                self.0.init(&[]);
+            }
+        }
+
+        // The key is kept after `compute_final`, only re-initialization is
+        // needed.
+        impl FixedOutputReset for $name {
+            fn finalize_into_reset(&mut self, out: &mut Output<Self>) {
+                self.0
+                    .compute_final(&[], out)
+                    .expect("failed to finalize the MAC operation");
+                self.0.init(&[]);
+            }
+        }
+    };
+}
+
+tee_hmac!(
+    /// HMAC-MD5 backed by [Mac](crate::Mac).
+    HmacMd5,
+    HmacMd5,
+    U64,
+    U16
+);
+tee_hmac!(
+    /// HMAC-SHA1 backed by [Mac](crate::Mac).
+    HmacSha1,
+    HmacSha1,
+    U64,
+    U20
+);
+tee_hmac!(
+    /// HMAC-SHA224 backed by [Mac](crate::Mac).
+    HmacSha224,
+    HmacSha224,
+    U64,
+    U28
+);
+tee_hmac!(
+    /// HMAC-SHA256 backed by [Mac](crate::Mac).
+    HmacSha256,
+    HmacSha256,
+    U64,
+    U32
+);
+tee_hmac!(
+    /// HMAC-SHA384 backed by [Mac](crate::Mac).
+    HmacSha384,
+    HmacSha384,
+    U128,
+    U48
+);
+tee_hmac!(
+    /// HMAC-SHA512 backed by [Mac](crate::Mac).
+    HmacSha512,
+    HmacSha512,
+    U128,
+    U64
+);
+tee_hmac!(
+    /// HMAC-SM3 backed by [Mac](crate::Mac).
+    HmacSm3,
+    HmacSm3,
+    U64,
+    U32
+);
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use std::vec::Vec;
+
+    use digest::Mac;
+    use optee_utee_mock::{
+        crypto::{set_global_crypto_mock, MockCryptoController},
+        object::{set_global_object_mock, MockObjectController, SERIAL_TEST_LOCK},
+        raw,
+    };
+
+    use super::HmacSha256;
+
+    const KEY: &[u8] = b"0123456789abcdef";
+
+    fn bytes<'a>(data: *const core::ffi::c_void, len: usize) -> &'a [u8] {
+        unsafe { core::slice::from_raw_parts(data as *const u8, len) }
+    }
+
+    // Mock the key objects and the MAC operations of `times` HMAC-SHA256
+    // instances, whose mocked MAC is the message xored with the key.
+    fn mock_hmac(times: usize) {
+        let key = Arc::new(Mutex::new(Vec::new()));
+
+        let mut object_mock = MockObjectController::new();
+        object_mock
+            .expect_TEE_AllocateTransientObject()
+            .withf(|object_type, size, _| {
+                (*object_type, *size) == (raw::TEE_TYPE_HMAC_SHA256, KEY.len() as u32 * 8)
+            })
+            .times(times)
+            .returning(|_, _, object| {
+                unsafe { *object = core::ptr::NonNull::dangling().as_ptr() };
+                raw::TEE_SUCCESS
+            });
+        let populated = key.clone();
+        object_mock
+            .expect_TEE_PopulateTransientObject()
+            .times(times)
+            .returning(move |_, attrs, count| {
+                assert_eq!(count, 1);
+                let attr = unsafe { &*attrs };
+                assert_eq!(attr.attributeID, raw::TEE_ATTR_SECRET_VALUE);
+                let memref = unsafe { attr.content.memref };
+                *populated.lock().unwrap() = bytes(memref.buffer, memref.size).to_vec();
+                raw::TEE_SUCCESS
+            });
+        object_mock
+            .expect_TEE_CloseObject()
+            .times(times)
+            .return_const(());
+        set_global_object_mock(object_mock);
+
+        let mut crypto_mock = MockCryptoController::new();
+        crypto_mock.expect_TEE_AllocateOperation_success(
+            times,
+            raw::TEE_ALG_HMAC_SHA256,
+            raw::TEE_MODE_MAC,
+            KEY.len() as u32 * 8,
+        );
+        crypto_mock
+            .expect_TEE_SetOperationKey()
+            .times(times)
+            .return_const(raw::TEE_SUCCESS);
+        let message = Arc::new(Mutex::new(Vec::new()));
+        let initialized = message.clone();
+        crypto_mock
+            .expect_TEE_MACInit()
+            .returning(move |_, _, iv_len| {
+                assert_eq!(iv_len, 0);
+                initialized.lock().unwrap().clear();
+            });
+        let updated = message.clone();
+        crypto_mock
+            .expect_TEE_MACUpdate()
+            .returning(move |_, chunk, size| {
+                updated
+                    .lock()
+                    .unwrap()
+                    .extend_from_slice(bytes(chunk, size))
+            });
+        crypto_mock
+            .expect_TEE_MACComputeFinal()
+            .times(times)
+            .returning(move |_, _, _, mac, mac_len| {
+                let key = key.lock().unwrap();
+                let message = message.lock().unwrap();
+                let mac = unsafe {
+                    assert_eq!(*mac_len, 32);
+                    core::slice::from_raw_parts_mut(mac as *mut u8, 32)
+                };
+                for (i, byte) in mac.iter_mut().enumerate() {
+                    *byte = message.get(i).copied().unwrap_or(0) ^ key[i % key.len()];
+                }
+                raw::TEE_SUCCESS
+            });
+        set_global_crypto_mock(crypto_mock);
+    }
+
+    #[test]
+    fn test_hmac_sha256() {
+        let _lock = SERIAL_TEST_LOCK.lock();
+        mock_hmac(3);
+
+        let mut mac = HmacSha256::new_from_slice(KEY).unwrap();
+        mac.update(b"mess");
+        mac.update(b"age");
+        let tag = mac.finalize().into_bytes();
+        let mut expected = [0u8; 32];
+        for (i, byte) in expected.iter_mut().enumerate() {
+            *byte = b"message".get(i).copied().unwrap_or(0) ^ KEY[i % KEY.len()];
+        }
+        assert_eq!(tag.as_slice(), &expected);
+
+        let mut mac = HmacSha256::new_from_slice(KEY).unwrap();
+        mac.update(b"message");
+        assert!(mac.verify_slice(&tag).is_ok());
+        let mut mac = HmacSha256::new_from_slice(KEY).unwrap();
+        mac.update(b"massage");
+        assert!(mac.verify_slice(&tag).is_err());
+    }
+}
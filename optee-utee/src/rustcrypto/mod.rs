@@ -0,0 +1,36 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Implementations of the [RustCrypto](https://github.com/RustCrypto/traits)
+//! traits on top of the TEE crypto operations, so that crates built on those
+//! traits can use the TEE implementation.
+//!
+//! Requires the `rustcrypto` feature.
+//!
+//! Since the traits have infallible constructors and finalizers, the
+//! adapters panic when the underlying TEE operation fails in those
+//! functions. Use the `try_new` constructors to handle allocation errors.
+
+mod aes_gcm;
+mod hash;
+mod hmac;
+mod signer;
+
+pub use self::aes_gcm::{Aes128Gcm, Aes192Gcm, Aes256Gcm, AesGcm};
+pub use self::hash::{Md5, Sha1, Sha224, Sha256, Sha384, Sha512, Sm3};
+pub use self::hmac::{HmacMd5, HmacSha1, HmacSha224, HmacSha256, HmacSha384, HmacSha512, HmacSm3};
+pub use self::signer::{Signature, SigningKey, VerifyingKey};
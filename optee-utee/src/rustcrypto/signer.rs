@@ -0,0 +1,315 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+
+use signature::{SignatureEncoding, Signer, Verifier};
+
+use crate::{AlgorithmId, Asymmetric, GenericObject, OperationMode, TransientObject};
+
+/// A signature produced by [SigningKey](SigningKey), in the encoding returned
+/// by the TEE: raw `r || s` for ECDSA, `R || S` for Ed25519 and the usual
+/// octet string for RSA.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Signature(Vec<u8>);
+
+impl Signature {
+    /// Return the encoded signature.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl AsRef<[u8]> for Signature {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl TryFrom<&[u8]> for Signature {
+    type Error = signature::Error;
+
+    fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+        if bytes.is_empty() {
+            return Err(signature::Error::new());
+        }
+        Ok(Self(bytes.to_vec()))
+    }
+}
+
+impl From<Signature> for Vec<u8> {
+    fn from(signature: Signature) -> Vec<u8> {
+        signature.0
+    }
+}
+
+impl SignatureEncoding for Signature {
+    type Repr = Vec<u8>;
+}
+
+fn allocate_operation(
+    algorithm: AlgorithmId,
+    mode: OperationMode,
+    key: &TransientObject,
+) -> crate::Result<Asymmetric> {
+    let operation = Asymmetric::allocate(algorithm, mode, key.info()?.object_size())?;
+    operation.set_key(key)?;
+    Ok(operation)
+}
+
+/// A private key held in a [TransientObject](crate::TransientObject)
+/// implementing [Signer](signature::Signer).
+///
+/// Supports the algorithms of
+/// [Asymmetric::sign_message](crate::Asymmetric::sign_message).
+pub struct SigningKey {
+    algorithm: AlgorithmId,
+    key: TransientObject,
+}
+
+impl SigningKey {
+    /// Wrap `key`, a key pair object matching `algorithm`.
+    pub fn new(algorithm: AlgorithmId, key: TransientObject) -> Self {
+        Self { algorithm, key }
+    }
+
+    /// Return the signature algorithm.
+    pub fn algorithm(&self) -> AlgorithmId {
+        self.algorithm
+    }
+
+    /// Return the key object.
+    pub fn key(&self) -> &TransientObject {
+        &self.key
+    }
+
+    fn sign_message(&self, msg: &[u8]) -> crate::Result<Signature> {
+        allocate_operation(self.algorithm, OperationMode::Sign, &self.key)?
+            .sign_message(&[], msg)
+            .map(Signature)
+    }
+}
+
+impl Signer<Signature> for SigningKey {
+    fn try_sign(&self, msg: &[u8]) -> Result<Signature, signature::Error> {
+        self.sign_message(msg).map_err(|_| signature::Error::new())
+    }
+}
+
+/// A public key (or key pair) held in a
+/// [TransientObject](crate::TransientObject) implementing
+/// [Verifier](signature::Verifier).
+///
+/// Supports the same algorithms as [SigningKey](SigningKey).
+pub struct VerifyingKey {
+    algorithm: AlgorithmId,
+    key: TransientObject,
+}
+
+impl VerifyingKey {
+    /// Wrap `key`, a public key or key pair object matching `algorithm`.
+    pub fn new(algorithm: AlgorithmId, key: TransientObject) -> Self {
+        Self { algorithm, key }
+    }
+
+    /// Return the signature algorithm.
+    pub fn algorithm(&self) -> AlgorithmId {
+        self.algorithm
+    }
+
+    /// Return the key object.
+    pub fn key(&self) -> &TransientObject {
+        &self.key
+    }
+
+    fn verify_message(&self, msg: &[u8], signature: &Signature) -> crate::Result<()> {
+        allocate_operation(self.algorithm, OperationMode::Verify, &self.key)?.verify_message(
+            &[],
+            msg,
+            signature.as_bytes(),
+        )
+    }
+}
+
+impl Verifier<Signature> for VerifyingKey {
+    fn verify(&self, msg: &[u8], signature: &Signature) -> Result<(), signature::Error> {
+        self.verify_message(msg, signature)
+            .map_err(|_| signature::Error::new())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::convert::TryFrom;
+    use std::vec::Vec;
+
+    use optee_utee_mock::{
+        crypto::{set_global_crypto_mock, MockCryptoController},
+        object::{set_global_object_mock, MockObjectController, SERIAL_TEST_LOCK},
+        raw,
+    };
+    use signature::{Signer, Verifier};
+
+    use super::{Signature, SigningKey, VerifyingKey};
+    use crate::{AlgorithmId, Asymmetric, TransientObject, TransientObjectType};
+
+    fn bytes<'a>(data: *const core::ffi::c_void, len: usize) -> &'a [u8] {
+        unsafe { core::slice::from_raw_parts(data as *const u8, len) }
+    }
+
+    // The mocked SHA-256 folds the message into 32 bytes, and the mocked
+    // ECDSA signature of a digest is `r || s` with `r` the digest and `s` its
+    // complement.
+    fn mocked_hash(message: &[u8]) -> [u8; 32] {
+        let mut hash = [0u8; 32];
+        for (i, byte) in message.iter().enumerate() {
+            hash[i % 32] ^= byte.wrapping_add(i as u8);
+        }
+        hash
+    }
+
+    fn mocked_signature(digest: &[u8]) -> Vec<u8> {
+        digest
+            .iter()
+            .chain(digest)
+            .enumerate()
+            .map(|(i, byte)| if i < 32 { *byte } else { !byte })
+            .collect()
+    }
+
+    // Mock a P-256 key pair signing `signs` messages with ECDSA SHA-256, and
+    // verifying `verifies` signatures.
+    fn mock_ecdsa(signs: usize, verifies: usize) {
+        let mut object_mock = MockObjectController::new();
+        object_mock
+            .expect_TEE_AllocateTransientObject()
+            .withf(|object_type, size, _| {
+                (*object_type, *size) == (raw::TEE_TYPE_ECDSA_KEYPAIR, 256)
+            })
+            .times(2)
+            .returning(|_, _, object| {
+                unsafe { *object = core::ptr::NonNull::dangling().as_ptr() };
+                raw::TEE_SUCCESS
+            });
+        object_mock
+            .expect_TEE_GetObjectInfo1()
+            .times(signs + verifies)
+            .returning(|_, info| {
+                unsafe { (*info).objectSize = 256 };
+                raw::TEE_SUCCESS
+            });
+        object_mock
+            .expect_TEE_CloseObject()
+            .times(2)
+            .return_const(());
+        set_global_object_mock(object_mock);
+
+        let algorithm = AlgorithmId::EcDsaSha256 as u32;
+        let mut crypto_mock = MockCryptoController::new();
+        crypto_mock.expect_TEE_AllocateOperation_success(signs, algorithm, raw::TEE_MODE_SIGN, 256);
+        crypto_mock.expect_TEE_AllocateOperation_success(
+            verifies,
+            algorithm,
+            raw::TEE_MODE_VERIFY,
+            256,
+        );
+        crypto_mock.expect_TEE_AllocateOperation_success(
+            signs + verifies,
+            raw::TEE_ALG_SHA256,
+            raw::TEE_MODE_DIGEST,
+            0,
+        );
+        crypto_mock
+            .expect_TEE_SetOperationKey()
+            .times(signs + verifies)
+            .return_const(raw::TEE_SUCCESS);
+        crypto_mock
+            .expect_TEE_GetOperationInfo()
+            .returning(move |_, info| unsafe {
+                (*info).algorithm = algorithm;
+                (*info).keySize = 256;
+            });
+        crypto_mock
+            .expect_TEE_DigestDoFinal()
+            .times(signs + verifies)
+            .returning(|_, chunk, chunk_len, hash, hash_len| {
+                let hash = unsafe {
+                    assert!(*hash_len >= 32);
+                    *hash_len = 32;
+                    core::slice::from_raw_parts_mut(hash as *mut u8, 32)
+                };
+                hash.copy_from_slice(&mocked_hash(bytes(chunk, chunk_len)));
+                raw::TEE_SUCCESS
+            });
+        crypto_mock
+            .expect_TEE_AsymmetricSignDigest()
+            .times(signs)
+            .returning(
+                |_, _, count, digest, digest_len, signature, signature_len| {
+                    assert_eq!(count, 0);
+                    assert_eq!(digest_len, 32);
+                    let signature = unsafe {
+                        assert_eq!(*signature_len, 64);
+                        core::slice::from_raw_parts_mut(signature as *mut u8, 64)
+                    };
+                    signature.copy_from_slice(&mocked_signature(bytes(digest, digest_len)));
+                    raw::TEE_SUCCESS
+                },
+            );
+        crypto_mock
+            .expect_TEE_AsymmetricVerifyDigest()
+            .times(verifies)
+            .returning(|_, _, _, digest, digest_len, signature, signature_len| {
+                if bytes(signature, signature_len) == mocked_signature(bytes(digest, digest_len)) {
+                    raw::TEE_SUCCESS
+                } else {
+                    raw::TEE_ERROR_SIGNATURE_INVALID
+                }
+            });
+        set_global_crypto_mock(crypto_mock);
+    }
+
+    fn key() -> TransientObject {
+        TransientObject::allocate(TransientObjectType::EcdsaKeypair, 256).unwrap()
+    }
+
+    #[test]
+    fn test_ecdsa_sha256() {
+        let _lock = SERIAL_TEST_LOCK.lock();
+        mock_ecdsa(1, 2);
+
+        let signing_key = SigningKey::new(AlgorithmId::EcDsaSha256, key());
+        let verifying_key = VerifyingKey::new(AlgorithmId::EcDsaSha256, key());
+
+        // the raw `r || s` signature of the TEE is returned as is
+        let signature = signing_key.try_sign(b"message").unwrap();
+        assert_eq!(
+            signature.as_bytes(),
+            mocked_signature(&mocked_hash(b"message"))
+        );
+
+        // and goes through DER, as exchanged with other parties, unchanged
+        let der = Asymmetric::ecdsa_raw_to_der(signature.as_bytes()).unwrap();
+        let raw = Asymmetric::ecdsa_der_to_raw(&der, 256).unwrap();
+        let signature = Signature::try_from(raw.as_slice()).unwrap();
+        verifying_key.verify(b"message", &signature).unwrap();
+
+        assert!(verifying_key.verify(b"other message", &signature).is_err());
+        assert!(Signature::try_from(&[][..]).is_err());
+    }
+}
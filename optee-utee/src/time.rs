@@ -15,9 +15,12 @@
 // specific language governing permissions and limitations
 // under the License.
 
-use crate::{Error, Result};
-use optee_utee_sys as raw;
+use crate::{Error, ErrorKind, Result};
+use core::convert::TryFrom;
 use core::fmt;
+use core::ops::{Add, AddAssign, Sub, SubAssign};
+use core::time::Duration;
+use optee_utee_sys as raw;
 
 /// A millisecond resolution structure for saving the time.
 pub struct Time {
@@ -91,6 +94,36 @@ impl Time {
         }
     }
 
+    /// Wait for the specified duration. The waiting timer is `System Time`.
+    ///
+    /// Unlike [wait](Time::wait), the duration is not limited to `u32` milliseconds, and is
+    /// rounded up to the next millisecond, so the wait is never shorter than `duration`.
+    ///
+    /// # Example
+    ///
+    /// ``` rust,no_run
+    /// # use optee_utee::{Time, Result};
+    /// # use core::time::Duration;
+    /// # fn main() -> Result<()> {
+    /// Time::sleep(Duration::from_millis(1500))?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// 1) `Cancel`: If the wait has been cancelled.
+    ///
+    /// # Panics
+    ///
+    /// 1) If the Implementation detects any error.
+    pub fn sleep(duration: Duration) -> Result<()> {
+        for step in wait_steps(duration) {
+            Time::wait(step)?;
+        }
+        Ok(())
+    }
+
     /// Retrieve the persisten time of the Trusted Application. Since the timer is not
     /// automatically set, this function should be called after [set_ta_time](Time::set_ta_time).
     /// The time is a real-time source of time and the origin of this time is set individually by each Trusted Application.
@@ -116,6 +149,8 @@ impl Time {
     /// 3) `Overflow`: The number of seconds in the TA Persistent Time overflows the range of a
     ///    `u32`. The field `seconds` is still set to the TA Persistent Time truncated to 32 bits.
     ///
+    /// Use [TaTime::status] to tell these errors apart without matching on error kinds.
+    ///
     /// # Panics
     ///
     /// 1) If the Implementation detects any error.
@@ -165,3 +200,656 @@ impl fmt::Display for Time {
         )
     }
 }
+
+// TEE_Wait() takes a u32 of milliseconds, where TEE_TIMEOUT_INFINITE means
+// forever, so longer durations are waited in several steps.
+fn wait_steps(duration: Duration) -> impl Iterator<Item = u32> {
+    const MAX_STEP: u128 = (raw::TEE_TIMEOUT_INFINITE - 1) as u128;
+    let mut millis = (duration.as_nanos() + 999_999) / 1_000_000;
+    core::iter::from_fn(move || {
+        if millis == 0 {
+            return None;
+        }
+        let step = millis.min(MAX_STEP);
+        millis -= step;
+        Some(step as u32)
+    })
+}
+
+fn from_raw_time(time: &raw::TEE_Time) -> Duration {
+    Duration::from_secs(time.seconds as u64) + Duration::from_millis(time.millis as u64)
+}
+
+fn to_raw_time(duration: Duration) -> Result<raw::TEE_Time> {
+    let seconds = u32::try_from(duration.as_secs()).map_err(|_| Error::new(ErrorKind::Overflow))?;
+    Ok(raw::TEE_Time {
+        seconds,
+        millis: duration.subsec_millis(),
+    })
+}
+
+macro_rules! impl_time_arithmetic {
+    ($ty:ident) => {
+        impl $ty {
+            /// Returns the amount of time elapsed from `earlier` to `self`, or `None` if
+            /// `earlier` is later than `self`.
+            pub fn checked_duration_since(&self, earlier: $ty) -> Option<Duration> {
+                self.0.checked_sub(earlier.0)
+            }
+
+            /// Returns the amount of time elapsed from `earlier` to `self`, or zero if
+            /// `earlier` is later than `self`.
+            pub fn saturating_duration_since(&self, earlier: $ty) -> Duration {
+                self.checked_duration_since(earlier).unwrap_or_default()
+            }
+
+            /// Returns `self + duration`, or `None` if the result cannot be represented.
+            pub fn checked_add(&self, duration: Duration) -> Option<$ty> {
+                self.0.checked_add(duration).map($ty)
+            }
+
+            /// Returns `self - duration`, or `None` if the result cannot be represented.
+            pub fn checked_sub(&self, duration: Duration) -> Option<$ty> {
+                self.0.checked_sub(duration).map($ty)
+            }
+        }
+
+        impl Add<Duration> for $ty {
+            type Output = $ty;
+
+            /// # Panics
+            ///
+            /// 1) If the result cannot be represented, see `checked_add`.
+            fn add(self, duration: Duration) -> $ty {
+                self.checked_add(duration)
+                    .expect("overflow when adding duration to time")
+            }
+        }
+
+        impl AddAssign<Duration> for $ty {
+            fn add_assign(&mut self, duration: Duration) {
+                *self = *self + duration;
+            }
+        }
+
+        impl Sub<Duration> for $ty {
+            type Output = $ty;
+
+            /// # Panics
+            ///
+            /// 1) If the result cannot be represented, see `checked_sub`.
+            fn sub(self, duration: Duration) -> $ty {
+                self.checked_sub(duration)
+                    .expect("overflow when subtracting duration from time")
+            }
+        }
+
+        impl SubAssign<Duration> for $ty {
+            fn sub_assign(&mut self, duration: Duration) {
+                *self = *self - duration;
+            }
+        }
+
+        impl Sub<$ty> for $ty {
+            type Output = Duration;
+
+            /// Returns the amount of time elapsed from `earlier` to `self`, or zero if
+            /// `earlier` is later than `self`.
+            fn sub(self, earlier: $ty) -> Duration {
+                self.saturating_duration_since(earlier)
+            }
+        }
+    };
+}
+
+/// A measurement of the system time, see [system_time](Time::system_time).
+///
+/// The system time is not reset or rolled back during the life of a TA instance, so an
+/// `Instant` is only meaningful when compared with other instants of the same TA instance,
+/// to compute time differences and operation deadlines.
+///
+/// # Example
+///
+/// ``` rust,no_run
+/// # use optee_utee::Instant;
+/// let start = Instant::now();
+/// // ...
+/// let elapsed = start.elapsed();
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Instant(Duration);
+
+impl Instant {
+    /// Returns the current system time.
+    ///
+    /// # Panics
+    ///
+    /// 1) If the Implementation detects any error.
+    pub fn now() -> Self {
+        let mut time = raw::TEE_Time {
+            seconds: 0,
+            millis: 0,
+        };
+        unsafe {
+            raw::TEE_GetSystemTime(&mut time);
+        }
+        Instant(from_raw_time(&time))
+    }
+
+    /// Returns the amount of time elapsed since this instant.
+    ///
+    /// # Panics
+    ///
+    /// 1) If the Implementation detects any error.
+    pub fn elapsed(&self) -> Duration {
+        Instant::now() - *self
+    }
+}
+
+impl_time_arithmetic!(Instant);
+
+/// A measurement of the persistent time of the Trusted Application, see
+/// [ta_time](Time::ta_time).
+///
+/// The TA persistent time is a trusted real-time clock whose origin is set by the Trusted
+/// Application, and which persists across reboots. It is a distinct type from [ReeTime] so
+/// the two clocks cannot be mixed up.
+///
+/// # Example
+///
+/// ``` rust,no_run
+/// # use optee_utee::{TaTime, Result};
+/// # use core::time::Duration;
+/// # fn main() -> Result<()> {
+/// let expires_at = TaTime::now()? + Duration::from_secs(3600);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TaTime(Duration);
+
+impl TaTime {
+    /// The origin of the TA persistent time.
+    pub const ORIGIN: TaTime = TaTime(Duration::ZERO);
+
+    /// Returns the current TA persistent time.
+    ///
+    /// # Errors
+    ///
+    /// 1) `TimeNotSet`: Time is not set.
+    /// 2) `TimeNeedsReset`: Time needs to be reset.
+    /// 3) `Overflow`: The number of seconds in the TA Persistent Time overflows the range of a
+    ///    `u32`.
+    ///
+    /// Use [status](TaTime::status) to tell these errors apart.
+    ///
+    /// # Panics
+    ///
+    /// 1) If the Implementation detects any error.
+    pub fn now() -> Result<Self> {
+        match TaTime::status()? {
+            TaTimeStatus::Valid(time) => Ok(time),
+            TaTimeStatus::NotSet => Err(Error::new(ErrorKind::TimeNotSet)),
+            TaTimeStatus::NeedsReset => Err(Error::new(ErrorKind::TimeNeedsReset)),
+            TaTimeStatus::Overflow(_) => Err(Error::new(ErrorKind::Overflow)),
+        }
+    }
+
+    /// Returns the status of the TA persistent time, together with the time when it can be
+    /// read.
+    ///
+    /// # Example
+    ///
+    /// ``` rust,no_run
+    /// # use optee_utee::{TaTime, TaTimeStatus, Result};
+    /// # fn main() -> Result<()> {
+    /// match TaTime::status()? {
+    ///     TaTimeStatus::Valid(time) => { /* ... */ }
+    ///     TaTimeStatus::NotSet | TaTimeStatus::NeedsReset => {
+    ///         // Set the time again from a trusted source, see ReanchorPolicy.
+    ///     }
+    ///     TaTimeStatus::Overflow(truncated) => { /* ... */ }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// 1) Any error returned by the Implementation other than the ones reported as a
+    ///    [TaTimeStatus].
+    ///
+    /// # Panics
+    ///
+    /// 1) If the Implementation detects any error.
+    pub fn status() -> Result<TaTimeStatus> {
+        let mut time = raw::TEE_Time {
+            seconds: 0,
+            millis: 0,
+        };
+        let code = unsafe { raw::TEE_GetTAPersistentTime(&mut time) };
+        TaTimeStatus::from_raw(code, &time)
+    }
+
+    /// Set the TA persistent time to this time.
+    ///
+    /// # Errors
+    ///
+    /// 1) `Overflow`: If the number of seconds since [ORIGIN](TaTime::ORIGIN) overflows the
+    ///    range of a `u32`.
+    /// 2) `OutOfMemory`: If not enough memory is available to complete the operation.
+    /// 3) `StorageNoSpace`: If insufficient storage space is available to complete the operation.
+    ///
+    /// # Panics
+    ///
+    /// 1) If the Implementation detects any error.
+    pub fn set(&self) -> Result<()> {
+        let time = to_raw_time(self.0)?;
+        match unsafe { raw::TEE_SetTAPersistentTime(&time) } {
+            raw::TEE_SUCCESS => Ok(()),
+            code => Err(Error::from_raw_error(code)),
+        }
+    }
+}
+
+impl_time_arithmetic!(TaTime);
+
+/// The status of the TA persistent time, see [status](TaTime::status).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TaTimeStatus {
+    /// The TA persistent time is set, and holds the current time.
+    Valid(TaTime),
+    /// The TA persistent time has never been set by the Trusted Application.
+    NotSet,
+    /// The TA persistent time was set, but a rollback or a tampering has been detected, so it
+    /// can no longer be trusted until it is set again.
+    NeedsReset,
+    /// The number of seconds in the TA persistent time overflows the range of a `u32`, and
+    /// holds the time with its seconds truncated to 32 bits.
+    Overflow(TaTime),
+}
+
+impl TaTimeStatus {
+    fn from_raw(code: raw::TEE_Result, time: &raw::TEE_Time) -> Result<Self> {
+        match code {
+            raw::TEE_SUCCESS => Ok(TaTimeStatus::Valid(TaTime(from_raw_time(time)))),
+            raw::TEE_ERROR_TIME_NOT_SET => Ok(TaTimeStatus::NotSet),
+            raw::TEE_ERROR_TIME_NEEDS_RESET => Ok(TaTimeStatus::NeedsReset),
+            raw::TEE_ERROR_OVERFLOW => Ok(TaTimeStatus::Overflow(TaTime(from_raw_time(time)))),
+            code => Err(Error::from_raw_error(code)),
+        }
+    }
+
+    /// Returns the TA persistent time if it is valid.
+    pub fn time(&self) -> Option<TaTime> {
+        match self {
+            TaTimeStatus::Valid(time) => Some(*time),
+            _ => None,
+        }
+    }
+
+    /// Returns whether the TA persistent time is valid.
+    pub fn is_valid(&self) -> bool {
+        self.time().is_some()
+    }
+}
+
+/// A policy to re-anchor the TA persistent time on a time from an authenticated source, such
+/// as a timestamp signed by a trusted server, see [reanchor](ReanchorPolicy::reanchor).
+///
+/// The TA persistent time is set to the trusted time when it is not valid, or when it drifted
+/// from the trusted time by more than the tolerated drift. A valid TA persistent time which is
+/// ahead of the trusted time is only moved back when rollbacks are allowed, since an old
+/// trusted time replayed by an attacker would otherwise roll the clock back.
+///
+/// # Example
+///
+/// ``` rust,no_run
+/// # use optee_utee::{ReanchorPolicy, Reanchored, Result, TaTime};
+/// # use core::time::Duration;
+/// # fn main() -> Result<()> {
+/// # let trusted = TaTime::ORIGIN;
+/// // `trusted` was verified against the signature of the time server.
+/// let policy = ReanchorPolicy::new().with_max_drift(Duration::from_secs(5));
+/// if let Reanchored::RollbackRefused(_) = policy.reanchor(trusted)? {
+///     // The trusted time is older than the TA time, reject it.
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct ReanchorPolicy {
+    max_drift: Duration,
+    allow_rollback: bool,
+}
+
+impl ReanchorPolicy {
+    /// Create a policy which tolerates no drift, and never moves a valid TA persistent time
+    /// back.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Tolerate a drift of up to `max_drift` between the TA persistent time and the trusted
+    /// time before setting the TA persistent time.
+    pub fn with_max_drift(mut self, max_drift: Duration) -> Self {
+        self.max_drift = max_drift;
+        self
+    }
+
+    /// Allow moving a valid TA persistent time back to the trusted time.
+    pub fn with_rollback(mut self, allow_rollback: bool) -> Self {
+        self.allow_rollback = allow_rollback;
+        self
+    }
+
+    /// Set the TA persistent time to `trusted` if the policy requires it.
+    ///
+    /// # Errors
+    ///
+    /// 1) `Overflow`: If the TA persistent time is set and the number of seconds of `trusted`
+    ///    overflows the range of a `u32`.
+    /// 2) `OutOfMemory`: If not enough memory is available to complete the operation.
+    /// 3) `StorageNoSpace`: If insufficient storage space is available to complete the operation.
+    ///
+    /// # Panics
+    ///
+    /// 1) If the Implementation detects any error.
+    pub fn reanchor(&self, trusted: TaTime) -> Result<Reanchored> {
+        let outcome = self.decide(TaTime::status()?, trusted);
+        if let Reanchored::Set(_) = outcome {
+            trusted.set()?;
+        }
+        Ok(outcome)
+    }
+
+    fn decide(&self, status: TaTimeStatus, trusted: TaTime) -> Reanchored {
+        let current = match status {
+            TaTimeStatus::Valid(current) => current,
+            _ => return Reanchored::Set(status),
+        };
+        match current.checked_duration_since(trusted) {
+            Some(ahead) if ahead <= self.max_drift => Reanchored::Kept,
+            Some(_) if !self.allow_rollback => Reanchored::RollbackRefused(current),
+            Some(_) => Reanchored::Set(status),
+            None if trusted - current <= self.max_drift => Reanchored::Kept,
+            None => Reanchored::Set(status),
+        }
+    }
+}
+
+/// The outcome of [reanchor](ReanchorPolicy::reanchor).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Reanchored {
+    /// The TA persistent time was kept, being within the tolerated drift of the trusted time.
+    Kept,
+    /// The TA persistent time was set to the trusted time, and holds its previous status.
+    Set(TaTimeStatus),
+    /// The TA persistent time was kept, being ahead of the trusted time by more than the
+    /// tolerated drift while rollbacks are not allowed, and holds the TA persistent time.
+    RollbackRefused(TaTime),
+}
+
+/// A measurement of the REE time, see [ree_time](Time::ree_time).
+///
+/// The REE time counts from the Unix epoch, and is as trusted as the REE itself, so it may
+/// be tampered with by the user. It is a distinct type from [TaTime] so the two clocks cannot
+/// be mixed up.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ReeTime(Duration);
+
+impl ReeTime {
+    /// The Unix epoch, 1970-01-01 00:00:00 UTC.
+    pub const UNIX_EPOCH: ReeTime = ReeTime(Duration::ZERO);
+
+    /// Returns the current REE time.
+    ///
+    /// # Panics
+    ///
+    /// 1) If the Implementation detects any error.
+    pub fn now() -> Self {
+        let mut time = raw::TEE_Time {
+            seconds: 0,
+            millis: 0,
+        };
+        unsafe {
+            raw::TEE_GetREETime(&mut time);
+        }
+        ReeTime(from_raw_time(&time))
+    }
+}
+
+impl_time_arithmetic!(ReeTime);
+
+/// A point of the system time by which an operation should complete, or no deadline at all.
+///
+/// # Example
+///
+/// ``` rust,no_run
+/// # use optee_utee::{Deadline, Result};
+/// # use core::time::Duration;
+/// # fn main() -> Result<()> {
+/// let deadline = Deadline::after(Duration::from_secs(5));
+/// while !deadline.has_passed() {
+///     // ...
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Deadline(Option<Instant>);
+
+impl Deadline {
+    /// Returns a deadline at `instant`.
+    pub fn at(instant: Instant) -> Self {
+        Deadline(Some(instant))
+    }
+
+    /// Returns a deadline `timeout` from now, or no deadline if it cannot be represented.
+    ///
+    /// # Panics
+    ///
+    /// 1) If the Implementation detects any error.
+    pub fn after(timeout: Duration) -> Self {
+        Deadline(Instant::now().checked_add(timeout))
+    }
+
+    /// Returns no deadline, which never passes.
+    pub fn never() -> Self {
+        Deadline(None)
+    }
+
+    /// Returns the instant of the deadline, or `None` if there is no deadline.
+    pub fn instant(&self) -> Option<Instant> {
+        self.0
+    }
+
+    /// Returns the time left until the deadline, zero if it has passed, or `None` if there is
+    /// no deadline.
+    ///
+    /// # Panics
+    ///
+    /// 1) If the Implementation detects any error.
+    pub fn remaining(&self) -> Option<Duration> {
+        self.0.map(|instant| instant - Instant::now())
+    }
+
+    /// Returns whether the deadline has passed.
+    ///
+    /// # Panics
+    ///
+    /// 1) If the Implementation detects any error.
+    pub fn has_passed(&self) -> bool {
+        self.remaining() == Some(Duration::ZERO)
+    }
+
+    /// Wait until the deadline has passed, or forever if there is no deadline.
+    ///
+    /// # Errors
+    ///
+    /// 1) `Cancel`: If the wait has been cancelled.
+    ///
+    /// # Panics
+    ///
+    /// 1) If the Implementation detects any error.
+    pub fn wait(&self) -> Result<()> {
+        match self.remaining() {
+            Some(remaining) => Time::sleep(remaining),
+            None => Time::wait(raw::TEE_TIMEOUT_INFINITE),
+        }
+    }
+}
+
+impl From<Instant> for Deadline {
+    fn from(instant: Instant) -> Self {
+        Deadline::at(instant)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn test_wait_steps() {
+        let steps = |duration| wait_steps(duration).collect::<Vec<_>>();
+        assert!(steps(Duration::ZERO).is_empty());
+        assert_eq!(steps(Duration::from_nanos(1)), [1]);
+        assert_eq!(steps(Duration::from_micros(1500)), [2]);
+        assert_eq!(steps(Duration::from_secs(2)), [2000]);
+        assert_eq!(
+            steps(Duration::from_millis(u32::MAX as u64)),
+            [u32::MAX - 1, 1]
+        );
+        assert_eq!(
+            steps(Duration::from_millis(2 * (u32::MAX as u64 - 1))),
+            [u32::MAX - 1, u32::MAX - 1]
+        );
+    }
+
+    #[test]
+    fn test_raw_time() {
+        let time = raw::TEE_Time {
+            seconds: 12,
+            millis: 345,
+        };
+        assert_eq!(from_raw_time(&time), Duration::from_millis(12_345));
+
+        let time = to_raw_time(Duration::from_micros(12_345_678)).unwrap();
+        assert_eq!((time.seconds, time.millis), (12, 345));
+
+        match to_raw_time(Duration::from_secs(u32::MAX as u64 + 1)) {
+            Err(err) => assert_eq!(err.kind(), ErrorKind::Overflow),
+            Ok(_) => panic!("it should be err"),
+        }
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        let start = TaTime::ORIGIN + Duration::from_secs(10);
+        let mut end = start;
+        end += Duration::from_millis(1500);
+
+        assert_eq!(end - start, Duration::from_millis(1500));
+        assert_eq!(start - end, Duration::ZERO);
+        assert_eq!(
+            end.checked_duration_since(start),
+            Some(Duration::from_millis(1500))
+        );
+        assert_eq!(start.checked_duration_since(end), None);
+        assert_eq!(end - Duration::from_millis(1500), start);
+        assert_eq!(TaTime::ORIGIN.checked_sub(Duration::from_nanos(1)), None);
+        assert_eq!(
+            TaTime::ORIGIN.checked_add(Duration::MAX),
+            Some(TaTime(Duration::MAX))
+        );
+        assert_eq!(
+            TaTime(Duration::MAX).checked_add(Duration::from_nanos(1)),
+            None
+        );
+
+        end -= Duration::from_secs(11);
+        assert_eq!(
+            end.checked_duration_since(TaTime::ORIGIN),
+            Some(Duration::from_millis(500))
+        );
+        assert!(end < start);
+    }
+
+    #[test]
+    #[should_panic(expected = "overflow when subtracting duration from time")]
+    fn test_arithmetic_overflow() {
+        let _ = ReeTime::UNIX_EPOCH - Duration::from_secs(1);
+    }
+
+    #[test]
+    fn test_ta_time_status() {
+        let time = raw::TEE_Time {
+            seconds: 7,
+            millis: 0,
+        };
+        let seven = TaTime::ORIGIN + Duration::from_secs(7);
+        let status = |code| TaTimeStatus::from_raw(code, &time).ok();
+
+        assert_eq!(status(raw::TEE_SUCCESS), Some(TaTimeStatus::Valid(seven)));
+        assert_eq!(
+            status(raw::TEE_ERROR_TIME_NOT_SET),
+            Some(TaTimeStatus::NotSet)
+        );
+        assert_eq!(
+            status(raw::TEE_ERROR_TIME_NEEDS_RESET),
+            Some(TaTimeStatus::NeedsReset)
+        );
+        assert_eq!(
+            status(raw::TEE_ERROR_OVERFLOW),
+            Some(TaTimeStatus::Overflow(seven))
+        );
+        assert_eq!(status(raw::TEE_ERROR_GENERIC), None);
+
+        assert_eq!(TaTimeStatus::Valid(seven).time(), Some(seven));
+        assert!(!TaTimeStatus::Overflow(seven).is_valid());
+    }
+
+    #[test]
+    fn test_reanchor_policy() {
+        let at = |seconds| TaTime::ORIGIN + Duration::from_secs(seconds);
+        let valid = |seconds| TaTimeStatus::Valid(at(seconds));
+        let policy = ReanchorPolicy::new().with_max_drift(Duration::from_secs(5));
+
+        for status in [
+            TaTimeStatus::NotSet,
+            TaTimeStatus::NeedsReset,
+            TaTimeStatus::Overflow(at(100)),
+        ] {
+            assert_eq!(policy.decide(status, at(100)), Reanchored::Set(status));
+        }
+        assert_eq!(policy.decide(valid(100), at(100)), Reanchored::Kept);
+        assert_eq!(policy.decide(valid(95), at(100)), Reanchored::Kept);
+        assert_eq!(policy.decide(valid(105), at(100)), Reanchored::Kept);
+        assert_eq!(
+            policy.decide(valid(94), at(100)),
+            Reanchored::Set(valid(94))
+        );
+        assert_eq!(
+            policy.decide(valid(106), at(100)),
+            Reanchored::RollbackRefused(at(106))
+        );
+        assert_eq!(
+            policy.with_rollback(true).decide(valid(106), at(100)),
+            Reanchored::Set(valid(106))
+        );
+        assert_eq!(
+            ReanchorPolicy::new().decide(valid(101), at(100)),
+            Reanchored::RollbackRefused(at(101))
+        );
+    }
+
+    #[test]
+    fn test_deadline() {
+        let instant = Instant(Duration::from_secs(1));
+        assert_eq!(Deadline::at(instant).instant(), Some(instant));
+        assert_eq!(Deadline::from(instant), Deadline::at(instant));
+        assert_eq!(Deadline::never().instant(), None);
+    }
+}
@@ -0,0 +1,145 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Recovery from the panics of the entry points of std Trusted Applications.
+//!
+//! By default, a panic in an entry point kills the TA instance, and all its sessions then fail
+//! with `TargetDead`. With the `catch_unwind` argument, the entry-point macros catch the
+//! panics of the function instead, e.g.:
+//!
+//! ``` rust,no_run
+//! # use core::ffi::c_void;
+//! # use optee_utee::{ta_invoke_command, Parameters, Result};
+//! # #[derive(Default)]
+//! # struct Session;
+//! // A panic returns TEE_ERROR_GENERIC.
+//! #[ta_invoke_command(catch_unwind)]
+//! fn invoke_command(session: &mut Session, cmd_id: u32, params: &mut Parameters) -> Result<()> {
+//!     # Ok(())
+//! }
+//! ```
+//!
+//! or `#[ta_invoke_command(catch_unwind = 0xFFFF0042)]` to return another code, which must be a
+//! GlobalPlatform error code or one of [`APP_ERROR_CODES`](crate::APP_ERROR_CODES): any other
+//! code, such as `TEE_SUCCESS`, is rejected at compile time.
+//!
+//! The panic is printed to the trace output, and the entry point returns the code. When the
+//! panic happened while invoking a command of a session with a context, the context may have
+//! been left inconsistent, so the session is poisoned: the next commands of the session fail
+//! with `TEE_ERROR_BAD_STATE` without calling the function, until the session is closed.
+
+use crate::trace_println;
+use core::any::Any;
+use core::ffi::c_void;
+use std::collections::BTreeSet;
+use std::panic::{self, AssertUnwindSafe};
+use std::string::String;
+use std::sync::Mutex;
+
+static POISONED: Mutex<BTreeSet<usize>> = Mutex::new(BTreeSet::new());
+
+/// Call `f`, and return `None` if it panics, after printing the panic to the trace output.
+/// Used by the entry-point macros.
+pub fn catch<R>(f: impl FnOnce() -> R) -> Option<R> {
+    match panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(result) => Some(result),
+        Err(payload) => {
+            trace_println!("[!] TA entry point panicked: {}", message(&*payload));
+            None
+        }
+    }
+}
+
+/// Poison the session whose context is `sess_ctx`.
+pub fn poison(sess_ctx: *mut c_void) {
+    poisoned().insert(sess_ctx as usize);
+}
+
+/// Returns whether the session whose context is `sess_ctx` is poisoned.
+pub fn is_poisoned(sess_ctx: *mut c_void) -> bool {
+    poisoned().contains(&(sess_ctx as usize))
+}
+
+/// Forget the poisoning of the session whose context is `sess_ctx`, when it is closed by the
+/// `#[ta_close_session]` entry point, whether or not it catches the panics.
+pub fn clear(sess_ctx: *mut c_void) {
+    poisoned().remove(&(sess_ctx as usize));
+}
+
+// The set is only modified by the functions above, which cannot panic while
+// holding the lock, so a poisoned lock still holds a consistent set.
+fn poisoned() -> std::sync::MutexGuard<'static, BTreeSet<usize>> {
+    POISONED.lock().unwrap_or_else(|err| err.into_inner())
+}
+
+fn message(payload: &(dyn Any + Send)) -> &str {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message
+    } else {
+        "Box<dyn Any>"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::boxed::Box;
+    use std::string::ToString;
+
+    #[test]
+    fn test_message() {
+        let payload: Box<dyn Any + Send> = Box::new("static message");
+        assert_eq!(message(&*payload), "static message");
+        let payload: Box<dyn Any + Send> = Box::new("formatted 42".to_string());
+        assert_eq!(message(&*payload), "formatted 42");
+        let payload: Box<dyn Any + Send> = Box::new(42);
+        assert_eq!(message(&*payload), "Box<dyn Any>");
+    }
+
+    #[test]
+    fn test_poison() {
+        let mut ctx = 0u8;
+        let sess_ctx = &mut ctx as *mut u8 as *mut c_void;
+
+        assert!(!is_poisoned(sess_ctx));
+        poison(sess_ctx);
+        assert!(is_poisoned(sess_ctx));
+        clear(sess_ctx);
+        assert!(!is_poisoned(sess_ctx));
+    }
+
+    #[test]
+    fn test_close_session() {
+        use crate as optee_utee;
+
+        struct Session(u64);
+
+        #[crate::ta_close_session]
+        fn close_session(session: &mut Session) {
+            session.0 += 1;
+        }
+
+        // a plain close unpoisons the session, so that the next one which
+        // gets the same context address is not refused
+        let sess_ctx = Box::into_raw(Box::new(Session(0))) as *mut c_void;
+        poison(sess_ctx);
+        unsafe { TA_CloseSessionEntryPoint(sess_ctx) };
+        assert!(!is_poisoned(sess_ctx));
+    }
+}